@@ -2,6 +2,7 @@ use oort_simulation_worker::SimAgent;
 use oort_simulator::snapshot::{Snapshot, Timing};
 use rand::Rng;
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
@@ -22,6 +23,9 @@ pub struct Benchmark {
     sim_agent: Box<dyn Bridge<SimAgent>>,
     time: f64,
     cumulative_timing: Timing,
+    // Instructions per team summed over the sampled ticks.
+    cumulative_gas: BTreeMap<i32, u64>,
+    num_gas_samples: usize,
     num_slow_ticks: usize,
     slowest_snapshot: Option<Snapshot>,
     hash: Option<String>,
@@ -58,6 +62,8 @@ impl Component for Benchmark {
             sim_agent,
             time: 0.0,
             cumulative_timing: Timing::default(),
+            cumulative_gas: BTreeMap::new(),
+            num_gas_samples: 0,
             num_slow_ticks: 0,
             slowest_snapshot: None,
             hash: None,
@@ -80,6 +86,10 @@ impl Component for Benchmark {
                     {
                         self.slowest_snapshot = Some(snapshot.clone());
                     }
+                    for (team, gas) in team_gas(&snapshot) {
+                        *self.cumulative_gas.entry(team).or_default() += gas;
+                    }
+                    self.num_gas_samples += 1;
                     self.cumulative_timing += snapshot.timing;
                     self.sim_agent
                         .send(oort_simulation_worker::Request::Snapshot {
@@ -91,6 +101,7 @@ impl Component for Benchmark {
                     if self.hash.is_none() {
                         let mut snapshot = snapshot;
                         snapshot.timing = Timing::default();
+                        snapshot.gas_used.clear();
                         let bytes = bincode::serialize(&snapshot).unwrap();
                         let mut hasher = Sha256::new();
                         hasher.update(&bytes);
@@ -99,6 +110,9 @@ impl Component for Benchmark {
                     true
                 }
             }
+            Msg::ReceivedSimAgentResponse(oort_simulation_worker::Response::EncodedSnapshot {
+                ..
+            }) => false,
         }
     }
 
@@ -111,6 +125,7 @@ impl Component for Benchmark {
                     { timing_view(&snapshot.timing, 1) }
                     <p>{ format!("Ships: {}", snapshot.ships.len()) }</p>
                     <p>{ format!("Bullets: {}", snapshot.bullets.len()) }</p>
+                    { gas_view(&team_gas(snapshot), 1) }
                 </div>
             }
         } else {
@@ -122,6 +137,7 @@ impl Component for Benchmark {
                 <p><b>{ "Cumulative:" }</b></p>
                 <p>{ format!("Simulated time: {:.1}s", self.time) }</p>
                 { timing_view(&self.cumulative_timing, BATCH_SIZE) }
+                { gas_view(&self.cumulative_gas, self.num_gas_samples) }
                 <p>{ format!("Slow ticks: {}", self.num_slow_ticks) }</p>
                 <p>{ format!("Hash: {:?}", self.hash) }</p>
                 { slowest_snapshot }
@@ -147,3 +163,25 @@ fn timing_view(timing: &Timing, batch_size: usize) -> Html {
         </>
     }
 }
+
+// Sums the instructions executed by each team's ships in the snapshot's tick.
+fn team_gas(snapshot: &Snapshot) -> BTreeMap<i32, u64> {
+    let mut result = BTreeMap::new();
+    for ship in snapshot.ships.iter() {
+        if let Some(&gas) = snapshot.gas_used.get(&ship.id) {
+            *result.entry(ship.team).or_default() += gas as u64;
+        }
+    }
+    result
+}
+
+fn gas_view(gas: &BTreeMap<i32, u64>, num_samples: usize) -> Html {
+    let n = num_samples.max(1) as u64;
+    html! {
+        <>
+            { for gas.iter().map(|(team, gas)| html! {
+                <p>{ format!("Team {} instructions per tick: {}", team, gas / n) }</p>
+            }) }
+        </>
+    }
+}
@@ -26,6 +26,8 @@ pub fn documentation(props: &DocumentationProps) -> Html {
                 <li>{ "W/A/S/D: Pan the camera." }</li>
                 <li>{ "Space: Pause/resume." }</li>
                 <li>{ "N: Single-step (advance time by one tick and then pause)." }</li>
+                <li>{ "P: Step backwards by one tick and pause." }</li>
+                <li>{ "R: Rewind five seconds." }</li>
                 <li>{ "F: Fast-forward." }</li>
                 <li>{ "M: Slow motion." }</li>
                 <li>{ "G: Show debug lines for all ships." }</li>
@@ -104,11 +106,23 @@ pub fn documentation(props: &DocumentationProps) -> Html {
               <li><code>{ "radar_min_distance() -> f64" }</code>{ ": Get current minimum distance filter." }</li>
               <li><code>{ "set_radar_max_distance(dist: f64)" }</code>{ ": Set the maximum distance filter." }</li>
               <li><code>{ "radar_max_distance() -> f64" }</code>{ ": Get current maximum distance filter." }</li>
+              <li><code>{ "set_radar_mode(mode: RadarMode)" }</code>{ ": Frigate and cruiser only. Set the radar mode." }</li>
+              <li><code>{ "RadarMode::Single" }</code>{ ": Return the single strongest contact." }</li>
+              <li><code>{ "RadarMode::TrackWhileScan" }</code>{ ": Return up to 4 (frigate) or 8 (cruiser) contacts per tick with reduced range and accuracy." }</li>
+              <li><code>{ "scan_all() → Vec<ScanResult>" }</code>{ ": Get all radar contacts, strongest first." }</li>
               <li><code>{ "set_radar_ecm_mode(mode: EcmMode)" }</code>{ ": Set the Electronic Counter Measures (ECM) mode." }</li>
               <li><code>{ "EcmMode::None" }</code>{ ": No ECM, radar will operate normally." }</li>
               <li><code>{ "EcmMode::Noise" }</code>{ ": Decrease the enemy radar's signal to noise ratio, making it more difficult to detect targets and reducing accuracy of returned contacts." }</li>
             </ul>
 
+            <h2>{ "Electronic Support Measures" }</h2>
+            <ul>
+              <li><code>{ "esm_contacts() → Vec<EsmContact>" }</code>{ ": Get the enemy radars illuminating your ship, strongest first. ESM is passive and doesn't reveal your position." }</li>
+              <li><code>{ "struct EsmContact { bearing: f64, rssi: f64 }" }</code></li>
+              <li><code>{ "set_radar_enabled(enabled: bool)" }</code>{ ": Turn the radar on or off. A disabled radar returns no contacts and can't be detected by enemy ESM." }</li>
+              <li><code>{ "radar_enabled() -> bool" }</code>{ ": Get whether the radar is on." }</li>
+            </ul>
+
             <h2>{ "Radio" }</h2>
             <ul>
               <li><code>{ "set_radio_channel(channel: usize)" }</code>{ ": Change the radio channel (0 to 9). Takes effect next tick." }</li>
@@ -118,6 +132,7 @@ pub fn documentation(props: &DocumentationProps) -> Html {
               <li><code>{ "send_bytes(data: &[u8])" }</code>{ ": Send a message on a channel as bytes, the data will be zero-filled or truncated to a length of 32 bytes." }</li>
               <li><code>{ "receive_bytes() -> Option<[u8; 32]>" }</code>{ ": Just like receive, but instead the message will be returned as a byte array." }</li>
               <li><code>{ "select_radio(index: usize)" }</code>{ ": Select the radio to control with subsequent API calls. Frigates have 4 radios and cruisers have 8." }</li>
+              <li><code>{ "set_radio_ecm_mode(mode: EcmMode)" }</code>{ ": Jam the radio channel, drowning out messages for every ship near the jammer. A jamming radio can't send or receive." }</li>
             </ul>
 
             <h2>{ "Special Abilities" }</h2>
@@ -128,6 +143,7 @@ pub fn documentation(props: &DocumentationProps) -> Html {
               <li>{ "Available abilities:" }
                 <ul>
                   <li><code>{ "Ability::Boost" }</code>{ ": Fighter and missile only. Applies a 100 m/s² forward acceleration for 2s. Reloads in 10s." }</li>
+                  <li><code>{ "Ability::ShapedCharge" }</code>{ ": Missile and torpedo only. For 1s, the warhead detonates as a narrow cone of heavier fragments aimed along the velocity relative to the radar contact. Reloads in 10s." }</li>
                   <li><code>{ "Ability::Decoy" }</code>{ ": Torpedo only. Mimics the radar signature of a Cruiser for 0.5s. Reloads in 10s." }</li>
                   <li><code>{ "Ability::Shield" }</code>{ ": Cruiser only. Deflects damage for 1s. Reloads in 5s." }</li>
                 </ul>
@@ -179,6 +195,7 @@ pub fn documentation(props: &DocumentationProps) -> Html {
             <ul>
               <li><code>{ "current_tick() → u32" }</code>{ ": Returns the number of ticks elapsed since the simulation started." }</li>
               <li><code>{ "current_time() → f64" }</code>{ ": Returns the number of seconds elapsed since the simulation started." }</li>
+              <li><code>{ "remaining_instructions() → u32" }</code>{ ": Returns the number of instructions the ship can still execute this tick." }</li>
               <li><code>{ "angle_diff(a: f64, b: f64) → f64" }</code>{ ": Returns the shortest (possibly negative) distance between two angles." }</li>
               <li><code>{ "rand(low: f64, high: f64) → f64" }</code>{ ": Get a random number." }</li>
               <li><code>{ "target() → Vec2" }</code>{ ": Used in some scenarios, returns the position of the target." }</li>
@@ -15,6 +15,7 @@ use crate::userid;
 use crate::versions_window::VersionsWindow;
 use crate::welcome::Welcome;
 use monaco::yew::CodeEditorLink;
+use oort_proto::compiler::CompilerDiagnostics;
 use oort_proto::{LeaderboardSubmission, Telemetry};
 use oort_simulation_worker::SimAgent;
 use oort_simulator::scenario::{self, Status, MAX_TICKS};
@@ -52,7 +53,7 @@ pub enum Msg {
     EditorAction { team: usize, action: String },
     ShowFeedback,
     DismissOverlay,
-    CompileFinished(Vec<Result<Code, String>>, ExecutionMode),
+    CompileFinished(Vec<Result<Code, CompilerDiagnostics>>, ExecutionMode),
     SubmitToTournament,
     UploadShortcode,
     FormattedCode { team: usize, text: String },
@@ -329,6 +330,11 @@ impl Component for Game {
                     false
                 }
             }
+            // Background simulations only request plain snapshots.
+            Msg::ReceivedBackgroundSimAgentResponse(
+                oort_simulation_worker::Response::EncodedSnapshot { .. },
+                _,
+            ) => false,
             Msg::ShowFeedback => {
                 self.overlay = Some(Overlay::Feedback);
                 true
@@ -372,7 +378,7 @@ impl Component for Game {
                 let errors: Vec<_> = results
                     .iter()
                     .filter_map(|x| x.as_ref().err())
-                    .cloned()
+                    .map(|x| x.to_string())
                     .collect();
                 if errors.is_empty() {
                     services::send_telemetry(Telemetry::StartScenario {
@@ -859,6 +865,22 @@ impl Game {
 
         let next_scenario = scenario::load(&context.props().scenario).next_scenario();
 
+        let match_stats = match self.last_snapshot.as_ref().and_then(|x| x.stats.as_ref()) {
+            Some(stats) => {
+                let team = stats.team(0);
+                html! {
+                    <>
+                        { "Accuracy: " }{ format!("{:.1}%", team.hit_rate() * 100.0) }
+                        { format!(" ({}/{} shots)", team.hits, team.shots_fired) }<br/>
+                        { "Missiles: " }{ format!("{}/{} hit", team.missile_hits, team.missiles_launched) }<br/>
+                        { "Damage: " }{ format!("{:.0} dealt, {:.0} received", team.damage_dealt, team.damage_received) }<br/>
+                        { "Ships lost: " }{ team.ships_lost }<br/>
+                    </>
+                }
+            }
+            None => html! {},
+        };
+
         let make_seed_link_cb = |seed: u32| {
             let link = context.link().clone();
             let navigator = context.link().navigator().unwrap();
@@ -1025,7 +1047,8 @@ impl Game {
             <div class="centered">
                 <h1>{ "Mission Complete" }</h1>
                 { "Time: " }{ format!("{score_time:.3}") }{ " seconds" }<br/>
-                { "Code size: " }{ code_size }{ " bytes" }<br/><br/>
+                { "Code size: " }{ code_size }{ " bytes" }<br/>
+                { match_stats }<br/>
                 { background_status }<br/><br/>
                 <br/><br/>
             </div>
@@ -1040,7 +1063,7 @@ impl Game {
             .link()
             .callback(move |results| Msg::CompileFinished(results, execution_mode));
 
-        async fn compile(text: String) -> Result<Code, String> {
+        async fn compile(text: String) -> Result<Code, CompilerDiagnostics> {
             if text.trim().is_empty() {
                 return Ok(Code::None);
             }
@@ -1051,20 +1074,21 @@ impl Game {
             let result = Request::post(&url).body(text).send().await;
             if let Err(e) = result {
                 log::error!("Compile error: {}", e);
-                return Err(e.to_string());
+                return Err(CompilerDiagnostics::from_message(&e.to_string()));
             }
 
             let response = result.unwrap();
             if !response.ok() {
                 let error = response.text().await.unwrap();
                 log::error!("Compile error: {}", error);
-                return Err(error);
+                return Err(serde_json::from_str(&error)
+                    .unwrap_or_else(|_| CompilerDiagnostics::from_message(&error)));
             }
 
             let wasm = response.binary().await;
             if let Err(e) = wasm {
                 log::error!("Compile error: {}", e);
-                return Err(e.to_string());
+                return Err(CompilerDiagnostics::from_message(&e.to_string()));
             }
 
             let elapsed = instant::Instant::now() - start_time;
@@ -1097,7 +1121,8 @@ impl Game {
             for source_code in source_codes {
                 let result = match source_code {
                     Code::Rust(text) => compile(text).await,
-                    Code::Builtin(name) => oort_simulator::vm::builtin::load_compiled(&name),
+                    Code::Builtin(name) => oort_simulator::vm::builtin::load_compiled(&name)
+                        .map_err(|e| CompilerDiagnostics::from_message(&e)),
                     other => Ok(other),
                 };
                 results.push(result);
@@ -1325,10 +1350,16 @@ impl Team {
             .map(|error| {
                 let decoration: IModelDeltaDecoration = empty().into();
                 decoration.set_range(
-                    &Range::new(error.line as f64, 1.0, error.line as f64, 1.0).unchecked_into(),
+                    &Range::new(
+                        error.line as f64,
+                        error.column as f64,
+                        error.end_line as f64,
+                        error.end_column as f64,
+                    )
+                    .unchecked_into(),
                 );
                 let options: IModelDecorationOptions = empty().into();
-                options.set_is_whole_line(Some(true));
+                options.set_is_whole_line(Some(false));
                 options.set_class_name("errorDecoration".into());
                 let hover_message: IMarkdownString = empty().into();
                 js_sys::Reflect::set(
@@ -1380,15 +1411,28 @@ pub fn str_to_code(s: &str) -> Code {
 #[derive(Debug, Clone)]
 pub struct CompilerError {
     pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
     pub msg: String,
 }
 
-fn make_editor_errors(error: &str) -> Vec<CompilerError> {
-    let re = Regex::new(r"(?m)error.*?: (.*?)$\n.*?ai/src/user.rs:(\d+):").unwrap();
-    re.captures_iter(error)
-        .map(|m| CompilerError {
-            line: m[2].parse().unwrap(),
-            msg: m[1].to_string(),
+fn make_editor_errors(diagnostics: &CompilerDiagnostics) -> Vec<CompilerError> {
+    diagnostics
+        .errors()
+        .filter_map(|d| {
+            let span = d.primary_span()?;
+            let msg = match &span.label {
+                Some(label) if !label.is_empty() => format!("{}: {}", d.message, label),
+                _ => d.message.clone(),
+            };
+            Some(CompilerError {
+                line: span.start_line as usize,
+                column: span.start_column as usize,
+                end_line: span.end_line as usize,
+                end_column: span.end_column as usize,
+                msg,
+            })
         })
         .collect()
 }
@@ -1,6 +1,7 @@
 use crate::ui::UI;
 use gloo_render::{request_animation_frame, AnimationFrame};
 use oort_simulation_worker::SimAgent;
+use oort_simulator::snapshot_codec::SnapshotDecoder;
 use oort_simulator::{scenario, simulation::Code, snapshot::Snapshot};
 use rand::Rng;
 use std::rc::Rc;
@@ -22,6 +23,7 @@ pub enum Msg {
     PointerEvent(web_sys::PointerEvent),
     BlurEvent(web_sys::FocusEvent),
     RequestSnapshot,
+    Seek(u32),
     ReceivedSimAgentResponse(oort_simulation_worker::Response),
 }
 
@@ -39,6 +41,7 @@ pub struct SimulationWindow {
     render_handle: Option<AnimationFrame>,
     nonce: u32,
     sim_agent: Box<dyn Bridge<SimAgent>>,
+    decoder: SnapshotDecoder,
     last_status: scenario::Status,
     canvas_ref: NodeRef,
     status_ref: NodeRef,
@@ -67,6 +70,7 @@ impl Component for SimulationWindow {
             render_handle,
             nonce: 0,
             sim_agent,
+            decoder: SnapshotDecoder::new(),
             last_status: scenario::Status::Running,
             canvas_ref: context.props().canvas_ref.clone(),
             status_ref: NodeRef::default(),
@@ -83,8 +87,10 @@ impl Component for SimulationWindow {
                 codes,
             } => {
                 self.nonce = rand::thread_rng().gen();
+                self.decoder = SnapshotDecoder::new();
                 self.ui = Some(Box::new(UI::new(
                     context.link().callback(|_| Msg::RequestSnapshot),
+                    context.link().callback(Msg::Seek),
                     seed,
                     self.nonce,
                     context.props().version.clone(),
@@ -110,12 +116,23 @@ impl Component for SimulationWindow {
             }
             Msg::RequestSnapshot => {
                 self.sim_agent
-                    .send(oort_simulation_worker::Request::Snapshot {
+                    .send(oort_simulation_worker::Request::EncodedSnapshot {
                         ticks: 1,
                         nonce: self.nonce,
                     });
                 false
             }
+            Msg::Seek(tick) => {
+                self.nonce = rand::thread_rng().gen();
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.on_seek(self.nonce);
+                }
+                self.sim_agent.send(oort_simulation_worker::Request::Seek {
+                    tick,
+                    nonce: self.nonce,
+                });
+                false
+            }
             Msg::KeyEvent(e) => {
                 if let Some(ui) = self.ui.as_mut() {
                     ui.on_key_event(e);
@@ -148,6 +165,20 @@ impl Component for SimulationWindow {
                 }
                 false
             }
+            Msg::ReceivedSimAgentResponse(oort_simulation_worker::Response::EncodedSnapshot {
+                data,
+            }) => {
+                match self.decoder.decode(&data) {
+                    Ok(snapshot) => {
+                        if let Some(ui) = self.ui.as_mut() {
+                            ui.on_snapshot(snapshot);
+                        }
+                    }
+                    // Expected for frames from a previous simulation.
+                    Err(e) => log::debug!("Dropping snapshot: {}", e),
+                }
+                false
+            }
         };
 
         if let Some(ui) = self.ui.as_ref() {
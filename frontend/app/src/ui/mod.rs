@@ -20,6 +20,7 @@ const MAX_ZOOM: f32 = 5e-3;
 const INITIAL_ZOOM: f32 = 1e-3;
 const SNAPSHOT_PRELOAD: usize = 5;
 const MAX_SNAPSHOT_REQUESTS_IN_FLIGHT: usize = 10;
+const REWIND_TICKS: u32 = 300;
 
 pub struct UI {
     version: String,
@@ -48,6 +49,8 @@ pub struct UI {
     snapshot_requests_in_flight: usize,
     nonce: u32,
     request_snapshot: yew::Callback<()>,
+    request_seek: yew::Callback<u32>,
+    seek_pending: bool,
     picked_ship_id: Option<u64>,
     chasing_ship_id: Option<u64>,
     status_ref: NodeRef,
@@ -63,6 +66,7 @@ impl UI {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         request_snapshot: yew::Callback<()>,
+        request_seek: yew::Callback<u32>,
         seed: u32,
         nonce: u32,
         version: String,
@@ -121,6 +125,8 @@ impl UI {
             snapshot_requests_in_flight: 0,
             nonce,
             request_snapshot,
+            request_seek,
+            seek_pending: false,
             picked_ship_id: None,
             chasing_ship_id: None,
             status_ref,
@@ -179,6 +185,20 @@ impl UI {
             self.paused = true;
             self.single_steps += 1;
         }
+        if self.keys_down.contains("p") && !self.keys_ignored.contains("p") {
+            self.keys_ignored.insert("p".to_string());
+            self.paused = true;
+            self.single_steps = 0;
+            if let Some(tick) = self.current_tick() {
+                self.seek(tick.saturating_sub(1));
+            }
+        }
+        if self.keys_down.contains("r") && !self.keys_ignored.contains("r") {
+            self.keys_ignored.insert("r".to_string());
+            if let Some(tick) = self.current_tick() {
+                self.seek(tick.saturating_sub(REWIND_TICKS));
+            }
+        }
         if self.keys_down.contains("g") && !self.keys_ignored.contains("g") {
             self.keys_ignored.insert("g".to_string());
             self.debug = !self.debug;
@@ -344,6 +364,17 @@ impl UI {
             return;
         }
 
+        if self.seek_pending {
+            self.seek_pending = false;
+            self.physics_time = Duration::from_secs_f64(snapshot.time);
+            self.status = snapshot.status;
+            self.renderer.update(&snapshot);
+            self.snapshot = Some(snapshot);
+            self.update_picked();
+            self.needs_render = true;
+            return;
+        }
+
         self.pending_snapshots.push_back(snapshot);
         if self.snapshot_requests_in_flight > 0 {
             self.snapshot_requests_in_flight -= 1;
@@ -352,6 +383,27 @@ impl UI {
         self.needs_render = true;
     }
 
+    fn current_tick(&self) -> Option<u32> {
+        self.snapshot
+            .as_ref()
+            .map(|snapshot| (snapshot.time / PHYSICS_TICK_LENGTH).round() as u32)
+    }
+
+    fn seek(&mut self, tick: u32) {
+        if !self.seek_pending {
+            self.request_seek.emit(tick);
+        }
+    }
+
+    // Called with the nonce used for the seek request. Snapshots already in
+    // flight are dropped.
+    pub fn on_seek(&mut self, nonce: u32) {
+        self.nonce = nonce;
+        self.seek_pending = true;
+        self.pending_snapshots.clear();
+        self.snapshot_requests_in_flight = 0;
+    }
+
     pub fn update_snapshot(&mut self) {
         while self.pending_snapshots.len() > SNAPSHOT_PRELOAD / 2
             && std::time::Duration::from_secs_f64(self.pending_snapshots[1].time)
@@ -2,6 +2,7 @@ use super::{buffer_arena, geometry, glutil};
 use glutil::VertexAttribBuilder;
 use nalgebra::{vector, Matrix4, Vector4};
 use oort_simulator::model;
+use oort_simulator::radar::ScanResult;
 use oort_simulator::ship::ShipClass;
 use oort_simulator::snapshot::{ShipSnapshot, Snapshot};
 use wasm_bindgen::prelude::*;
@@ -95,6 +96,12 @@ void main() {
             0 => vector![0.99, 0.98, 0.00, 1.00],
             1 => vector![0.99, 0.00, 0.98, 1.00],
             2 => vector![0.13, 0.50, 0.73, 1.00],
+            3 => vector![0.00, 0.90, 0.40, 1.00],
+            4 => vector![0.99, 0.45, 0.00, 1.00],
+            5 => vector![0.50, 0.30, 0.99, 1.00],
+            6 => vector![0.00, 0.85, 0.85, 1.00],
+            7 => vector![0.95, 0.20, 0.20, 1.00],
+            8 => vector![0.70, 0.85, 0.30, 1.00],
             9 => vector![0.40, 0.40, 0.40, 1.00],
             _ => vector![1.0, 1.0, 1.0, 1.0],
         }
@@ -110,12 +117,10 @@ void main() {
     ) -> DrawSet {
         let mut ships_by_class = std::collections::HashMap::<ShipClass, Vec<ShipSnapshot>>::new();
 
-        for ship in snapshot.ships.iter() {
+        let contacts = snapshot.contacts.iter().map(contact_ship);
+        for ship in snapshot.ships.iter().cloned().chain(contacts) {
             ships_by_class.entry(ship.class).or_insert_with(Vec::new);
-            ships_by_class
-                .get_mut(&ship.class)
-                .unwrap()
-                .push((*ship).clone());
+            ships_by_class.get_mut(&ship.class).unwrap().push(ship);
         }
 
         let mut draws = vec![];
@@ -150,7 +155,11 @@ void main() {
                 for ship in ships.iter() {
                     let p = ship.position.coords.cast::<f32>();
                     let shielded = ship.active_abilities.contains(&oort_api::Ability::Shield);
-                    let mut team_color = Self::team_color(ship.team);
+                    let mut team_color = if ship.class == ShipClass::Planet {
+                        vector![0.13, 0.50, 0.73, 1.00]
+                    } else {
+                        Self::team_color(ship.team)
+                    };
                     if nlips_draw {
                         team_color.w *= (nlips_scale / min_nlips_scale - 1.0)
                             .clamp(0.0, 1.0)
@@ -234,3 +243,20 @@ void main() {
         self.context.bind_vertex_array(None);
     }
 }
+
+// Radar contacts in team snapshots are drawn as ships of an unknown team.
+fn contact_ship(contact: &ScanResult) -> ShipSnapshot {
+    ShipSnapshot {
+        id: 0,
+        position: contact.position.into(),
+        velocity: contact.velocity,
+        acceleration: vector![0.0, 0.0],
+        heading: contact.velocity.y.atan2(contact.velocity.x),
+        angular_velocity: 0.0,
+        team: -1,
+        class: contact.class,
+        health: 0.0,
+        fuel: None,
+        active_abilities: vec![],
+    }
+}
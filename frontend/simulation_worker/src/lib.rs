@@ -1,7 +1,9 @@
+use oort_simulator::history::History;
 use oort_simulator::scenario::{Status, MAX_TICKS};
 use oort_simulator::simulation::Code;
 use oort_simulator::simulation::Simulation;
 use oort_simulator::snapshot::Snapshot;
+use oort_simulator::snapshot_codec::SnapshotEncoder;
 use serde::{Deserialize, Serialize};
 use yew_agent::{HandlerId, Private, WorkerLink};
 
@@ -17,17 +19,31 @@ pub enum Request {
         ticks: u32,
         nonce: u32,
     },
+    // Like Snapshot but the response is encoded with SnapshotEncoder.
+    EncodedSnapshot {
+        ticks: u32,
+        nonce: u32,
+    },
+    // Rewinds or fast-forwards to the given tick. Seeking backwards only works
+    // as far back as the oldest saved checkpoint.
+    Seek {
+        tick: u32,
+        nonce: u32,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Response {
     Snapshot { snapshot: Snapshot },
+    EncodedSnapshot { data: Vec<u8> },
 }
 
 pub struct SimAgent {
     link: WorkerLink<Self>,
     sim: Option<Box<Simulation>>,
     errored: bool,
+    encoder: SnapshotEncoder,
+    history: History,
 }
 
 impl yew_agent::Worker for SimAgent {
@@ -41,6 +57,8 @@ impl yew_agent::Worker for SimAgent {
             link,
             sim: None,
             errored: false,
+            encoder: SnapshotEncoder::new(),
+            history: History::default(),
         }
     }
 
@@ -55,6 +73,9 @@ impl yew_agent::Worker for SimAgent {
                 nonce,
             } => {
                 self.sim = Some(Simulation::new(&scenario_name, seed, &codes));
+                self.encoder.force_keyframe();
+                self.history.clear();
+                self.history.record(self.sim.as_ref().unwrap());
                 let snapshot = self.sim().snapshot(nonce);
                 self.errored = !snapshot.errors.is_empty();
                 self.link.respond(who, Response::Snapshot { snapshot });
@@ -63,13 +84,29 @@ impl yew_agent::Worker for SimAgent {
                 if self.errored {
                     return;
                 }
-                for _ in 0..ticks {
-                    if self.sim().status() == Status::Running && self.sim().tick() < MAX_TICKS {
-                        self.sim().step();
+                let snapshot = self.step(ticks, nonce);
+                self.link.respond(who, Response::Snapshot { snapshot });
+            }
+            Request::EncodedSnapshot { ticks, nonce } => {
+                if self.errored {
+                    return;
+                }
+                let snapshot = self.step(ticks, nonce);
+                let data = self.encoder.encode(snapshot);
+                self.link.respond(who, Response::EncodedSnapshot { data });
+            }
+            Request::Seek { tick, nonce } => {
+                if self.sim.is_none() {
+                    return;
+                }
+                if tick < self.sim().tick() {
+                    match self.history.seek(tick) {
+                        Ok(sim) => self.sim = Some(sim),
+                        Err(e) => log::warn!("Seek to tick {} failed: {}", tick, e),
                     }
                 }
-                let snapshot = self.sim().snapshot(nonce);
-                self.errored = !snapshot.errors.is_empty();
+                let ticks = tick.saturating_sub(self.sim().tick());
+                let snapshot = self.step(ticks, nonce);
                 self.link.respond(who, Response::Snapshot { snapshot });
             }
         };
@@ -84,4 +121,16 @@ impl SimAgent {
     fn sim(&mut self) -> &mut Simulation {
         self.sim.as_mut().unwrap()
     }
+
+    fn step(&mut self, ticks: u32, nonce: u32) -> Snapshot {
+        for _ in 0..ticks {
+            if self.sim().status() == Status::Running && self.sim().tick() < MAX_TICKS {
+                self.sim().step();
+                self.history.record(self.sim.as_ref().unwrap());
+            }
+        }
+        let snapshot = self.sim().snapshot(nonce);
+        self.errored = !snapshot.errors.is_empty();
+        snapshot
+    }
 }
@@ -1,11 +1,13 @@
 use axum::extract::State;
-use axum::Router;
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
 use bytes::Bytes;
 use clap::Parser as _;
 use http::{Method, StatusCode};
 use once_cell::sync::Lazy;
 use oort_compiler::Compiler;
 use oort_compiler_service::{error, Error};
+use oort_proto::compiler::CompilerDiagnostics;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
@@ -20,7 +22,7 @@ static SEMAPHORE: Lazy<tokio::sync::Semaphore> =
 async fn post_compile(
     State(compiler): State<Arc<Mutex<Compiler>>>,
     mut code: String,
-) -> Result<Bytes, Error> {
+) -> Result<Response, Error> {
     let permit = SEMAPHORE.try_acquire();
     if permit.is_err() {
         return Err(error(
@@ -42,12 +44,16 @@ async fn post_compile(
     match result {
         Ok(wasm) => {
             log::info!("Compile succeeded in {:?}", elapsed);
-            Ok(Bytes::copy_from_slice(&wasm))
+            Ok(Bytes::copy_from_slice(&wasm).into_response())
         }
         Err(e) => {
             log::info!("Compile failed in {:?}", elapsed);
             log::debug!("Compile failed: {}", e);
-            Err(error(StatusCode::BAD_REQUEST, e.to_string()))
+            let diagnostics = match e.downcast::<CompilerDiagnostics>() {
+                Ok(diagnostics) => diagnostics,
+                Err(e) => CompilerDiagnostics::from_message(&e.to_string()),
+            };
+            Ok((StatusCode::BAD_REQUEST, Json(diagnostics)).into_response())
         }
     }
 }
@@ -3,6 +3,9 @@
 
 use std::f64::consts::TAU;
 
+#[doc(hidden)]
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
 #[doc(hidden)]
 pub mod panic;
 mod vec;
@@ -147,6 +150,14 @@ pub enum SystemState {
 
     Id,
 
+    RadarMode,
+    RadarContactsLength,
+
+    RadarDisabled,
+    EsmContactsLength,
+
+    RadioEcmModes,
+
     Size,
     MaxSize = 128,
 }
@@ -312,8 +323,7 @@ pub enum Ability {
     None,
     /// Fighter and missile only. Applies a 100 m/s² forward acceleration for 2s. Reloads in 10s.
     Boost,
-    /// Deprecated
-    #[doc(hidden)]
+    /// Missile and torpedo only. For 1s, the warhead detonates as a narrow cone of heavier fragments aimed along the velocity relative to the radar contact. Reloads in 10s.
     ShapedCharge,
     /// Torpedo only. Mimics the radar signature of a Cruiser for 0.5s. Reloads in 10s.
     Decoy,
@@ -322,7 +332,12 @@ pub enum Ability {
 }
 
 /// Array of all ability types.
-pub const ABILITIES: &[Ability] = &[Ability::Boost, Ability::Decoy, Ability::Shield];
+pub const ABILITIES: &[Ability] = &[
+    Ability::Boost,
+    Ability::ShapedCharge,
+    Ability::Decoy,
+    Ability::Shield,
+];
 
 /// Electronic Counter Measures (ECM) modes.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -345,6 +360,44 @@ impl From<f64> for EcmMode {
     }
 }
 
+/// Radar modes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RadarMode {
+    /// Returns the single contact with the highest signal strength.
+    Single,
+    /// Frigate and cruiser only. Returns up to [`MAX_RADAR_CONTACTS`] contacts
+    /// per tick via [`scan_all`](prelude::scan_all), at the cost of reduced
+    /// range and noisier measurements.
+    TrackWhileScan,
+}
+
+impl From<f64> for RadarMode {
+    fn from(x: f64) -> Self {
+        match x as u32 {
+            0 => RadarMode::Single,
+            1 => RadarMode::TrackWhileScan,
+            _ => RadarMode::Single,
+        }
+    }
+}
+
+/// Maximum number of contacts returned by [`scan_all`](prelude::scan_all).
+pub const MAX_RADAR_CONTACTS: usize = 8;
+
+/// Number of f64 values stored for each contact in the track-while-scan
+/// buffer: class, position, velocity, rssi and snr.
+#[doc(hidden)]
+pub const RADAR_CONTACT_SIZE: usize = 7;
+
+/// Maximum number of contacts returned by [`esm_contacts`](prelude::esm_contacts).
+pub const MAX_ESM_CONTACTS: usize = 8;
+
+/// Number of f64 values stored for each contact in the ESM buffer: bearing
+/// and rssi.
+#[doc(hidden)]
+pub const ESM_CONTACT_SIZE: usize = 2;
+
 #[doc(hidden)]
 #[derive(Default, Clone)]
 pub struct Line {
@@ -372,7 +425,8 @@ pub type Message = [f64; 4];
 // Public for fuzzer.
 #[doc(hidden)]
 pub mod sys {
-    use crate::MAX_ENVIRONMENT_SIZE;
+    use crate::{ESM_CONTACT_SIZE, MAX_ENVIRONMENT_SIZE, MAX_ESM_CONTACTS};
+    use crate::{MAX_RADAR_CONTACTS, RADAR_CONTACT_SIZE};
 
     use super::SystemState;
 
@@ -412,6 +466,33 @@ pub mod sys {
         std::str::from_utf8(&environment[..n]).expect("Failed to convert environment to string")
     }
 
+    #[no_mangle]
+    pub static mut ESM_CONTACTS: [f64; MAX_ESM_CONTACTS * ESM_CONTACT_SIZE] =
+        [0.0; MAX_ESM_CONTACTS * ESM_CONTACT_SIZE];
+
+    #[no_mangle]
+    pub static mut RADAR_CONTACTS: [f64; MAX_RADAR_CONTACTS * RADAR_CONTACT_SIZE] =
+        [0.0; MAX_RADAR_CONTACTS * RADAR_CONTACT_SIZE];
+
+    // Calls to this import are replaced by the simulator with a read of the
+    // instruction counter.
+    #[cfg(target_arch = "wasm32")]
+    #[link(wasm_import_module = "oort")]
+    extern "C" {
+        #[link_name = "remaining_gas"]
+        fn oort_remaining_gas() -> i32;
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn remaining_gas() -> u32 {
+        unsafe { oort_remaining_gas() }.max(0) as u32
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn remaining_gas() -> u32 {
+        u32::MAX
+    }
+
     pub fn getenv(key: &str) -> Option<&'static str> {
         let environment = read_environment();
         for line in environment.lines() {
@@ -461,7 +542,7 @@ pub mod rng_state {
         pub rng: oorandom::Rand64,
     }
 
-    static mut RNG_STATE: Option<RngState> = None;
+    pub(crate) static mut RNG_STATE: Option<RngState> = None;
 
     impl RngState {
         #[allow(clippy::new_without_default)]
@@ -483,7 +564,8 @@ pub mod rng_state {
 
 mod api {
     use super::sys::{read_system_state, write_system_state};
-    use super::{Ability, Class, EcmMode, SystemState};
+    use super::{Ability, Class, EcmMode, RadarMode, SystemState};
+    use super::{ESM_CONTACT_SIZE, MAX_ESM_CONTACTS, MAX_RADAR_CONTACTS, RADAR_CONTACT_SIZE};
     use crate::sys::{read_system_state_u64, write_system_state_u64};
     use crate::{vec::*, ActiveAbilities, Message};
 
@@ -731,6 +813,83 @@ mod api {
         })
     }
 
+    /// Gets the radar mode.
+    pub fn radar_mode() -> RadarMode {
+        read_system_state(SystemState::RadarMode).into()
+    }
+
+    /// Sets the radar mode.
+    ///
+    /// It takes effect next tick. Ships that don't support track-while-scan
+    /// stay in single-contact mode.
+    pub fn set_radar_mode(mode: RadarMode) {
+        write_system_state(SystemState::RadarMode, mode as u32 as f64);
+    }
+
+    /// Returns all radar contacts found in track-while-scan mode, strongest
+    /// first.
+    ///
+    /// In single-contact mode this returns the result of [`scan`], if any.
+    pub fn scan_all() -> Vec<ScanResult> {
+        if radar_mode() != RadarMode::TrackWhileScan {
+            return scan().into_iter().collect();
+        }
+        let n =
+            (read_system_state(SystemState::RadarContactsLength) as usize).min(MAX_RADAR_CONTACTS);
+        let contacts = unsafe { &crate::sys::RADAR_CONTACTS };
+        contacts
+            .chunks_exact(RADAR_CONTACT_SIZE)
+            .take(n)
+            .map(|c| ScanResult {
+                class: Class::from_f64(c[0]),
+                position: vec2(c[1], c[2]),
+                velocity: vec2(c[3], c[4]),
+                rssi: c[5],
+                snr: c[6],
+            })
+            .collect()
+    }
+
+    /// Returns whether the radar is transmitting.
+    pub fn radar_enabled() -> bool {
+        read_system_state(SystemState::RadarDisabled) == 0.0
+    }
+
+    /// Turns the radar on or off.
+    ///
+    /// A disabled radar returns no contacts and can't be detected by enemy
+    /// ESM. It takes effect next tick.
+    pub fn set_radar_enabled(enabled: bool) {
+        write_system_state(SystemState::RadarDisabled, if enabled { 0.0 } else { 1.0 });
+    }
+
+    /// An enemy radar detected by the Electronic Support Measures (ESM)
+    /// receiver.
+    #[derive(Clone, Debug)]
+    pub struct EsmContact {
+        /// The approximate direction to the emitter (in radians).
+        pub bearing: f64,
+        /// The approximate received signal strength measured in dBm.
+        pub rssi: f64,
+    }
+
+    /// Returns the enemy radars whose beams are illuminating this ship,
+    /// strongest first.
+    ///
+    /// ESM is passive, so using it doesn't reveal your position.
+    pub fn esm_contacts() -> Vec<EsmContact> {
+        let n = (read_system_state(SystemState::EsmContactsLength) as usize).min(MAX_ESM_CONTACTS);
+        let contacts = unsafe { &crate::sys::ESM_CONTACTS };
+        contacts
+            .chunks_exact(ESM_CONTACT_SIZE)
+            .take(n)
+            .map(|c| EsmContact {
+                bearing: c[0],
+                rssi: c[1],
+            })
+            .collect()
+    }
+
     #[doc(hidden)]
     pub mod radio_internal {
         use super::SystemState;
@@ -791,7 +950,9 @@ mod api {
 
     /// Sends a radio message.
     ///
-    /// The message will be received on the next tick.
+    /// The message will be received on the next tick. Some scenarios add a
+    /// delay proportional to distance and lose messages near the edge of
+    /// radio range.
     ///
     /// If you want to send arbitrary data, consider using [`send_bytes`] instead.
     pub fn send(msg: Message) {
@@ -804,6 +965,31 @@ mod api {
         write_system_state(idxs.data[3], msg[3]);
     }
 
+    /// Sets the Electronic Counter Measures (ECM) mode of the selected radio.
+    ///
+    /// A jamming radio raises the noise floor on its channel for every ship,
+    /// including your own, and can't send or receive messages itself.
+    /// Takes effect next tick.
+    pub fn set_radio_ecm_mode(mode: EcmMode) {
+        let index = read_system_state(SystemState::SelectedRadio) as u64;
+        let mut modes = read_system_state_u64(SystemState::RadioEcmModes);
+        match mode {
+            EcmMode::None => modes &= !(1 << index),
+            EcmMode::Noise => modes |= 1 << index,
+        }
+        write_system_state_u64(SystemState::RadioEcmModes, modes);
+    }
+
+    /// Gets the Electronic Counter Measures (ECM) mode of the selected radio.
+    pub fn radio_ecm_mode() -> EcmMode {
+        let index = read_system_state(SystemState::SelectedRadio) as u64;
+        if read_system_state_u64(SystemState::RadioEcmModes) & (1 << index) != 0 {
+            EcmMode::Noise
+        } else {
+            EcmMode::None
+        }
+    }
+
     /// Returns the received radio message.
     pub fn receive() -> Option<Message> {
         let idxs =
@@ -823,7 +1009,9 @@ mod api {
     /// Sends a radio message.
     /// The message will be zero-filled or truncated to be 32 bytes long.
     ///
-    /// The message will be received on the next tick.
+    /// The message will be received on the next tick. Some scenarios add a
+    /// delay proportional to distance and lose messages near the edge of
+    /// radio range.
     ///
     /// If you only want to send [`f64`]s consider using [`send`] instead.
     pub fn send_bytes(msg: &[u8]) {
@@ -900,6 +1088,13 @@ mod api {
         read_system_state(SystemState::CurrentTick) * TICK_LENGTH
     }
 
+    /// Returns the number of instructions the ship can still execute this tick.
+    ///
+    /// Each ship gets a fixed budget every tick and crashes if it runs out.
+    pub fn remaining_instructions() -> u32 {
+        super::sys::remaining_gas()
+    }
+
     /// Activates a special ability.
     pub fn activate_ability(ability: Ability) {
         let mut active_abilities =
@@ -948,9 +1143,9 @@ pub mod dbg {
     use crate::vec::*;
     use std::f64::consts::TAU;
 
-    static mut TEXT_BUFFER: String = String::new();
-    static mut LINE_BUFFER: Vec<Line> = Vec::new();
-    static mut DRAWN_TEXT_BUFFER: Vec<Text> = Vec::new();
+    pub(crate) static mut TEXT_BUFFER: String = String::new();
+    pub(crate) static mut LINE_BUFFER: Vec<Line> = Vec::new();
+    pub(crate) static mut DRAWN_TEXT_BUFFER: Vec<Text> = Vec::new();
 
     /// Adds text to be displayed when the ship is selected by clicking on it.
     ///
@@ -1224,7 +1419,9 @@ pub mod prelude {
     #[doc(inline)]
     pub use super::vec::*;
     #[doc(inline)]
-    pub use super::{Ability, Class, EcmMode, Message};
+    pub use super::{
+        Ability, Class, EcmMode, Message, RadarMode, MAX_ESM_CONTACTS, MAX_RADAR_CONTACTS,
+    };
     #[doc(inline)]
     pub use crate::{debug, draw_text};
 
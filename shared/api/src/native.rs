@@ -0,0 +1,82 @@
+// Runs ship code natively instead of in wasm, for unit tests and debugging.
+//
+// The API keeps a ship's state in globals. Each ship gets a Context holding
+// its own copy, which is swapped into the globals around every tick. A lock
+// serializes ships ticking on different threads.
+use crate::rng_state::{self, RngState};
+use crate::{dbg, sys, Line, SystemState, Text};
+use crate::{ESM_CONTACT_SIZE, MAX_ENVIRONMENT_SIZE, MAX_ESM_CONTACTS};
+use crate::{MAX_RADAR_CONTACTS, RADAR_CONTACT_SIZE};
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+pub struct Context {
+    pub system_state: [u64; SystemState::MaxSize as usize],
+    pub environment: [u8; MAX_ENVIRONMENT_SIZE],
+    pub esm_contacts: [f64; MAX_ESM_CONTACTS * ESM_CONTACT_SIZE],
+    pub radar_contacts: [f64; MAX_RADAR_CONTACTS * RADAR_CONTACT_SIZE],
+    rng: Option<RngState>,
+}
+
+#[derive(Default)]
+pub struct DebugOutput {
+    pub text: String,
+    pub lines: Vec<Line>,
+    pub drawn_text: Vec<Text>,
+}
+
+impl Context {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            system_state: [0; SystemState::MaxSize as usize],
+            environment: [0; MAX_ENVIRONMENT_SIZE],
+            esm_contacts: [0.0; MAX_ESM_CONTACTS * ESM_CONTACT_SIZE],
+            radar_contacts: [0.0; MAX_RADAR_CONTACTS * RADAR_CONTACT_SIZE],
+            rng: None,
+        }
+    }
+
+    // Calls f with this context installed. Returns the panic message if f
+    // panicked.
+    pub fn run(&mut self, f: impl FnOnce()) -> (Result<(), String>, DebugOutput) {
+        // A panicking ship can't leave the globals inconsistent, since they're
+        // overwritten before the next ship runs.
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            sys::SYSTEM_STATE = self.system_state;
+            sys::ENVIRONMENT = self.environment;
+            sys::ESM_CONTACTS = self.esm_contacts;
+            sys::RADAR_CONTACTS = self.radar_contacts;
+            match self.rng.take() {
+                Some(rng) => rng_state::set(rng),
+                // The RNG is seeded from the system state, so it can't be
+                // created before the first tick.
+                None => rng_state::set(RngState::new()),
+            }
+        }
+        dbg::reset();
+
+        let result = std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+            let msg = payload
+                .downcast_ref::<&str>()
+                .map(|x| x.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            format!("ship panicked at '{msg}'")
+        });
+
+        unsafe {
+            self.system_state = sys::SYSTEM_STATE;
+            self.rng = rng_state::RNG_STATE.take();
+            let output = DebugOutput {
+                text: std::mem::take(&mut dbg::TEXT_BUFFER),
+                lines: std::mem::take(&mut dbg::LINE_BUFFER),
+                drawn_text: std::mem::take(&mut dbg::DRAWN_TEXT_BUFFER),
+            };
+            (result, output)
+        }
+    }
+}
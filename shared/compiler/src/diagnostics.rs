@@ -0,0 +1,217 @@
+use lazy_static::lazy_static;
+use oort_proto::compiler::{CompilerDiagnostics, Diagnostic, Severity, Span, Suggestion};
+use regex::Regex;
+use serde::Deserialize;
+
+// Subset of rustc's --error-format=json output.
+#[derive(Deserialize)]
+struct RustcDiagnostic {
+    #[serde(rename = "$message_type", default)]
+    message_type: Option<String>,
+    message: String,
+    code: Option<RustcCode>,
+    level: String,
+    spans: Vec<RustcSpan>,
+    children: Vec<RustcDiagnostic>,
+    rendered: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RustcCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RustcSpan {
+    file_name: String,
+    line_start: u32,
+    line_end: u32,
+    column_start: u32,
+    column_end: u32,
+    is_primary: bool,
+    label: Option<String>,
+    suggested_replacement: Option<String>,
+}
+
+fn is_user_file(file_name: &str) -> bool {
+    file_name.ends_with("ai/src/user.rs") || file_name.ends_with("user.c")
+}
+
+fn severity(level: &str) -> Severity {
+    if level.starts_with("error") {
+        Severity::Error
+    } else if level == "warning" {
+        Severity::Warning
+    } else if level == "help" {
+        Severity::Help
+    } else {
+        Severity::Note
+    }
+}
+
+impl RustcSpan {
+    fn to_span(&self) -> Span {
+        Span {
+            start_line: self.line_start,
+            start_column: self.column_start,
+            end_line: self.line_end,
+            end_column: self.column_end,
+            primary: self.is_primary,
+            label: self.label.clone(),
+        }
+    }
+}
+
+impl RustcDiagnostic {
+    fn to_diagnostic(&self) -> Diagnostic {
+        let user_spans = |d: &RustcDiagnostic| {
+            d.spans
+                .iter()
+                .filter(|s| is_user_file(&s.file_name))
+                .collect::<Vec<_>>()
+        };
+        let suggestions = self
+            .children
+            .iter()
+            .flat_map(|child| {
+                user_spans(child).into_iter().filter_map(|s| {
+                    Some(Suggestion {
+                        message: child.message.clone(),
+                        span: s.to_span(),
+                        replacement: s.suggested_replacement.clone()?,
+                    })
+                })
+            })
+            .collect();
+        Diagnostic {
+            severity: severity(&self.level),
+            message: self.message.clone(),
+            code: self.code.as_ref().map(|c| c.code.clone()),
+            spans: user_spans(self).into_iter().map(|s| s.to_span()).collect(),
+            suggestions,
+            rendered: self
+                .rendered
+                .clone()
+                .unwrap_or_else(|| format!("{}: {}", self.level, self.message)),
+        }
+    }
+}
+
+// Parses the stderr of rustc run with --error-format=json. Lines that aren't
+// diagnostics (e.g. from a crashing linker) become span-less errors.
+pub fn parse_rustc(stderr: &str) -> CompilerDiagnostics {
+    let mut diagnostics = vec![];
+    for line in stderr.lines().filter(|line| !line.trim().is_empty()) {
+        match serde_json::from_str::<RustcDiagnostic>(line) {
+            Ok(d) if d.message_type.as_deref().unwrap_or("diagnostic") == "diagnostic" => {
+                diagnostics.push(d.to_diagnostic())
+            }
+            Ok(_) => {}
+            Err(_) => diagnostics.extend(CompilerDiagnostics::from_message(line).diagnostics),
+        }
+    }
+    CompilerDiagnostics { diagnostics }
+}
+
+// Parses clang's human-readable diagnostics. Context lines following a
+// diagnostic are added to its rendered text.
+pub fn parse_clang(stderr: &str) -> CompilerDiagnostics {
+    lazy_static! {
+        static ref LOCATED: Regex =
+            Regex::new(r"^(.*?):(\d+):(\d+): (fatal error|error|warning|note): (.*)$").unwrap();
+        static ref UNLOCATED: Regex = Regex::new(r"^[\w.-]+: (error|warning): (.*)$").unwrap();
+    }
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+    for line in stderr.lines() {
+        let (level, message, spans) = if let Some(m) = LOCATED.captures(line) {
+            let spans = if is_user_file(&m[1]) {
+                let line_number: u32 = m[2].parse().unwrap();
+                let column: u32 = m[3].parse().unwrap();
+                vec![Span {
+                    start_line: line_number,
+                    start_column: column,
+                    end_line: line_number,
+                    end_column: column + 1,
+                    primary: true,
+                    label: None,
+                }]
+            } else {
+                vec![]
+            };
+            (m[4].to_string(), m[5].to_string(), spans)
+        } else if let Some(m) = UNLOCATED.captures(line) {
+            (m[1].to_string(), m[2].to_string(), vec![])
+        } else {
+            if let Some(last) = diagnostics.last_mut() {
+                last.rendered.push('\n');
+                last.rendered.push_str(line);
+            }
+            continue;
+        };
+        diagnostics.push(Diagnostic {
+            severity: severity(&level),
+            message,
+            code: None,
+            spans,
+            suggestions: vec![],
+            rendered: line.to_string(),
+        });
+    }
+    if diagnostics.is_empty() {
+        return CompilerDiagnostics::from_message(stderr);
+    }
+    CompilerDiagnostics { diagnostics }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rustc() {
+        let stderr = [
+            r#"{"$message_type":"diagnostic","message":"mismatched types","code":{"code":"E0308","explanation":null},"level":"error","spans":[{"file_name":"/tmp/oort-ai/ai/src/user.rs","byte_start":0,"byte_end":1,"line_start":5,"line_end":5,"column_start":22,"column_end":23,"is_primary":true,"text":[],"label":"expected `f64`, found integer","suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"use a float literal","code":null,"level":"help","spans":[{"file_name":"/tmp/oort-ai/ai/src/user.rs","byte_start":0,"byte_end":1,"line_start":5,"line_end":5,"column_start":22,"column_end":23,"is_primary":true,"text":[],"label":null,"suggested_replacement":"1.0","suggestion_applicability":"MaybeIncorrect","expansion":null}],"children":[],"rendered":null}],"rendered":"error[E0308]: mismatched types\n"}"#,
+            r#"{"$message_type":"diagnostic","message":"aborting due to previous error","code":null,"level":"error","spans":[],"children":[],"rendered":"error: aborting due to previous error\n"}"#,
+        ]
+        .join("\n");
+        let diagnostics = parse_rustc(&stderr).diagnostics;
+        assert_eq!(diagnostics.len(), 2);
+        let d = &diagnostics[0];
+        assert_eq!(d.severity, Severity::Error);
+        assert_eq!(d.message, "mismatched types");
+        assert_eq!(d.code.as_deref(), Some("E0308"));
+        assert_eq!(d.primary_span().unwrap().start_line, 5);
+        assert_eq!(d.primary_span().unwrap().start_column, 22);
+        assert_eq!(d.suggestions.len(), 1);
+        assert_eq!(d.suggestions[0].replacement, "1.0");
+        assert!(diagnostics[1].spans.is_empty());
+    }
+
+    #[test]
+    fn test_rustc_garbage() {
+        let diagnostics = parse_rustc("linker crashed\n").diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "linker crashed");
+    }
+
+    #[test]
+    fn test_clang() {
+        let stderr = "/tmp/oort/user.c:3:5: error: use of undeclared identifier 'x'\n    3 |     x = 1;\n      |     ^\n1 error generated.\n";
+        let diagnostics = parse_clang(stderr).diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        let d = &diagnostics[0];
+        assert_eq!(d.severity, Severity::Error);
+        assert_eq!(d.message, "use of undeclared identifier 'x'");
+        assert_eq!(d.spans[0].start_line, 3);
+        assert_eq!(d.spans[0].start_column, 5);
+        assert!(d.rendered.contains("x = 1;"));
+    }
+
+    #[test]
+    fn test_clang_linker() {
+        let diagnostics = parse_clang("wasm-ld: error: undefined symbol: foo\n").diagnostics;
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "undefined symbol: foo");
+        assert!(diagnostics[0].spans.is_empty());
+    }
+}
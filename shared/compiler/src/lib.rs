@@ -1,8 +1,13 @@
+mod diagnostics;
 mod sanitizer;
+mod wasm_text;
 
 use anyhow::{bail, Result};
+use oort_proto::compiler::CompilerDiagnostics;
 use std::path::{Path, PathBuf};
 
+pub use wasm_text::WatError;
+
 pub struct Compiler {
     #[allow(dead_code)]
     tmp_dir: Option<tempdir::TempDir>,
@@ -37,10 +42,18 @@ impl Compiler {
         self.offline = false;
     }
 
+    // Compile errors are returned as CompilerDiagnostics, which callers can
+    // recover with downcast_ref.
     pub fn compile(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
         match detect_language(code) {
             Language::Rust => self.compile_rust(code),
             Language::C => self.compile_c(code),
+            Language::Wat => wasm_text::compile(code).map_err(|e| {
+                CompilerDiagnostics {
+                    diagnostics: vec![e.to_diagnostic(code)],
+                }
+                .into()
+            }),
             Language::Unknown => bail!("Unknown language"),
         }
     }
@@ -145,7 +158,7 @@ impl Compiler {
                 "--target",
                 "wasm32-unknown-unknown",
                 "-C",
-                "strip=debuginfo",
+                "debuginfo=line-tables-only",
                 "-L",
                 &format!(
                     "dependency={}",
@@ -171,10 +184,11 @@ impl Compiler {
                 "llvm-args=-rng-seed=42",
                 "--remap-path-prefix",
                 &format!("{}=/tmp/oort-ai", tmp_path.display()),
+                "--error-format=json",
             ])
             .output()?;
         if !output.status.success() {
-            bail!("rustc failed: {}", std::str::from_utf8(&output.stderr)?);
+            return Err(diagnostics::parse_rustc(std::str::from_utf8(&output.stderr)?).into());
         }
 
         Ok(std::fs::read(tmp_path.join(
@@ -205,11 +219,16 @@ impl Compiler {
             ])
             .output()?;
         if !output.status.success() {
-            bail!("clang failed: {}", std::str::from_utf8(&output.stderr)?);
+            return Err(diagnostics::parse_clang(std::str::from_utf8(&output.stderr)?).into());
         }
 
         Ok(std::fs::read(&dst_path)?)
     }
+
+    // Errors are WatError, which callers can recover with downcast_ref.
+    pub fn compile_wat(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
+        Ok(wasm_text::compile(code)?)
+    }
 }
 
 fn find_rlib(tmp_path: &Path, crate_name: &str) -> PathBuf {
@@ -252,12 +271,16 @@ fn find_rustc() -> String {
 enum Language {
     Rust,
     C,
+    Wat,
     Unknown,
 }
 
 fn detect_language(code: &str) -> Language {
     log::info!("Code: {:?}", code);
-    if code.contains("void tick()") {
+    if is_wat(code) {
+        log::info!("Detected WAT");
+        Language::Wat
+    } else if code.contains("void tick()") {
         log::info!("Detected C");
         Language::C
     } else if code.contains("impl Ship") {
@@ -266,3 +289,10 @@ fn detect_language(code: &str) -> Language {
         Language::Unknown
     }
 }
+
+fn is_wat(code: &str) -> bool {
+    code.lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty() && !line.starts_with(";;"))
+        .map_or(false, |line| line.starts_with("(module"))
+}
@@ -0,0 +1,339 @@
+use lazy_static::lazy_static;
+use oort_proto::compiler::{Diagnostic, Severity, Span};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use wasmparser::{ExternalKind, FuncType, Parser, Payload, Type, TypeRef, ValType, Validator};
+
+// Globals holding the addresses of the API's statics. The contact buffers are
+// optional since older code doesn't export them.
+const REQUIRED_GLOBALS: &[&str] = &["SYSTEM_STATE", "ENVIRONMENT", "PANIC_BUFFER"];
+const OPTIONAL_GLOBALS: &[&str] = &["ESM_CONTACTS", "RADAR_CONTACTS"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatError {
+    Parse(String),
+    Invalid(String),
+    MissingExport {
+        name: String,
+        kind: &'static str,
+    },
+    WrongType {
+        name: String,
+        expected: String,
+        found: String,
+    },
+    UnsupportedImport {
+        module: String,
+        name: String,
+    },
+}
+
+impl fmt::Display for WatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatError::Parse(msg) => write!(f, "Failed to parse WAT: {msg}"),
+            WatError::Invalid(msg) => write!(f, "Invalid module: {msg}"),
+            WatError::MissingExport { name, kind } => {
+                write!(f, "Missing {kind} export {name:?}")
+            }
+            WatError::WrongType {
+                name,
+                expected,
+                found,
+            } => write!(f, "Export {name:?} has type {found}, expected {expected}"),
+            WatError::UnsupportedImport { module, name } => {
+                write!(f, "Unsupported import {module}.{name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for WatError {}
+
+impl WatError {
+    // Converts to the same shape as rustc and clang errors. `code` is the WAT
+    // source, used to locate the error.
+    pub fn to_diagnostic(&self, code: &str) -> Diagnostic {
+        let (error_code, span) = match self {
+            WatError::Parse(msg) => ("parse", parse_error_span(msg)),
+            WatError::Invalid(_) => ("invalid", None),
+            WatError::MissingExport { .. } => ("missing-export", None),
+            WatError::WrongType { name, .. } => {
+                ("wrong-type", find_span(code, &format!("(export {name:?}")))
+            }
+            WatError::UnsupportedImport { module, name } => (
+                "unsupported-import",
+                find_span(code, &format!("(import {module:?} {name:?}")),
+            ),
+        };
+        let message = self.to_string();
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.clone(),
+            code: Some(error_code.to_string()),
+            spans: span.into_iter().collect(),
+            suggestions: vec![],
+            rendered: format!("error: {message}"),
+        }
+    }
+}
+
+// The wat crate reports parse errors as "... --> <anon>:line:column".
+fn parse_error_span(msg: &str) -> Option<Span> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"--> [^\n]*:(\d+):(\d+)").unwrap();
+    }
+    let m = RE.captures(msg)?;
+    let line: u32 = m[1].parse().ok()?;
+    let column: u32 = m[2].parse().ok()?;
+    Some(Span {
+        start_line: line,
+        start_column: column,
+        end_line: line,
+        end_column: column + 1,
+        primary: true,
+        label: None,
+    })
+}
+
+fn find_span(code: &str, needle: &str) -> Option<Span> {
+    let offset = code.find(needle)?;
+    let line_start = code[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line = code[..offset].matches('\n').count() as u32 + 1;
+    let column = code[line_start..offset].chars().count() as u32 + 1;
+    Some(Span {
+        start_line: line,
+        start_column: column,
+        end_line: line,
+        end_column: column + needle.chars().count() as u32,
+        primary: true,
+        label: None,
+    })
+}
+
+// Parses WebAssembly text and checks that the module has the exports the
+// simulator expects from compiled ship code.
+pub fn compile(code: &str) -> Result<Vec<u8>, WatError> {
+    let wasm = wat::parse_str(code).map_err(|e| WatError::Parse(e.to_string()))?;
+    Validator::new()
+        .validate_all(&wasm)
+        .map_err(|e| WatError::Invalid(e.to_string()))?;
+    check_layout(&wasm)?;
+    Ok(wasm)
+}
+
+fn check_layout(wasm: &[u8]) -> Result<(), WatError> {
+    let invalid = |e: wasmparser::BinaryReaderError| WatError::Invalid(e.to_string());
+    let mut types: Vec<FuncType> = vec![];
+    // Type index of each function, including imports.
+    let mut functions: Vec<u32> = vec![];
+    let mut globals: Vec<ValType> = vec![];
+    let mut exports: HashMap<String, (ExternalKind, u32)> = HashMap::new();
+
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload.map_err(invalid)? {
+            Payload::TypeSection(reader) => {
+                for ty in reader {
+                    let Type::Func(ty) = ty.map_err(invalid)?;
+                    types.push(ty);
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import.map_err(invalid)?;
+                    match (import.module, import.name, import.ty) {
+                        ("oort", "remaining_gas", TypeRef::Func(index)) => {
+                            let results = &[ValType::I32];
+                            check_signature(&types, "remaining_gas", index, &[], results)?;
+                            functions.push(index);
+                        }
+                        _ => {
+                            return Err(WatError::UnsupportedImport {
+                                module: import.module.to_string(),
+                                name: import.name.to_string(),
+                            })
+                        }
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for index in reader {
+                    functions.push(index.map_err(invalid)?);
+                }
+            }
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    globals.push(global.map_err(invalid)?.ty.content_type);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(invalid)?;
+                    exports.insert(export.name.to_string(), (export.kind, export.index));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    match exports.get("tick") {
+        Some((ExternalKind::Func, index)) => {
+            check_signature(&types, "tick", functions[*index as usize], &[], &[])?
+        }
+        _ => {
+            return Err(WatError::MissingExport {
+                name: "tick".to_string(),
+                kind: "function",
+            })
+        }
+    }
+
+    if !matches!(exports.get("memory"), Some((ExternalKind::Memory, _))) {
+        return Err(WatError::MissingExport {
+            name: "memory".to_string(),
+            kind: "memory",
+        });
+    }
+
+    for &name in REQUIRED_GLOBALS.iter().chain(OPTIONAL_GLOBALS) {
+        match exports.get(name) {
+            Some((ExternalKind::Global, index)) => {
+                let ty = globals[*index as usize];
+                if ty != ValType::I32 {
+                    return Err(WatError::WrongType {
+                        name: name.to_string(),
+                        expected: "i32".to_string(),
+                        found: format!("{ty:?}").to_lowercase(),
+                    });
+                }
+            }
+            _ if OPTIONAL_GLOBALS.contains(&name) => {}
+            _ => {
+                return Err(WatError::MissingExport {
+                    name: name.to_string(),
+                    kind: "global",
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn check_signature(
+    types: &[FuncType],
+    name: &str,
+    type_index: u32,
+    params: &[ValType],
+    results: &[ValType],
+) -> Result<(), WatError> {
+    let ty = &types[type_index as usize];
+    if ty.params() != params || ty.results() != results {
+        return Err(WatError::WrongType {
+            name: name.to_string(),
+            expected: format_signature(params, results),
+            found: format_signature(ty.params(), ty.results()),
+        });
+    }
+    Ok(())
+}
+
+fn format_signature(params: &[ValType], results: &[ValType]) -> String {
+    let format = |types: &[ValType]| {
+        types
+            .iter()
+            .map(|ty| format!("{ty:?}").to_lowercase())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("({}) -> ({})", format(params), format(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = r#"
+        (memory (export "memory") 1)
+        (global (export "SYSTEM_STATE") i32 (i32.const 1024))
+        (global (export "ENVIRONMENT") i32 (i32.const 2048))
+        (global (export "PANIC_BUFFER") i32 (i32.const 4096))
+    "#;
+
+    #[test]
+    fn test_valid() {
+        let code = format!(r#"(module {HEADER} (func (export "tick")))"#);
+        let wasm = compile(&code).unwrap();
+        assert_eq!(&wasm[0..4], b"\0asm");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let code = "(module\n  (func (export \"tick\") (foo)))";
+        let err = compile(code).unwrap_err();
+        assert!(matches!(err, WatError::Parse(_)));
+        let span = err.to_diagnostic(code).spans[0].clone();
+        assert_eq!(span.start_line, 2);
+    }
+
+    #[test]
+    fn test_missing_tick() {
+        let code = format!(r#"(module {HEADER})"#);
+        assert_eq!(
+            compile(&code),
+            Err(WatError::MissingExport {
+                name: "tick".to_string(),
+                kind: "function"
+            })
+        );
+    }
+
+    #[test]
+    fn test_wrong_tick_signature() {
+        let code = format!(r#"(module {HEADER} (func (export "tick") (param i32)))"#);
+        let err = compile(&code).unwrap_err();
+        assert_eq!(
+            err,
+            WatError::WrongType {
+                name: "tick".to_string(),
+                expected: "() -> ()".to_string(),
+                found: "(i32) -> ()".to_string(),
+            }
+        );
+
+        let diagnostic = err.to_diagnostic(&code);
+        assert_eq!(diagnostic.code.as_deref(), Some("wrong-type"));
+        let span = &diagnostic.spans[0];
+        let line = code.lines().nth(span.start_line as usize - 1).unwrap();
+        assert_eq!(
+            &line[span.start_column as usize - 1..span.end_column as usize - 1],
+            r#"(export "tick""#
+        );
+    }
+
+    #[test]
+    fn test_missing_global() {
+        let code = r#"(module (memory (export "memory") 1) (func (export "tick")))"#;
+        assert_eq!(
+            compile(code),
+            Err(WatError::MissingExport {
+                name: "SYSTEM_STATE".to_string(),
+                kind: "global"
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsupported_import() {
+        let code =
+            format!(r#"(module (import "env" "foo" (func)) {HEADER} (func (export "tick")))"#);
+        assert_eq!(
+            compile(&code),
+            Err(WatError::UnsupportedImport {
+                module: "env".to_string(),
+                name: "foo".to_string(),
+            })
+        );
+    }
+}
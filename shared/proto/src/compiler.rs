@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Body of an unsuccessful /compile response.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct CompilerDiagnostics {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub code: Option<String>,
+    pub spans: Vec<Span>,
+    pub suggestions: Vec<Suggestion>,
+    // Human-readable output from the compiler, including context lines.
+    pub rendered: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+// Lines and columns are 1-based and refer to the user's source code.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Span {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub primary: bool,
+    pub label: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl CompilerDiagnostics {
+    // Wraps an error that didn't come with any location information.
+    pub fn from_message(msg: &str) -> Self {
+        Self {
+            diagnostics: vec![Diagnostic {
+                severity: Severity::Error,
+                message: msg.to_string(),
+                code: None,
+                spans: vec![],
+                suggestions: vec![],
+                rendered: msg.to_string(),
+            }],
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+    }
+}
+
+impl Diagnostic {
+    pub fn primary_span(&self) -> Option<&Span> {
+        self.spans.iter().find(|s| s.primary)
+    }
+}
+
+impl fmt::Display for CompilerDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic.rendered.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CompilerDiagnostics {}
@@ -1,4 +1,5 @@
 pub mod analyzer;
+pub mod compiler;
 
 use chrono::serde::ts_milliseconds;
 use chrono::{DateTime, Utc};
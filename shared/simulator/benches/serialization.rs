@@ -2,6 +2,7 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use oort_simulator::scenario;
 use oort_simulator::simulation;
 use oort_simulator::snapshot::Snapshot;
+use oort_simulator::snapshot_codec::{SnapshotDecoder, SnapshotEncoder};
 
 fn make_snapshot() -> Snapshot {
     let scenario_name = "tutorial_squadron";
@@ -13,6 +14,21 @@ fn make_snapshot() -> Snapshot {
     sim.snapshot(0)
 }
 
+fn make_snapshot_stream() -> Vec<Snapshot> {
+    let scenario_name = "fleet";
+    let scenario = scenario::load(scenario_name);
+    let mut sim = simulation::Simulation::new(scenario_name, 0, &scenario.solution_codes());
+    for _ in 0..600 {
+        sim.step();
+    }
+    (0..60)
+        .map(|i| {
+            sim.step();
+            sim.snapshot(i)
+        })
+        .collect()
+}
+
 fn criterion_benchmark(c: &mut Criterion) {
     let snapshot = make_snapshot();
     c.bench_function("json", |b| {
@@ -29,6 +45,37 @@ fn criterion_benchmark(c: &mut Criterion) {
             ))
         })
     });
+
+    let snapshots = make_snapshot_stream();
+    let bincode_size: usize = snapshots
+        .iter()
+        .map(|x| bincode::serialize(x).unwrap().len())
+        .sum();
+    let mut encoder = SnapshotEncoder::new();
+    let codec_size: usize = snapshots
+        .iter()
+        .map(|x| encoder.encode(x.clone()).len())
+        .sum();
+    println!("Stream size: bincode {bincode_size} bytes, codec {codec_size} bytes");
+    c.bench_function("bincode_stream", |b| {
+        b.iter(|| {
+            for snapshot in snapshots.iter() {
+                black_box(bincode::deserialize::<Snapshot>(
+                    &bincode::serialize(snapshot).unwrap(),
+                ))
+                .unwrap();
+            }
+        })
+    });
+    c.bench_function("codec_stream", |b| {
+        b.iter(|| {
+            let mut encoder = SnapshotEncoder::new();
+            let mut decoder = SnapshotDecoder::new();
+            for snapshot in snapshots.iter() {
+                black_box(decoder.decode(&encoder.encode(snapshot.clone()))).unwrap();
+            }
+        })
+    });
 }
 
 pub fn criterion_config() -> Criterion {
@@ -1,16 +1,18 @@
 use std::collections::HashMap;
 
 use super::index_set::{HasIndex, Index};
+use crate::ship::ShipHandle;
 use crate::simulation::{Simulation, MAX_WORLD_SIZE, PHYSICS_TICK_LENGTH};
 use crate::{collision, simulation};
 use bitvec::vec::BitVec;
 use nalgebra::Vector2;
 use rapier2d_f64::prelude::*;
+use serde::{Deserialize, Serialize};
 use static_aabb2d_index::*;
 
 const COLOR_COLLIDERS: bool = false;
 
-#[derive(Hash, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
+#[derive(Hash, PartialEq, Eq, Copy, Clone, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct BulletHandle(pub Index);
 
 impl HasIndex for BulletHandle {
@@ -25,12 +27,13 @@ impl From<BulletHandle> for RigidBodyHandle {
     }
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct BulletData {
     pub mass: f32,
     pub team: i32,
     pub ttl: f32,
     pub color: u32,
+    pub owner: Option<ShipHandle>,
 }
 
 pub fn body(sim: &Simulation, handle: BulletHandle) -> &RigidBody {
@@ -1,4 +1,5 @@
 use crate::bullet::{self, BulletHandle};
+use crate::event::{Attacker, GameEventKind};
 use crate::index_set::HasIndex;
 use crate::ship::{ShipClass, ShipHandle};
 use crate::simulation::{Particle, Simulation, PHYSICS_TICK_LENGTH};
@@ -86,7 +87,10 @@ pub fn handle_collisions(sim: &mut Simulation, events: &[CollisionEvent]) {
                             false,
                         );
                     }
-                    bullet::data_mut(sim, bullet).team = sim.ship(ship).data().team;
+                    let team = sim.ship(ship).data().team;
+                    let data = bullet::data_mut(sim, bullet);
+                    data.team = team;
+                    data.owner = Some(ship);
                     return;
                 }
                 if bullet::data(sim, bullet).team == sim.ship(ship).data().team {
@@ -107,11 +111,25 @@ pub fn handle_collisions(sim: &mut Simulation, events: &[CollisionEvent]) {
                         lifetime: (PHYSICS_TICK_LENGTH * 30.0) as f32,
                     });
                 }
-                let ship_destroyed = {
+                let attacker = {
+                    let data = bullet::data(sim, bullet);
+                    Attacker {
+                        ship: data.owner.map(u64::from),
+                        team: data.team,
+                    }
+                };
+                let (ship_team, ship_destroyed) = {
                     let ship_data = sim.ship_data.get_mut(ship.index()).unwrap();
                     ship_data.health -= damage;
-                    ship_data.health <= 0.0
+                    ship_data.last_attacker = Some(attacker);
+                    (ship_data.team, ship_data.health <= 0.0)
                 };
+                sim.record_event(GameEventKind::Hit {
+                    ship: ship.into(),
+                    team: ship_team,
+                    attacker,
+                    damage,
+                });
                 if ship_destroyed {
                     for _ in 0..10 {
                         let rot = Rotation2::new(sim.rng.gen_range(0.0..TAU));
@@ -0,0 +1,71 @@
+use crate::ship::ShipClass;
+use oort_api::Ability;
+use serde::{Deserialize, Serialize};
+
+// Gameplay events recorded during a tick, e.g. for kill feeds and post-match
+// analysis. Ship IDs match ShipSnapshot::id.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameEvent {
+    pub tick: u32,
+    pub kind: GameEventKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum GameEventKind {
+    GunFired {
+        ship: u64,
+        team: i32,
+        gun: usize,
+        bullets: u32,
+    },
+    MissileLaunched {
+        ship: u64,
+        team: i32,
+        missile: u64,
+        class: ShipClass,
+    },
+    Hit {
+        ship: u64,
+        team: i32,
+        attacker: Attacker,
+        damage: f64,
+    },
+    Exploded {
+        ship: u64,
+        team: i32,
+    },
+    AbilityActivated {
+        ship: u64,
+        team: i32,
+        ability: Ability,
+    },
+    Destroyed {
+        ship: u64,
+        team: i32,
+        class: ShipClass,
+        // The last ship to damage this one, if any.
+        attacker: Option<Attacker>,
+    },
+}
+
+impl GameEventKind {
+    // Team of the ship the event happened to.
+    pub fn team(&self) -> i32 {
+        match *self {
+            GameEventKind::GunFired { team, .. }
+            | GameEventKind::MissileLaunched { team, .. }
+            | GameEventKind::Hit { team, .. }
+            | GameEventKind::Exploded { team, .. }
+            | GameEventKind::AbilityActivated { team, .. }
+            | GameEventKind::Destroyed { team, .. } => team,
+        }
+    }
+}
+
+// The ship that fired a bullet. For warhead fragments this is the missile or
+// torpedo, see GameEventKind::MissileLaunched for who launched it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Attacker {
+    pub ship: Option<u64>,
+    pub team: i32,
+}
@@ -0,0 +1,232 @@
+use crate::ship::ShipHandle;
+use crate::simulation::{Simulation, PHYSICS_TICK_LENGTH};
+use nalgebra::Vector2;
+use rapier2d_f64::prelude::RigidBody;
+use serde::{Deserialize, Serialize};
+
+pub const G: f64 = 6.674e-11;
+
+// Forces applied to every ship and bullet each tick. Added by scenarios with
+// Simulation::add_force_field.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ForceField {
+    // Newtonian gravity towards a fixed point.
+    PointMass {
+        position: Vector2<f64>,
+        mass: f64,
+    },
+    // Newtonian gravity towards a ship (e.g. a planet) using its mass. The
+    // ship itself is unaffected and the field disappears when it's destroyed.
+    ShipGravity {
+        ship: ShipHandle,
+    },
+    // Constant acceleration everywhere.
+    Uniform {
+        acceleration: Vector2<f64>,
+    },
+    // Acceleration opposing velocity inside a circle.
+    Drag {
+        center: Vector2<f64>,
+        radius: f64,
+        coefficient: f64,
+    },
+}
+
+// A field with ship positions and masses looked up for this tick.
+enum ResolvedField {
+    PointMass {
+        position: Vector2<f64>,
+        mass: f64,
+        source: Option<ShipHandle>,
+    },
+    Uniform {
+        acceleration: Vector2<f64>,
+    },
+    Drag {
+        center: Vector2<f64>,
+        radius: f64,
+        coefficient: f64,
+    },
+}
+
+impl ResolvedField {
+    fn acceleration(&self, position: Vector2<f64>, velocity: Vector2<f64>) -> Vector2<f64> {
+        match *self {
+            ResolvedField::PointMass {
+                position: center,
+                mass,
+                ..
+            } => {
+                let dp = center - position;
+                let r_sq = dp.norm_squared();
+                if r_sq < 1.0 {
+                    return Vector2::zeros();
+                }
+                dp.normalize() * (G * mass / r_sq)
+            }
+            ResolvedField::Uniform { acceleration } => acceleration,
+            ResolvedField::Drag {
+                center,
+                radius,
+                coefficient,
+            } => {
+                if (position - center).norm_squared() < radius * radius {
+                    -velocity * coefficient
+                } else {
+                    Vector2::zeros()
+                }
+            }
+        }
+    }
+
+    fn affects(&self, handle: ShipHandle) -> bool {
+        !matches!(self, ResolvedField::PointMass { source: Some(source), .. } if *source == handle)
+    }
+}
+
+fn apply(fields: &[ResolvedField], body: &mut RigidBody, handle: Option<ShipHandle>) {
+    let position = *body.translation();
+    let velocity = *body.linvel();
+    let acc: Vector2<f64> = fields
+        .iter()
+        .filter(|field| handle.map(|h| field.affects(h)).unwrap_or(true))
+        .map(|field| field.acceleration(position, velocity))
+        .sum();
+    if acc != Vector2::zeros() {
+        let impulse = acc * body.mass() * PHYSICS_TICK_LENGTH;
+        body.apply_impulse(impulse, true);
+    }
+}
+
+#[inline(never)]
+pub fn tick(sim: &mut Simulation) {
+    if sim.force_fields.is_empty() {
+        return;
+    }
+
+    sim.force_fields.retain(|field| match field {
+        ForceField::ShipGravity { ship } => sim.ships.contains(*ship),
+        _ => true,
+    });
+
+    let fields: Vec<ResolvedField> = sim
+        .force_fields
+        .iter()
+        .map(|field| match *field {
+            ForceField::PointMass { position, mass } => ResolvedField::PointMass {
+                position,
+                mass,
+                source: None,
+            },
+            ForceField::ShipGravity { ship } => ResolvedField::PointMass {
+                position: sim.ship(ship).position().vector,
+                mass: sim.ship(ship).data().mass,
+                source: Some(ship),
+            },
+            ForceField::Uniform { acceleration } => ResolvedField::Uniform { acceleration },
+            ForceField::Drag {
+                center,
+                radius,
+                coefficient,
+            } => ResolvedField::Drag {
+                center,
+                radius,
+                coefficient,
+            },
+        })
+        .collect();
+
+    let handles = sim.ships.iter().cloned().collect::<Vec<_>>();
+    for handle in handles {
+        apply(&fields, sim.ship_mut(handle).body(), Some(handle));
+    }
+
+    let handles = sim.bullets.iter().cloned().collect::<Vec<_>>();
+    for handle in handles {
+        apply(&fields, sim.bodies.get_mut(handle.into()).unwrap(), None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ship;
+    use crate::simulation::Code;
+    use nalgebra::vector;
+    use test_log::test;
+
+    #[test]
+    fn test_uniform() {
+        let mut sim = Simulation::new("test", 0, &[Code::None]);
+        let handle = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        sim.add_force_field(ForceField::Uniform {
+            acceleration: vector![0.0, 10.0],
+        });
+        for _ in 0..60 {
+            sim.step();
+        }
+        assert!((sim.ship(handle).velocity() - vector![0.0, 10.0]).norm() < 0.1);
+    }
+
+    #[test]
+    fn test_drag() {
+        let mut sim = Simulation::new("test", 0, &[Code::None]);
+        let inside = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![100.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        let outside = ship::create(
+            &mut sim,
+            vector![0.0, 5000.0],
+            vector![100.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        sim.add_force_field(ForceField::Drag {
+            center: vector![0.0, 0.0],
+            radius: 1000.0,
+            coefficient: 1.0,
+        });
+        for _ in 0..60 {
+            sim.step();
+        }
+        assert!(sim.ship(inside).velocity().x < 50.0);
+        assert!((sim.ship(outside).velocity().x - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ship_gravity() {
+        let mut sim = Simulation::new("test", 0, &[Code::None]);
+        let planet = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::planet(1e19),
+        );
+        let fighter = ship::create(
+            &mut sim,
+            vector![10e3, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        sim.add_force_field(ForceField::ShipGravity { ship: planet });
+        sim.step();
+        assert!(sim.ship(fighter).velocity().x < 0.0);
+        assert_eq!(sim.ship(planet).velocity(), vector![0.0, 0.0]);
+
+        sim.ship_mut(planet).data_mut().destroyed = true;
+        sim.step();
+        assert!(sim.force_fields().is_empty());
+    }
+}
@@ -0,0 +1,116 @@
+use crate::simulation::Simulation;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+
+pub const DEFAULT_INTERVAL: u32 = 300;
+pub const DEFAULT_MAX_BYTES: usize = 64 << 20;
+
+// Ring buffer of periodic checkpoints used to seek backwards in a
+// simulation. Seeking restores the closest earlier checkpoint and steps
+// forward, which relies on the simulation being deterministic.
+//
+// Checkpoints include every VM's linear memory, which is mostly zeroes, so
+// they're stored compressed and the oldest are dropped once the total size
+// exceeds `max_bytes`.
+pub struct History {
+    interval: u32,
+    max_bytes: usize,
+    bytes: usize,
+    checkpoints: VecDeque<(u32, Vec<u8>)>,
+}
+
+impl History {
+    pub fn new(interval: u32, max_bytes: usize) -> Self {
+        assert!(interval > 0 && max_bytes > 0);
+        Self {
+            interval,
+            max_bytes,
+            bytes: 0,
+            checkpoints: VecDeque::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.checkpoints.clear();
+        self.bytes = 0;
+    }
+
+    // Call after every tick. Saves a checkpoint every `interval` ticks.
+    pub fn record(&mut self, sim: &Simulation) {
+        let tick = sim.tick();
+        if tick % self.interval != 0 {
+            return;
+        }
+        if let Some(&(last_tick, _)) = self.checkpoints.back() {
+            if tick <= last_tick {
+                // Replaying after a seek.
+                return;
+            }
+        }
+        let data = match sim.checkpoint().and_then(|data| compress(&data)) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("Failed to checkpoint at tick {}: {}", tick, e);
+                return;
+            }
+        };
+        if data.len() > self.max_bytes {
+            log::warn!(
+                "Checkpoint at tick {} is larger than the history limit ({} > {} bytes)",
+                tick,
+                data.len(),
+                self.max_bytes
+            );
+            return;
+        }
+        while self.bytes + data.len() > self.max_bytes {
+            let (_, evicted) = self.checkpoints.pop_front().unwrap();
+            self.bytes -= evicted.len();
+        }
+        self.bytes += data.len();
+        self.checkpoints.push_back((tick, data));
+    }
+
+    pub fn earliest_tick(&self) -> Option<u32> {
+        self.checkpoints.front().map(|(tick, _)| *tick)
+    }
+
+    // Total size of the stored checkpoints.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // Returns a simulation at the given tick.
+    pub fn seek(&self, tick: u32) -> anyhow::Result<Box<Simulation>> {
+        let (_, data) = self
+            .checkpoints
+            .iter()
+            .rev()
+            .find(|(checkpoint_tick, _)| *checkpoint_tick <= tick)
+            .ok_or_else(|| anyhow::anyhow!("No checkpoint before tick {}", tick))?;
+        let mut sim = Simulation::restore(&decompress(data)?)?;
+        while sim.tick() < tick {
+            sim.step();
+        }
+        Ok(sim)
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_INTERVAL, DEFAULT_MAX_BYTES)
+    }
+}
+
+fn compress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = libflate::deflate::Encoder::new(Vec::new());
+    encoder.write_all(data)?;
+    Ok(encoder.finish().into_result()?)
+}
+
+fn decompress(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut decoder = libflate::deflate::Decoder::new(data);
+    let mut result = vec![];
+    decoder.read_to_end(&mut result)?;
+    Ok(result)
+}
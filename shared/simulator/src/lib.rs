@@ -2,13 +2,20 @@ pub mod bullet;
 pub mod collision;
 pub mod color;
 pub mod debug;
+pub mod event;
+pub mod force_field;
+pub mod history;
 pub mod index_set;
 pub mod model;
 pub mod radar;
 pub mod radio;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod rl;
 pub mod rng;
 pub mod scenario;
 pub mod ship;
 pub mod simulation;
 pub mod snapshot;
+pub mod snapshot_codec;
+pub mod stats;
 pub mod vm;
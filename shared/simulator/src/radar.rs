@@ -2,11 +2,12 @@ use crate::ship::{self, ShipClass, ShipHandle};
 use crate::simulation::{Line, Simulation};
 use crate::{model, rng, simulation};
 use nalgebra::{vector, ComplexField, Point2, Rotation2, Vector2};
-use oort_api::{Ability, EcmMode};
+use oort_api::{Ability, EcmMode, RadarMode};
 use rand::Rng;
 use rand_distr::StandardNormal;
 use rapier2d_f64::parry;
 use rapier2d_f64::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::f64::consts::TAU;
 use std::ops::Range;
@@ -18,8 +19,13 @@ const JAMMER_COEFF: f64 = 1e-9; // Account for frequency hopping and pulse lengt
 const BEARING_NOISE_FACTOR: f64 = 1e1 * (TAU / 360.0);
 const DISTANCE_NOISE_FACTOR: f64 = 1e4;
 const VELOCITY_NOISE_FACTOR: f64 = 1e2;
+// Track-while-scan spreads the radar's dwell time across many contacts,
+// reducing range by ~30% and adding 3 dB of noise.
+const TWS_RSSI_FACTOR: f64 = 0.25;
+const TWS_NOISE_FACTOR: f64 = 2.0;
+const ESM_BEARING_NOISE_FACTOR: f64 = TAU / 360.0;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Radar {
     pub heading: f64,
     pub width: f64,
@@ -33,6 +39,11 @@ pub struct Radar {
     pub min_rssi: f64,
     pub ecm_mode: EcmMode,
     pub result: Option<ScanResult>,
+    pub mode: RadarMode,
+    // Zero if track-while-scan is unsupported.
+    pub max_contacts: usize,
+    pub contacts: Vec<ScanResult>,
+    pub enabled: bool,
 }
 
 impl Default for Radar {
@@ -50,6 +61,10 @@ impl Default for Radar {
             min_rssi: from_dbm(-100.0),
             ecm_mode: EcmMode::None,
             result: None,
+            mode: RadarMode::Single,
+            max_contacts: 0,
+            contacts: vec![],
+            enabled: true,
         }
     }
 }
@@ -91,9 +106,55 @@ impl Radar {
         self.ecm_mode = mode;
     }
 
+    pub fn set_mode(&mut self, mode: RadarMode) {
+        self.mode = if self.max_contacts > 0 {
+            mode
+        } else {
+            RadarMode::Single
+        };
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
     pub fn scan(&self) -> Option<ScanResult> {
         self.result
     }
+
+    pub fn scan_all(&self) -> &[ScanResult] {
+        &self.contacts
+    }
+}
+
+// Passive receiver that detects enemy radars illuminating the ship.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Esm {
+    pub rx_cross_section: f64,
+    pub min_rssi: f64,
+    pub contacts: Vec<EsmContact>,
+}
+
+impl Default for Esm {
+    fn default() -> Self {
+        Esm {
+            rx_cross_section: 10.0,
+            min_rssi: from_dbm(-100.0),
+            contacts: vec![],
+        }
+    }
+}
+
+impl Esm {
+    pub fn contacts(&self) -> &[EsmContact] {
+        &self.contacts
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct EsmContact {
+    pub bearing: f64,
+    pub rssi: f64,
 }
 
 struct RadarEmitter {
@@ -131,7 +192,7 @@ struct RadarJammer {
     ecm_mode: EcmMode,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct ScanResult {
     pub class: ShipClass,
     pub position: Vector2<f64>,
@@ -147,7 +208,7 @@ struct ReflectorTeam {
     reflectors: Vec<RadarReflector>,
 }
 
-fn into_dbm(x: f64) -> f64 {
+pub(crate) fn into_dbm(x: f64) -> f64 {
     10.0 * ComplexField::log10(x) + 30.0
 }
 
@@ -175,6 +236,7 @@ fn build_reflector_team(sim: &Simulation) -> Vec<ReflectorTeam> {
         let jammer = ship_data
             .radar
             .as_ref()
+            .filter(|radar| radar.enabled)
             .and_then(|radar| match radar.ecm_mode {
                 EcmMode::None => None,
                 _ => Some(RadarJammer {
@@ -248,13 +310,25 @@ pub fn tick(sim: &mut Simulation) {
         .cloned()
         .collect::<Vec<_>>();
 
+    let mut esm_emitters: Vec<EsmEmitter> = Vec::new();
+
     for handle in handle_snapshot.iter().cloned() {
         let ship = sim.ship(handle);
         let ship_data = ship.data();
 
         if let Some(radar) = ship_data.radar.as_ref() {
+            if !radar.enabled {
+                let mut ship = sim.ship_mut(handle);
+                let radar = ship.data_mut().radar.as_mut().unwrap();
+                radar.result = None;
+                radar.contacts.clear();
+                continue;
+            }
+
             let h = radar.heading;
             let w = radar.width;
+            let tws = radar.mode == RadarMode::TrackWhileScan;
+            let max_contacts = radar.max_contacts;
             assert!(w < TAU / 2.0);
             let max_distance = compute_max_detection_range(radar, 40.0 /*cruiser*/)
                 .min(radar.max_distance)
@@ -288,12 +362,21 @@ pub fn tick(sim: &mut Simulation) {
                 rays,
             };
 
+            esm_emitters.push(EsmEmitter {
+                team: emitter.team,
+                center: emitter.center,
+                bearing: emitter.bearing,
+                width: emitter.width,
+                power: emitter.power,
+            });
+
             if radar.ecm_mode != EcmMode::None {
                 {
                     let mut ship = sim.ship_mut(emitter.handle);
                     let ship_data = ship.data_mut();
                     let radar = ship_data.radar.as_mut().unwrap();
                     radar.result = None;
+                    radar.contacts.clear();
                 }
                 draw_emitter(sim, &emitter, reliable_distance);
                 continue;
@@ -303,6 +386,7 @@ pub fn tick(sim: &mut Simulation) {
 
             let mut best_rssi = emitter.min_rssi;
             let mut best_reflector: Option<&RadarReflector> = None;
+            let mut tws_candidates: Vec<(&RadarReflector, f64)> = Vec::new();
             let mut received_noise =
                 BACKGROUND_NOISE * ComplexField::powf(2.0f64, rng.gen_range(-1.0..1.0));
             candidates.clear();
@@ -347,8 +431,14 @@ pub fn tick(sim: &mut Simulation) {
                         &reflector.position,
                     ))
                 {
-                    let rssi = compute_rssi(&emitter, reflector)
+                    let mut rssi = compute_rssi(&emitter, reflector)
                         * ComplexField::powf(1.2f64, rng.gen_range(-1.0..1.0));
+                    if tws {
+                        rssi *= TWS_RSSI_FACTOR;
+                        if rssi > emitter.min_rssi {
+                            tws_candidates.push((reflector, rssi));
+                        }
+                    }
                     if rssi > best_rssi {
                         best_reflector = Some(reflector);
                         best_rssi = rssi;
@@ -356,6 +446,10 @@ pub fn tick(sim: &mut Simulation) {
                 }
             }
 
+            if tws {
+                received_noise *= TWS_NOISE_FACTOR;
+            }
+
             let best_rssi_dbm = into_dbm(best_rssi);
             let received_noise_dbm = into_dbm(received_noise);
             let signal_db = best_rssi_dbm - received_noise_dbm;
@@ -393,19 +487,109 @@ pub fn tick(sim: &mut Simulation) {
                 })
             };
 
+            let contacts: Vec<ScanResult> = if tws {
+                tws_candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+                tws_candidates
+                    .iter()
+                    .filter_map(|&(reflector, rssi)| {
+                        let rssi_dbm = into_dbm(rssi);
+                        if rssi_dbm - received_noise_dbm < 3.0
+                            || (rssi < emitter.reliable_rssi
+                                && decide_unreliable_rssi(&mut rng, rssi, emitter.reliable_rssi))
+                        {
+                            None
+                        } else {
+                            Some(make_scan_result(
+                                &emitter,
+                                reflector,
+                                rssi_dbm,
+                                received_noise_dbm,
+                                &mut rng,
+                            ))
+                        }
+                    })
+                    .take(max_contacts)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            draw_emitter(sim, &emitter, reliable_distance);
+            if let Some(contact) = &result {
+                draw_contact(sim, emitter.handle, contact);
+            }
+            for contact in contacts.iter() {
+                draw_contact(sim, emitter.handle, contact);
+            }
+
             {
                 let mut ship = sim.ship_mut(emitter.handle);
                 let ship_data = ship.data_mut();
                 let radar = ship_data.radar.as_mut().unwrap();
                 radar.result = result;
-            }
-
-            draw_emitter(sim, &emitter, reliable_distance);
-            if let Some(contact) = &result {
-                draw_contact(sim, emitter.handle, contact);
+                radar.contacts = contacts;
             }
         }
     }
+
+    tick_esm(sim, &handle_snapshot, &esm_emitters);
+}
+
+struct EsmEmitter {
+    team: i32,
+    center: Point2<f64>,
+    bearing: f64,
+    width: f64,
+    power: f64,
+}
+
+#[inline(never)]
+fn tick_esm(sim: &mut Simulation, handles: &[ShipHandle], emitters: &[EsmEmitter]) {
+    let mut rng = rng::new_rng(sim.tick());
+    for &handle in handles.iter() {
+        let ship = sim.ship(handle);
+        let ship_data = ship.data();
+        let esm = match ship_data.esm.as_ref() {
+            Some(esm) => esm,
+            None => continue,
+        };
+        let position: Point2<f64> = ship.position().vector.into();
+
+        // One-way propagation, so ESM hears a radar well beyond the range at
+        // which that radar could detect the receiver.
+        let mut received: Vec<(&EsmEmitter, f64)> = emitters
+            .iter()
+            .filter(|emitter| emitter.team != ship_data.team)
+            .filter(|emitter| {
+                check_inside_beam_raw(&emitter.center, emitter.bearing, emitter.width, &position)
+            })
+            .map(|emitter| {
+                let r_sq = nalgebra::distance_squared(&emitter.center, &position);
+                let rssi =
+                    emitter.power * esm.rx_cross_section / (TAU * emitter.width * r_sq.max(1.0));
+                (emitter, rssi)
+            })
+            .filter(|&(_, rssi)| rssi > esm.min_rssi)
+            .collect();
+        received.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let contacts: Vec<EsmContact> = received
+            .iter()
+            .take(oort_api::MAX_ESM_CONTACTS)
+            .map(|&(emitter, rssi)| {
+                let dp = emitter.center - position;
+                EsmContact {
+                    bearing: (dp.y.atan2(dp.x)
+                        + rng.sample::<f64, _>(StandardNormal) * ESM_BEARING_NOISE_FACTOR)
+                        .rem_euclid(TAU),
+                    rssi: into_dbm(rssi * ComplexField::powf(2.0f64, rng.gen_range(-1.0..1.0))),
+                }
+            })
+            .collect();
+
+        let mut ship = sim.ship_mut(handle);
+        ship.data_mut().esm.as_mut().unwrap().contacts = contacts;
+    }
 }
 
 #[inline(never)]
@@ -657,7 +841,7 @@ mod test {
     use crate::simulation::Code;
     use crate::simulation::Simulation;
     use nalgebra::{vector, UnitComplex};
-    use oort_api::EcmMode;
+    use oort_api::{EcmMode, RadarMode};
     use rand::Rng;
     use std::f64::consts::{PI, TAU};
     use test_log::test;
@@ -884,4 +1068,186 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_track_while_scan() {
+        let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+        let ship0 = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::cruiser(0),
+        );
+        for i in 0..10 {
+            ship::create(
+                &mut sim,
+                vector![5000.0, -900.0 + 200.0 * i as f64],
+                vector![0.0, 0.0],
+                0.0,
+                ship::fighter(1),
+            );
+        }
+        sim.ship_mut(ship0).radar_mut().unwrap().heading = 0.0;
+        sim.ship_mut(ship0).radar_mut().unwrap().width = TAU / 8.0;
+
+        sim.step();
+        let radar = sim.ship(ship0).radar().unwrap();
+        assert!(radar.result.is_some());
+        assert!(radar.scan_all().is_empty());
+
+        sim.ship_mut(ship0)
+            .radar_mut()
+            .unwrap()
+            .set_mode(RadarMode::TrackWhileScan);
+        sim.step();
+        let radar = sim.ship(ship0).radar().unwrap();
+        let contacts = radar.scan_all();
+        assert_eq!(contacts.len(), oort_api::MAX_RADAR_CONTACTS);
+        assert!(contacts.windows(2).all(|w| w[0].rssi >= w[1].rssi));
+        for contact in contacts {
+            assert_eq!(contact.class, ShipClass::Fighter);
+            assert!((contact.position - vector![5000.0, 0.0]).magnitude() < 1500.0);
+        }
+    }
+
+    #[test]
+    fn test_track_while_scan_unsupported() {
+        let mut radar = ship::fighter(0).radar.unwrap();
+        radar.set_mode(RadarMode::TrackWhileScan);
+        assert_eq!(radar.mode, RadarMode::Single);
+
+        let mut radar = ship::frigate(0).radar.unwrap();
+        radar.set_mode(RadarMode::TrackWhileScan);
+        assert_eq!(radar.mode, RadarMode::TrackWhileScan);
+    }
+
+    #[test]
+    fn test_track_while_scan_range() {
+        let check_detection = |mode, range| {
+            let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+            let offset = vector![-90e3, 0.0];
+            let ship0 = ship::create(
+                &mut sim,
+                vector![0.0, 0.0] + offset,
+                vector![0.0, 0.0],
+                0.0,
+                ship::cruiser(0),
+            );
+            ship::create(
+                &mut sim,
+                vector![range, 0.0] + offset,
+                vector![0.0, 0.0],
+                0.0,
+                ship::fighter(1),
+            );
+            sim.ship_mut(ship0).radar_mut().unwrap().heading = 0.0;
+            sim.ship_mut(ship0).radar_mut().unwrap().width = TAU / 4.0;
+            sim.ship_mut(ship0).radar_mut().unwrap().set_mode(mode);
+
+            (0..100)
+                .map(|_| {
+                    sim.step();
+                    let radar = sim.ship(ship0).radar().unwrap();
+                    match mode {
+                        RadarMode::Single => radar.result.is_some(),
+                        RadarMode::TrackWhileScan => !radar.scan_all().is_empty(),
+                    }
+                })
+                .filter(|x| *x)
+                .count()
+                > 50
+        };
+
+        assert!(check_detection(RadarMode::Single, 20e3));
+        assert!(check_detection(RadarMode::TrackWhileScan, 20e3));
+        assert!(check_detection(RadarMode::Single, 40e3));
+        assert!(!check_detection(RadarMode::TrackWhileScan, 40e3));
+    }
+
+    #[test]
+    fn test_esm() {
+        let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+        let ship0 = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        let ship1 = ship::create(
+            &mut sim,
+            vector![0.0, 20e3],
+            vector![0.0, 0.0],
+            0.0,
+            ship::frigate(1),
+        );
+
+        // Enemy radar pointed away.
+        sim.ship_mut(ship0).radar_mut().unwrap().heading = PI / 2.0;
+        sim.ship_mut(ship1).radar_mut().unwrap().heading = PI / 2.0;
+        sim.step();
+        assert!(esm_contacts_of(&sim, ship0).is_empty());
+
+        // Enemy radar pointed at us. Our own radar isn't detected.
+        sim.ship_mut(ship1).radar_mut().unwrap().heading = -PI / 2.0;
+        sim.step();
+        let contacts = esm_contacts_of(&sim, ship0);
+        assert_eq!(contacts.len(), 1);
+        assert!((contacts[0].bearing - PI / 2.0).abs() < 0.1);
+        assert!(contacts[0].rssi > -100.0);
+        assert!(sim.ship(ship0).radar().unwrap().result.is_some());
+        assert_eq!(esm_contacts_of(&sim, ship1).len(), 1);
+
+        // Enemy radar disabled.
+        sim.ship_mut(ship1).radar_mut().unwrap().set_enabled(false);
+        sim.step();
+        assert!(esm_contacts_of(&sim, ship0).is_empty());
+        assert!(sim.ship(ship1).radar().unwrap().result.is_none());
+
+        // Our radar disabled, enemy can't hear us but we can still hear them.
+        sim.ship_mut(ship1).radar_mut().unwrap().set_enabled(true);
+        sim.ship_mut(ship0).radar_mut().unwrap().set_enabled(false);
+        sim.step();
+        assert_eq!(esm_contacts_of(&sim, ship0).len(), 1);
+        assert!(esm_contacts_of(&sim, ship1).is_empty());
+        assert!(sim.ship(ship0).radar().unwrap().result.is_none());
+    }
+
+    #[test]
+    fn test_esm_range() {
+        // ESM hears a fighter's radar well beyond the radar's own detection range.
+        let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+        let offset = vector![-90e3, 0.0];
+        let ship0 = ship::create(
+            &mut sim,
+            vector![0.0, 0.0] + offset,
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        let ship1 = ship::create(
+            &mut sim,
+            vector![100e3, 0.0] + offset,
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(1),
+        );
+        sim.ship_mut(ship0).radar_mut().unwrap().heading = 0.0;
+        sim.ship_mut(ship1).radar_mut().unwrap().heading = PI;
+        sim.step();
+        assert!(sim.ship(ship0).radar().unwrap().result.is_none());
+        assert_eq!(esm_contacts_of(&sim, ship0).len(), 1);
+        assert_eq!(esm_contacts_of(&sim, ship1).len(), 1);
+    }
+
+    fn esm_contacts_of(sim: &Simulation, handle: ship::ShipHandle) -> Vec<super::EsmContact> {
+        sim.ship(handle)
+            .data()
+            .esm
+            .as_ref()
+            .unwrap()
+            .contacts
+            .clone()
+    }
 }
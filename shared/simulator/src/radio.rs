@@ -1,20 +1,49 @@
+use crate::radar::into_dbm;
+use crate::rng;
 use crate::ship::ShipHandle;
-use crate::simulation::Simulation;
+use crate::simulation::{Simulation, PHYSICS_TICK_LENGTH};
 use nalgebra::Point2;
-use oort_api::Message;
+use oort_api::{EcmMode, Message};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::f64::consts::TAU;
 
 const NUM_CHANNELS: usize = 10;
 
-#[derive(Clone, Debug)]
+// How radio messages propagate, chosen by the scenario. The default delivers
+// every message on the next tick as long as it's above the receiver's
+// sensitivity.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RadioModel {
+    // Speed (m/s) at which messages travel. None for instant delivery.
+    #[serde(default)]
+    pub speed: Option<f64>,
+    // Randomly drop messages received below the reliable signal strength.
+    #[serde(default)]
+    pub packet_loss: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Radio {
     pub(crate) power: f64,
     pub(crate) rx_cross_section: f64,
     pub(crate) min_rssi: f64,
+    pub(crate) reliable_rssi: f64,
     pub(crate) channel: usize,
+    pub(crate) ecm_mode: EcmMode,
     pub(crate) sent: Option<Message>,
     pub(crate) received: Option<Message>,
+    pub(crate) in_flight: Vec<InFlightMessage>,
+}
+
+// A delayed message on its way to a receiving radio.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct InFlightMessage {
+    arrival_tick: u32,
+    channel: usize,
+    rssi: f64,
+    msg: Message,
 }
 
 impl Radio {
@@ -26,6 +55,14 @@ impl Radio {
         self.channel = channel.clamp(0, NUM_CHANNELS - 1);
     }
 
+    pub fn get_ecm_mode(&self) -> EcmMode {
+        self.ecm_mode
+    }
+
+    pub fn set_ecm_mode(&mut self, mode: EcmMode) {
+        self.ecm_mode = mode;
+    }
+
     pub fn set_sent(&mut self, sent: Option<Message>) {
         self.sent = sent;
     }
@@ -41,25 +78,45 @@ struct RadioSender {
     msg: Message,
 }
 
+struct RadioJammer {
+    position: Point2<f64>,
+    power: f64,
+}
+
 struct RadioReceiver {
     handle: ShipHandle,
     radio_index: usize,
     position: Point2<f64>,
     rx_cross_section: f64,
     min_rssi: f64,
+    reliable_rssi: f64,
 }
 
 #[inline(never)]
 pub fn tick(sim: &mut Simulation) {
     let handle_snapshot: Vec<ShipHandle> = sim.ships.iter().cloned().collect();
+    let model = sim.radio_model();
+    let current_tick = sim.tick();
+    let mut rng = rng::new_rng(current_tick);
 
     let mut receivers: BTreeMap<usize, Vec<RadioReceiver>> = BTreeMap::new();
     let mut senders: BTreeMap<usize, Vec<RadioSender>> = BTreeMap::new();
+    let mut jammers: BTreeMap<usize, Vec<RadioJammer>> = BTreeMap::new();
 
     for handle in handle_snapshot.iter().cloned() {
         let ship = sim.ship(handle);
         let ship_data = ship.data();
         for (radio_index, radio) in ship_data.radios.iter().enumerate() {
+            // A jamming radio transmits noise instead of messages and can't
+            // receive anything itself.
+            if radio.ecm_mode != EcmMode::None {
+                jammers.entry(radio.channel).or_default().push(RadioJammer {
+                    position: ship.position().vector.into(),
+                    power: radio.power,
+                });
+                continue;
+            }
+
             receivers
                 .entry(radio.channel)
                 .or_default()
@@ -69,6 +126,7 @@ pub fn tick(sim: &mut Simulation) {
                     position: ship.position().vector.into(),
                     rx_cross_section: radio.rx_cross_section,
                     min_rssi: radio.min_rssi,
+                    reliable_rssi: radio.reliable_rssi,
                 });
 
             if let Some(msg) = radio.sent {
@@ -81,22 +139,68 @@ pub fn tick(sim: &mut Simulation) {
         }
     }
 
+    for handle in handle_snapshot.iter().cloned() {
+        for radio in sim.ship_mut(handle).data_mut().radios.iter_mut() {
+            radio.received = None;
+        }
+    }
+
     for channel in 0..NUM_CHANNELS {
         for rx in receivers.get(&channel).unwrap_or(&Vec::new()) {
-            let mut best_msg = None;
-            let mut best_rssi = rx.min_rssi;
+            let mut ship = sim.ship_mut(rx.handle);
+            let radio = ship.radio_mut(rx.radio_index).unwrap();
+
+            let mut best: Option<(f64, Message)> = None;
+            let mut consider = |rssi: f64, msg: Message| {
+                if rssi > rx.min_rssi && best.map(|(x, _)| rssi > x).unwrap_or(true) {
+                    best = Some((rssi, msg));
+                }
+            };
+
             for tx in senders.get(&channel).unwrap_or(&Vec::new()) {
-                let rssi = compute_rssi(tx, rx);
-                if rssi > best_rssi {
-                    best_rssi = rssi;
-                    best_msg = Some(tx.msg);
+                let rssi = compute_rssi(tx.position, tx.power, rx);
+                let delay = match model.speed {
+                    Some(speed) => compute_delay_ticks(&tx.position, &rx.position, speed),
+                    None => 0,
+                };
+                if delay == 0 {
+                    consider(rssi, tx.msg);
+                } else if rssi > rx.min_rssi {
+                    radio.in_flight.push(InFlightMessage {
+                        arrival_tick: current_tick + delay,
+                        channel,
+                        rssi,
+                        msg: tx.msg,
+                    });
                 }
             }
-            sim.ship_mut(rx.handle)
-                .radio_mut(rx.radio_index)
-                .as_mut()
-                .unwrap()
-                .received = best_msg;
+
+            // Messages that were sent on another channel are lost.
+            radio.in_flight.retain(|x| {
+                if x.arrival_tick > current_tick {
+                    return true;
+                }
+                if x.channel == channel {
+                    consider(x.rssi, x.msg);
+                }
+                false
+            });
+
+            let noise: f64 = jammers
+                .get(&channel)
+                .unwrap_or(&Vec::new())
+                .iter()
+                .map(|jammer| compute_rssi(jammer.position, jammer.power, rx))
+                .sum();
+
+            radio.received = best
+                .filter(|&(rssi, _)| noise <= 0.0 || into_dbm(rssi) - into_dbm(noise) >= 3.0)
+                .filter(|&(rssi, _)| {
+                    !model.packet_loss
+                        || rssi >= rx.reliable_rssi
+                        || !rng.gen_bool(loss_probability(rssi, rx))
+                })
+                .map(|(_, msg)| msg);
         }
     }
 
@@ -107,17 +211,30 @@ pub fn tick(sim: &mut Simulation) {
     }
 }
 
-fn compute_rssi(sender: &RadioSender, receiver: &RadioReceiver) -> f64 {
-    let r_sq = nalgebra::distance_squared(&sender.position, &receiver.position);
-    sender.power * receiver.rx_cross_section / (TAU * r_sq)
+fn compute_rssi(position: Point2<f64>, power: f64, receiver: &RadioReceiver) -> f64 {
+    let r_sq = nalgebra::distance_squared(&position, &receiver.position).max(1.0);
+    power * receiver.rx_cross_section / (TAU * r_sq)
+}
+
+fn compute_delay_ticks(a: &Point2<f64>, b: &Point2<f64>, speed: f64) -> u32 {
+    (nalgebra::distance(a, b) / (speed * PHYSICS_TICK_LENGTH)) as u32
+}
+
+// Rises linearly (in dB) from zero at the reliable signal strength to one at
+// the receiver's sensitivity.
+fn loss_probability(rssi: f64, receiver: &RadioReceiver) -> f64 {
+    let margin = into_dbm(receiver.reliable_rssi) - into_dbm(receiver.min_rssi);
+    ((into_dbm(receiver.reliable_rssi) - into_dbm(rssi)) / margin).clamp(0.0, 1.0)
 }
 
 #[cfg(test)]
 mod test {
+    use crate::scenario::FileScenario;
     use crate::ship;
     use crate::simulation::Code;
     use crate::simulation::Simulation;
     use nalgebra::vector;
+    use oort_api::EcmMode;
     use test_log::test;
 
     #[test]
@@ -238,4 +355,133 @@ mod test {
             );
         }
     }
+
+    #[test]
+    fn test_delay() {
+        let scenario =
+            FileScenario::from_ron("(name: \"radio_test\", radio: (speed: Some(1000.0)))").unwrap();
+        let mut sim = Simulation::new_with_scenario(Box::new(scenario), 0, &[Code::None]);
+
+        let ship0 = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        let ship1 = ship::create(
+            &mut sim,
+            vector![1000.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+
+        let msg = [42.0, 43.0, 44.0, 45.0];
+        sim.ship_mut(ship1).radio_mut(0).unwrap().sent = Some(msg);
+        sim.step();
+        assert_eq!(sim.ship(ship1).radio(0).unwrap().received, Some(msg));
+        assert!(sim.ship(ship0).radio(0).unwrap().received.is_none());
+
+        // 1 km at 1 km/s takes 60 ticks.
+        for _ in 0..59 {
+            sim.step();
+            assert!(sim.ship(ship0).radio(0).unwrap().received.is_none());
+        }
+        sim.step();
+        assert_eq!(sim.ship(ship0).radio(0).unwrap().received, Some(msg));
+        sim.step();
+        assert!(sim.ship(ship0).radio(0).unwrap().received.is_none());
+    }
+
+    #[test]
+    fn test_packet_loss() {
+        let count_received = |scenario: FileScenario, distance: f64| {
+            let mut sim = Simulation::new_with_scenario(Box::new(scenario), 0, &[Code::None]);
+            let ship0 = ship::create(
+                &mut sim,
+                vector![-distance / 2.0, 0.0],
+                vector![0.0, 0.0],
+                0.0,
+                ship::fighter(0),
+            );
+            let ship1 = ship::create(
+                &mut sim,
+                vector![distance / 2.0, 0.0],
+                vector![0.0, 0.0],
+                0.0,
+                ship::fighter(0),
+            );
+            (0..100)
+                .filter(|_| {
+                    sim.ship_mut(ship1).radio_mut(0).unwrap().sent = Some([1.0; 4]);
+                    sim.step();
+                    sim.ship(ship0).radio(0).unwrap().received.is_some()
+                })
+                .count()
+        };
+        let lossy = || {
+            FileScenario::from_ron("(name: \"radio_test\", radio: (packet_loss: true))").unwrap()
+        };
+        let lossless = || FileScenario::from_ron("(name: \"radio_test\")").unwrap();
+
+        assert_eq!(count_received(lossless(), 35e3), 100);
+        assert_eq!(count_received(lossy(), 20e3), 100);
+        let n = count_received(lossy(), 35e3);
+        assert!(n > 10 && n < 90, "received {n} messages");
+        assert_eq!(count_received(lossy(), 45e3), 0);
+    }
+
+    #[test]
+    fn test_jamming() {
+        let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+
+        let ship0 = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        let ship1 = ship::create(
+            &mut sim,
+            vector![1000.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+        let jammer = ship::create(
+            &mut sim,
+            vector![0.0, 500.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(1),
+        );
+
+        let msg = [42.0, 43.0, 44.0, 45.0];
+        let send = |sim: &mut Simulation| {
+            sim.ship_mut(ship1).radio_mut(0).unwrap().sent = Some(msg);
+            sim.step();
+            sim.ship(ship0).radio(0).unwrap().received
+        };
+
+        assert_eq!(send(&mut sim), Some(msg));
+
+        sim.ship_mut(jammer)
+            .radio_mut(0)
+            .unwrap()
+            .set_ecm_mode(EcmMode::Noise);
+        assert_eq!(send(&mut sim), None);
+
+        // Jamming another channel.
+        sim.ship_mut(jammer).radio_mut(0).unwrap().set_channel(1);
+        assert_eq!(send(&mut sim), Some(msg));
+
+        // Jammer too far away.
+        sim.ship_mut(jammer).radio_mut(0).unwrap().set_channel(0);
+        sim.ship_mut(jammer)
+            .body()
+            .set_translation(vector![0.0, 10e3], true);
+        assert_eq!(send(&mut sim), Some(msg));
+    }
 }
@@ -0,0 +1,385 @@
+// Gym-style environment for training policies that control one team.
+//
+// Each step the policy receives an observation per ship and returns actions,
+// which are applied the same way the VM applies a ship's system state. The
+// other teams run the scenario's usual code.
+use crate::scenario::{self, Status};
+use crate::ship::ShipHandle;
+use crate::simulation::{Code, Simulation};
+use crate::vm::{apply_system_state, generate_system_state, LocalSystemState};
+use nalgebra::Vector2;
+use oort_api::SystemState;
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+
+pub const OBSERVATION_SIZE: usize = SystemState::Size as usize;
+
+// A ship's system state, indexed by SystemState.
+#[derive(Clone, Debug)]
+pub struct Observation {
+    pub ship: u64,
+    pub values: Vec<f64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Action {
+    pub ship: u64,
+    pub accelerate: Vector2<f64>,
+    pub torque: f64,
+    // Heading to aim each gun at. Only used when firing.
+    pub aim: [f64; 4],
+    pub fire: [bool; 4],
+    pub radar_heading: Option<f64>,
+    pub radar_width: Option<f64>,
+    pub explode: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub observations: Vec<Observation>,
+    pub reward: f64,
+    pub done: bool,
+}
+
+pub trait Reward: Send {
+    fn reset(&mut self, _sim: &Simulation, _team: i32) {}
+
+    // Returns the reward for the step that just finished.
+    fn reward(&mut self, sim: &Simulation, team: i32) -> f64;
+}
+
+impl<F: FnMut(&Simulation, i32) -> f64 + Send> Reward for F {
+    fn reward(&mut self, sim: &Simulation, team: i32) -> f64 {
+        self(sim, team)
+    }
+}
+
+// Damage dealt by the team during the step.
+#[derive(Default)]
+pub struct DamageDealt {
+    total: f64,
+}
+
+impl Reward for DamageDealt {
+    fn reset(&mut self, sim: &Simulation, team: i32) {
+        self.total = sim.stats().team(team).damage_dealt;
+    }
+
+    fn reward(&mut self, sim: &Simulation, team: i32) -> f64 {
+        let total = sim.stats().team(team).damage_dealt;
+        let reward = total - self.total;
+        self.total = total;
+        reward
+    }
+}
+
+// 1 for every step the team has a ship alive.
+pub struct Survival;
+
+impl Reward for Survival {
+    fn reward(&mut self, sim: &Simulation, team: i32) -> f64 {
+        let alive = sim
+            .ships
+            .iter()
+            .any(|&handle| sim.ship(handle).data().team == team);
+        if alive {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+// 1 for a victory and -1 for a loss, given on the final step.
+pub struct Outcome;
+
+impl Reward for Outcome {
+    fn reward(&mut self, sim: &Simulation, team: i32) -> f64 {
+        match sim.status() {
+            Status::Victory { team: winner } if winner == team => 1.0,
+            Status::Victory { .. } | Status::Failed => -1.0,
+            Status::Running | Status::Draw => 0.0,
+        }
+    }
+}
+
+pub struct Env {
+    team: i32,
+    reward: Box<dyn Reward>,
+    sim: Option<Box<Simulation>>,
+    states: BTreeMap<u64, (ShipHandle, LocalSystemState)>,
+}
+
+impl Env {
+    pub fn new(team: i32, reward: impl Reward + 'static) -> Self {
+        Self {
+            team,
+            reward: Box::new(reward),
+            sim: None,
+            states: BTreeMap::new(),
+        }
+    }
+
+    pub fn reset(&mut self, scenario_name: &str, seed: u32) -> anyhow::Result<Vec<Observation>> {
+        let scenario = scenario::load_safe(scenario_name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown scenario {:?}", scenario_name))?;
+        let mut codes = scenario.initial_code();
+        if let Some(code) = codes.get_mut(self.team as usize) {
+            *code = Code::None;
+        }
+        let sim = Simulation::new_with_scenario(scenario, seed, &codes);
+        self.reward.reset(&sim, self.team);
+        self.sim = Some(sim);
+        self.states.clear();
+        Ok(self.observe())
+    }
+
+    pub fn step(&mut self, actions: &[Action]) -> Step {
+        let sim = self.sim.as_mut().expect("step called before reset");
+        for action in actions {
+            if let Some((handle, state)) = self.states.get_mut(&action.ship) {
+                apply_action(sim, *handle, state, action);
+            }
+        }
+        sim.step();
+
+        let reward = self.reward.reward(sim, self.team);
+        let done = sim.status() != Status::Running || sim.tick() >= scenario::MAX_TICKS;
+        Step {
+            observations: self.observe(),
+            reward,
+            done,
+        }
+    }
+
+    pub fn simulation(&self) -> Option<&Simulation> {
+        self.sim.as_deref()
+    }
+
+    fn observe(&mut self) -> Vec<Observation> {
+        let sim = self.sim.as_mut().unwrap();
+        let handles: BTreeMap<u64, ShipHandle> = sim
+            .ships
+            .iter()
+            .filter(|&&handle| sim.ship(handle).data().team == self.team)
+            .map(|&handle| (handle.into(), handle))
+            .collect();
+        self.states.retain(|id, _| handles.contains_key(id));
+
+        let mut observations = vec![];
+        for (id, handle) in handles {
+            let (_, state) = self
+                .states
+                .entry(id)
+                .or_insert_with(|| (handle, LocalSystemState::new()));
+            generate_system_state(sim, handle, state);
+            observations.push(Observation {
+                ship: id,
+                values: state
+                    .state
+                    .iter()
+                    .map(|&bits| {
+                        let v = f64::from_bits(bits);
+                        if v.is_finite() {
+                            v
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect(),
+            });
+        }
+        observations
+    }
+}
+
+fn apply_action(
+    sim: &mut Simulation,
+    handle: ShipHandle,
+    state: &mut LocalSystemState,
+    action: &Action,
+) {
+    state.set(SystemState::AccelerateX, action.accelerate.x);
+    state.set(SystemState::AccelerateY, action.accelerate.y);
+    state.set(SystemState::Torque, action.torque);
+    for (i, (aim, fire)) in [
+        (SystemState::Aim0, SystemState::Fire0),
+        (SystemState::Aim1, SystemState::Fire1),
+        (SystemState::Aim2, SystemState::Fire2),
+        (SystemState::Aim3, SystemState::Fire3),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        state.set(aim, action.aim[i]);
+        state.set(fire, if action.fire[i] { 1.0 } else { 0.0 });
+    }
+    if let Some(heading) = action.radar_heading {
+        state.set(SystemState::RadarHeading, heading);
+    }
+    if let Some(width) = action.radar_width {
+        state.set(SystemState::RadarWidth, width);
+    }
+    state.set(SystemState::Explode, if action.explode { 1.0 } else { 0.0 });
+    apply_system_state(sim, handle, state);
+}
+
+// Steps many environments in parallel. Simulation isn't Send, so each Env is
+// created and stepped on the worker thread that owns it. There is one worker
+// per available core, each owning a contiguous chunk of the envs.
+pub struct VecEnv {
+    workers: Vec<Worker>,
+    num_envs: usize,
+}
+
+enum Request {
+    Reset {
+        scenario_name: String,
+        seeds: Vec<u32>,
+    },
+    Step(Vec<Vec<Action>>),
+}
+
+enum Response {
+    Reset(Vec<anyhow::Result<Vec<Observation>>>),
+    Step(Vec<Step>),
+}
+
+struct Worker {
+    num_envs: usize,
+    requests: Option<mpsc::Sender<Request>>,
+    responses: mpsc::Receiver<Response>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Worker {
+    fn new(num_envs: usize, new_env: Arc<dyn Fn() -> Env + Send + Sync>) -> Self {
+        let (request_sender, request_receiver) = mpsc::channel();
+        let (response_sender, response_receiver) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut envs: Vec<Env> = (0..num_envs).map(|_| new_env()).collect();
+            for request in request_receiver {
+                let response = match request {
+                    Request::Reset {
+                        scenario_name,
+                        seeds,
+                    } => Response::Reset(
+                        envs.iter_mut()
+                            .zip(seeds)
+                            .map(|(env, seed)| env.reset(&scenario_name, seed))
+                            .collect(),
+                    ),
+                    Request::Step(actions) => Response::Step(
+                        envs.iter_mut()
+                            .zip(actions)
+                            .map(|(env, actions)| env.step(&actions))
+                            .collect(),
+                    ),
+                };
+                if response_sender.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            num_envs,
+            requests: Some(request_sender),
+            responses: response_receiver,
+            thread: Some(thread),
+        }
+    }
+
+    fn send(&self, request: Request) {
+        self.requests
+            .as_ref()
+            .unwrap()
+            .send(request)
+            .expect("Environment thread exited");
+    }
+
+    fn receive(&self) -> Response {
+        self.responses.recv().expect("Environment thread exited")
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        // Closing the request channel stops the thread.
+        self.requests.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl VecEnv {
+    pub fn new(n: usize, new_env: impl Fn() -> Env + Send + Sync + 'static) -> Self {
+        let new_env: Arc<dyn Fn() -> Env + Send + Sync> = Arc::new(new_env);
+        let num_workers = thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1)
+            .min(n);
+        // The first n % num_workers workers take one extra env.
+        let workers = (0..num_workers)
+            .map(|i| {
+                let num_envs = n / num_workers + usize::from(i < n % num_workers);
+                Worker::new(num_envs, new_env.clone())
+            })
+            .collect();
+        Self {
+            workers,
+            num_envs: n,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_envs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_envs == 0
+    }
+
+    pub fn reset(
+        &mut self,
+        scenario_name: &str,
+        seeds: &[u32],
+    ) -> anyhow::Result<Vec<Vec<Observation>>> {
+        anyhow::ensure!(seeds.len() == self.num_envs, "Expected one seed per env");
+        let mut seeds = seeds.iter().cloned();
+        for worker in self.workers.iter() {
+            worker.send(Request::Reset {
+                scenario_name: scenario_name.to_string(),
+                seeds: seeds.by_ref().take(worker.num_envs).collect(),
+            });
+        }
+        let mut observations = Vec::with_capacity(self.num_envs);
+        for worker in self.workers.iter() {
+            match worker.receive() {
+                Response::Reset(results) => observations.extend(results),
+                Response::Step(_) => unreachable!(),
+            }
+        }
+        observations.into_iter().collect()
+    }
+
+    pub fn step(&mut self, actions: &[Vec<Action>]) -> Vec<Step> {
+        assert_eq!(actions.len(), self.num_envs, "Expected actions per env");
+        let mut actions = actions.iter().cloned();
+        for worker in self.workers.iter() {
+            worker.send(Request::Step(
+                actions.by_ref().take(worker.num_envs).collect(),
+            ));
+        }
+        let mut steps = Vec::with_capacity(self.num_envs);
+        for worker in self.workers.iter() {
+            match worker.receive() {
+                Response::Step(results) => steps.extend(results),
+                Response::Reset(_) => unreachable!(),
+            }
+        }
+        steps
+    }
+}
@@ -0,0 +1,55 @@
+use super::prelude::*;
+
+const NUM_TEAMS: usize = 4;
+
+pub struct FighterFreeForAll {}
+
+impl FighterFreeForAll {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Scenario for FighterFreeForAll {
+    fn name(&self) -> String {
+        "fighter_ffa".into()
+    }
+
+    fn human_name(&self) -> String {
+        "Fighter Free-for-All".into()
+    }
+
+    fn init(&mut self, sim: &mut Simulation, seed: u32) {
+        let mut rng = new_rng(seed);
+        let placements = place_ffa_teams(&mut rng, self.world_size(), NUM_TEAMS);
+
+        for (team, placement) in placements.into_iter().enumerate() {
+            let Placement { position, heading } = placement;
+            ship::create(
+                sim,
+                position,
+                vector![0.0, 0.0],
+                heading,
+                fighter(team as i32),
+            );
+        }
+    }
+
+    fn status(&self, sim: &Simulation) -> Status {
+        check_tournament_victory(sim)
+    }
+
+    fn initial_code(&self) -> Vec<Code> {
+        let mut codes = vec![reference_ai(); NUM_TEAMS];
+        codes[0] = empty_ai();
+        codes
+    }
+
+    fn solution(&self) -> Code {
+        reference_ai()
+    }
+
+    fn world_size(&self) -> f64 {
+        20000.0
+    }
+}
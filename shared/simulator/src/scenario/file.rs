@@ -0,0 +1,379 @@
+use std::path::Path;
+
+use super::prelude::*;
+use super::MAX_TICKS;
+use crate::ship::{ShipData, MAX_PLAYER_TEAMS};
+
+// A scenario described by a RON or TOML file instead of Rust code.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileScenario {
+    pub name: String,
+    #[serde(default)]
+    pub human_name: Option<String>,
+    #[serde(default = "default_world_size")]
+    pub world_size: f64,
+    #[serde(default = "default_max_ticks")]
+    pub max_ticks: u32,
+    #[serde(default)]
+    pub victory: VictoryRule,
+    #[serde(default)]
+    pub radio: RadioModel,
+    #[serde(default)]
+    pub force_fields: Vec<ForceField>,
+    // Indexed by team ID.
+    #[serde(default)]
+    pub teams: Vec<TeamSpec>,
+    // Ships not controlled by any team, e.g. asteroids and planets.
+    #[serde(default)]
+    pub neutral: Vec<ShipSpec>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VictoryRule {
+    #[default]
+    Tournament,
+    CapitalShipTournament,
+    Tutorial,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TeamSpec {
+    #[serde(default = "empty_ai")]
+    pub code: Code,
+    #[serde(default)]
+    pub limits: VmLimits,
+    #[serde(default)]
+    pub ships: Vec<ShipSpec>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShipSpec {
+    pub template: Template,
+    pub position: Vector2<f64>,
+    #[serde(default = "Vector2::zeros")]
+    pub velocity: Vector2<f64>,
+    #[serde(default)]
+    pub heading: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Template {
+    Fighter,
+    Frigate,
+    Cruiser,
+    Asteroid {
+        variant: i32,
+    },
+    Planet {
+        mass: f64,
+        // Attract other ships and bullets.
+        #[serde(default)]
+        gravity: bool,
+    },
+}
+
+fn default_world_size() -> f64 {
+    40000.0
+}
+
+fn default_max_ticks() -> u32 {
+    MAX_TICKS
+}
+
+impl Template {
+    fn create(self, team: i32) -> ShipData {
+        match self {
+            Template::Fighter => fighter(team),
+            Template::Frigate => frigate(team),
+            Template::Cruiser => cruiser(team),
+            Template::Asteroid { variant } => {
+                let mut data = asteroid(variant);
+                data.team = team;
+                data
+            }
+            Template::Planet { mass, .. } => ShipData {
+                team,
+                ..planet(mass)
+            },
+        }
+    }
+}
+
+impl FileScenario {
+    pub fn from_ron(text: &str) -> anyhow::Result<Self> {
+        Self::validate(ron::from_str(text)?)
+    }
+
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        Self::validate(toml::from_str(text)?)
+    }
+
+    // The format is picked from the file extension.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("ron") => Self::from_ron(&text),
+            Some("toml") => Self::from_toml(&text),
+            _ => anyhow::bail!("Unknown scenario file extension for {:?}", path),
+        }
+    }
+
+    fn validate(scenario: Self) -> anyhow::Result<Self> {
+        if scenario.name.is_empty() {
+            anyhow::bail!("Scenario name must not be empty");
+        }
+        if super::load_safe(&scenario.name).is_some() {
+            anyhow::bail!("Scenario name {:?} is already built in", scenario.name);
+        }
+        if scenario.world_size <= 0.0 {
+            anyhow::bail!("World size must be positive");
+        }
+        if scenario.teams.len() > MAX_PLAYER_TEAMS {
+            anyhow::bail!("At most {} teams are supported", MAX_PLAYER_TEAMS);
+        }
+        if scenario.max_ticks == 0 {
+            anyhow::bail!("Max ticks must be positive");
+        }
+        if scenario.radio.speed.map(|x| x <= 0.0).unwrap_or(false) {
+            anyhow::bail!("Radio speed must be positive");
+        }
+        for field in scenario.force_fields.iter() {
+            if let ForceField::ShipGravity { .. } = field {
+                anyhow::bail!("Use a planet with gravity instead of a ShipGravity field");
+            }
+        }
+        let limit = scenario.world_size / 2.0;
+        let ships = scenario
+            .teams
+            .iter()
+            .flat_map(|team| team.ships.iter())
+            .chain(scenario.neutral.iter());
+        for ship in ships {
+            if ship.position.x.abs() >= limit || ship.position.y.abs() >= limit {
+                anyhow::bail!("Ship at {:?} is outside the world", ship.position);
+            }
+        }
+        Ok(scenario)
+    }
+}
+
+impl Scenario for FileScenario {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn human_name(&self) -> String {
+        self.human_name.clone().unwrap_or_else(|| self.name())
+    }
+
+    fn init(&mut self, sim: &mut Simulation, _seed: u32) {
+        let ships = self
+            .teams
+            .iter()
+            .enumerate()
+            .flat_map(|(team, spec)| spec.ships.iter().map(move |ship| (team as i32, ship)))
+            .chain(self.neutral.iter().map(|ship| (NEUTRAL_TEAM, ship)));
+        for (team, ship) in ships {
+            let handle = ship::create(
+                sim,
+                ship.position,
+                ship.velocity,
+                ship.heading,
+                ship.template.create(team),
+            );
+            if let Template::Planet { gravity: true, .. } = ship.template {
+                sim.add_force_field(ForceField::ShipGravity { ship: handle });
+            }
+        }
+        for field in self.force_fields.iter() {
+            sim.add_force_field(field.clone());
+        }
+    }
+
+    fn status(&self, sim: &Simulation) -> Status {
+        let status = match self.victory {
+            VictoryRule::Tournament => check_tournament_victory(sim),
+            VictoryRule::CapitalShipTournament => check_capital_ship_tournament_victory(sim),
+            VictoryRule::Tutorial => check_tutorial_victory(sim, self.max_ticks),
+        };
+        if status == Status::Running && sim.tick() >= self.max_ticks - 1 {
+            Status::Draw
+        } else {
+            status
+        }
+    }
+
+    fn initial_code(&self) -> Vec<Code> {
+        if self.teams.is_empty() {
+            vec![empty_ai()]
+        } else {
+            self.teams.iter().map(|team| team.code.clone()).collect()
+        }
+    }
+
+    fn world_size(&self) -> f64 {
+        self.world_size
+    }
+
+    fn radio_model(&self) -> RadioModel {
+        self.radio
+    }
+
+    fn vm_limits(&self, team: i32) -> VmLimits {
+        self.teams
+            .get(team as usize)
+            .map(|team| team.limits.clone())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ship::ShipClass;
+
+    const RON: &str = r#"
+(
+    name: "test_arena",
+    world_size: 10000.0,
+    max_ticks: 600,
+    victory: Tournament,
+    radio: (speed: Some(20000.0), packet_loss: true),
+    force_fields: [Drag(center: [0.0, 0.0], radius: 500.0, coefficient: 0.1)],
+    teams: [
+        (
+            code: Builtin("reference"),
+            ships: [
+                (template: Fighter, position: [-1000.0, 0.0]),
+                (template: Frigate, position: [-1000.0, 500.0], heading: 1.0),
+            ],
+        ),
+        (
+            limits: (gas_per_tick: 100000, class_gas_per_tick: {Cruiser: 500000}),
+            ships: [(template: Cruiser, position: [1000.0, 0.0], velocity: [0.0, 10.0])],
+        ),
+    ],
+    neutral: [
+        (template: Asteroid(variant: 2), position: [0.0, 2000.0]),
+        (template: Planet(mass: 1e18, gravity: true), position: [0.0, -3000.0]),
+    ],
+)
+"#;
+
+    const TOML: &str = r#"
+name = "test_arena"
+victory = "CapitalShipTournament"
+
+[[teams]]
+code = { Builtin = "reference" }
+limits = { memory_size = 131072 }
+ships = [{ template = "Fighter", position = [-1000.0, 0.0] }]
+
+[[teams]]
+ships = [{ template = "Frigate", position = [1000.0, 0.0], heading = 3.0 }]
+
+[[neutral]]
+template = { Asteroid = { variant = 1 } }
+position = [0.0, 1000.0]
+"#;
+
+    #[test]
+    fn test_ron() {
+        let scenario = FileScenario::from_ron(RON).unwrap();
+        assert_eq!(scenario.name(), "test_arena");
+        assert_eq!(scenario.world_size(), 10000.0);
+        assert_eq!(scenario.max_ticks, 600);
+        assert_eq!(scenario.radio.speed, Some(20000.0));
+        assert!(scenario.radio.packet_loss);
+        assert_eq!(scenario.teams.len(), 2);
+        assert_eq!(scenario.teams[0].ships[1].heading, 1.0);
+        assert_eq!(scenario.teams[1].ships[0].velocity, vector![0.0, 10.0]);
+        assert_eq!(scenario.vm_limits(0), VmLimits::default());
+        let limits = scenario.vm_limits(1);
+        assert_eq!(limits.gas_per_tick(ShipClass::Fighter), 100000);
+        assert_eq!(limits.gas_per_tick(ShipClass::Cruiser), 500000);
+        assert_eq!(
+            scenario.neutral[1].template,
+            Template::Planet {
+                mass: 1e18,
+                gravity: true
+            }
+        );
+        assert_eq!(
+            scenario.initial_code(),
+            vec![builtin("reference"), empty_ai()]
+        );
+    }
+
+    #[test]
+    fn test_toml() {
+        let scenario = FileScenario::from_toml(TOML).unwrap();
+        assert_eq!(scenario.world_size(), 40000.0);
+        assert_eq!(scenario.max_ticks, MAX_TICKS);
+        assert_eq!(scenario.victory, VictoryRule::CapitalShipTournament);
+        assert_eq!(scenario.radio, RadioModel::default());
+        assert_eq!(scenario.teams[1].ships[0].template, Template::Frigate);
+        assert_eq!(scenario.vm_limits(0).memory_size, 131072);
+        assert_eq!(scenario.vm_limits(0).gas_per_tick, 1_000_000);
+        assert_eq!(
+            scenario.neutral[0].template,
+            Template::Asteroid { variant: 1 }
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(FileScenario::from_ron("(name: \"fighter_duel\")").is_err());
+        assert!(FileScenario::from_ron(
+            "(name: \"x\", world_size: 100.0, neutral: [(template: Fighter, position: [60.0, 0.0])])"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let scenario = FileScenario::from_ron(RON).unwrap();
+        let codes = scenario.initial_code();
+        let mut sim = Simulation::new_with_scenario(Box::new(scenario), 0, &codes);
+        assert_eq!(sim.ships.len(), 5);
+        assert_eq!(sim.force_fields().len(), 2);
+        for _ in 0..60 {
+            sim.step();
+        }
+        assert_eq!(sim.status(), Status::Running);
+
+        let enemies: Vec<_> = sim
+            .ships
+            .iter()
+            .cloned()
+            .filter(|&handle| sim.ship(handle).data().team == 1)
+            .collect();
+        for handle in enemies {
+            sim.ship_mut(handle).explode();
+        }
+        sim.step();
+        assert_eq!(sim.status(), Status::Victory { team: 0 });
+    }
+
+    #[test]
+    fn test_draw_at_max_ticks() {
+        let scenario = FileScenario::from_ron(
+            r#"(
+                name: "test_draw",
+                max_ticks: 60,
+                teams: [
+                    (ships: [(template: Fighter, position: [-5000.0, 0.0])]),
+                    (ships: [(template: Fighter, position: [5000.0, 0.0])]),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let codes = scenario.initial_code();
+        let mut sim = Simulation::new_with_scenario(Box::new(scenario), 0, &codes);
+        while sim.status() == Status::Running {
+            sim.step();
+        }
+        assert_eq!(sim.status(), Status::Draw);
+        assert_eq!(sim.tick(), 59);
+    }
+}
@@ -2,6 +2,8 @@ mod asteroid_duel;
 mod belt;
 mod cruiser_duel;
 mod fighter_duel;
+mod fighter_ffa;
+mod file;
 mod fleet;
 mod frigate_duel;
 mod gunnery;
@@ -28,13 +30,19 @@ mod tutorial_search;
 mod tutorial_squadron;
 mod welcome;
 
-use crate::ship::{asteroid, fighter, ShipAccessor, ShipClass, ShipData};
+use crate::radio::RadioModel;
+use crate::ship::{
+    asteroid, fighter, ShipAccessor, ShipClass, ShipData, MAX_PLAYER_TEAMS, NEUTRAL_TEAM,
+};
 use crate::simulation::{Code, Line, Simulation};
+use crate::vm::VmLimits;
 use nalgebra::{vector, Vector2};
 use rand::{seq::SliceRandom, Rng, RngCore};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub use file::{FileScenario, ShipSpec, TeamSpec, Template, VictoryRule};
+
 pub mod prelude {
     pub use super::Scenario;
     pub use super::Status;
@@ -43,15 +51,20 @@ pub mod prelude {
         check_capital_ship_tournament_victory, check_tournament_victory, check_tutorial_victory,
     };
     pub use super::{fighter_without_missiles, fighter_without_missiles_or_radar, target_asteroid};
-    pub use super::{place_teams, Placement};
+    pub use super::{place_ffa_teams, place_teams, Placement};
     pub use super::{DEFAULT_TUTORIAL_MAX_TICKS, TOURNAMENT_MAX_TICKS};
+    pub use crate::force_field::ForceField;
+    pub use crate::radio::RadioModel;
     pub use crate::rng::{new_rng, SeededRng};
     pub use crate::ship::{
-        self, asteroid, cruiser, fighter, frigate, missile, target, torpedo, ShipHandle,
+        self, asteroid, cruiser, fighter, frigate, missile, planet, target, torpedo, ShipHandle,
+        NEUTRAL_TEAM,
     };
     pub use crate::simulation::{Code, Line, Simulation};
+    pub use crate::vm::VmLimits;
     pub use nalgebra::{point, vector, Point2, Rotation2, Vector2};
     pub use rand::Rng;
+    pub use serde::{Deserialize, Serialize};
     pub use std::f64::consts::{PI, TAU};
 }
 
@@ -120,6 +133,24 @@ pub trait Scenario {
     fn world_size(&self) -> f64 {
         40000.0
     }
+
+    fn radio_model(&self) -> RadioModel {
+        Default::default()
+    }
+
+    fn vm_limits(&self, _team: i32) -> VmLimits {
+        Default::default()
+    }
+
+    // Scenarios with internal state (e.g. an RNG or ship handles) must
+    // override these for checkpoints to restore them.
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(vec![])
+    }
+
+    fn load_state(&mut self, _state: &[u8]) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
 pub fn load_safe(name: &str) -> Option<Box<dyn Scenario>> {
@@ -146,6 +177,7 @@ pub fn load_safe(name: &str) -> Option<Box<dyn Scenario>> {
         "primitive_duel" => Some(Box::new(primitive_duel::PrimitiveDuel::new())),
         "radar_duel" => Some(Box::new(radar_duel::RadarDuel::new())),
         "fighter_duel" => Some(Box::new(fighter_duel::FighterDuel::new())),
+        "fighter_ffa" => Some(Box::new(fighter_ffa::FighterFreeForAll::new())),
         "frigate_duel" => Some(Box::new(frigate_duel::FrigateDuel::new())),
         "cruiser_duel" => Some(Box::new(cruiser_duel::CruiserDuel::new())),
         "asteroid_duel" => Some(Box::new(asteroid_duel::AsteroidDuel::new())),
@@ -283,14 +315,14 @@ pub fn check_tutorial_victory(sim: &Simulation, max_ticks: u32) -> Status {
 pub fn check_tournament_victory(sim: &Simulation) -> Status {
     check_victory_with_filter(sim, TOURNAMENT_MAX_TICKS, |ship| {
         [ShipClass::Fighter, ShipClass::Frigate, ShipClass::Cruiser].contains(&ship.data().class)
-            && ship.data().team < 2
+            && ship.data().team != NEUTRAL_TEAM
     })
 }
 
 pub fn check_capital_ship_tournament_victory(sim: &Simulation) -> Status {
     check_victory_with_filter(sim, TOURNAMENT_MAX_TICKS, |ship| {
         [ShipClass::Frigate, ShipClass::Cruiser].contains(&ship.data().class)
-            && ship.data().team < 2
+            && ship.data().team != NEUTRAL_TEAM
     })
 }
 
@@ -334,3 +366,22 @@ pub fn place_teams(rng: &mut dyn RngCore, world_size: f64) -> Vec<Placement> {
     placements.shuffle(rng);
     placements
 }
+
+// Places teams evenly around a circle facing the center, with a random
+// rotation and a random assignment of teams to positions.
+pub fn place_ffa_teams(rng: &mut dyn RngCore, world_size: f64, num_teams: usize) -> Vec<Placement> {
+    assert!(num_teams <= MAX_PLAYER_TEAMS);
+    let r = world_size * 0.45;
+    let offset = rng.gen_range(0.0..std::f64::consts::TAU);
+    let mut placements: Vec<Placement> = (0..num_teams)
+        .map(|i| {
+            let angle = offset + std::f64::consts::TAU * i as f64 / num_teams as f64;
+            Placement {
+                position: vector![r * angle.cos(), r * angle.sin()],
+                heading: angle + std::f64::consts::PI,
+            }
+        })
+        .collect();
+    placements.shuffle(rng);
+    placements
+}
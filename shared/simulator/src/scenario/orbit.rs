@@ -1,13 +1,9 @@
 use nalgebra::ComplexField;
-use oort_api::{Class, ClassStats};
-use rapier2d_f64::prelude::RigidBody;
 
 use super::prelude::*;
-use crate::ship::{ShipClass, ShipData};
-use crate::simulation::PHYSICS_TICK_LENGTH;
+use crate::force_field::G;
 
 const PLANET_MASS: f64 = 1.5e19;
-const G: f64 = 6.674e-11;
 
 pub struct Orbit {}
 
@@ -44,47 +40,16 @@ impl Scenario for Orbit {
             );
         }
 
-        ship::create(
+        let planet_handle = ship::create(
             sim,
             vector![0.0, 0.0],
             vector![0.0, 0.0],
             0.0,
-            ShipData {
-                class: ShipClass::Planet,
-                team: 2,
-                health: 1e9,
-                radar_cross_section: 1e6,
-                ..ShipData::from(ClassStats {
-                    mass: PLANET_MASS,
-                    ..Class::Unknown.default_stats()
-                })
-            },
+            planet(PLANET_MASS),
         );
-    }
-
-    fn tick(&mut self, sim: &mut Simulation) {
-        let apply_gravity = |body: &mut RigidBody| {
-            let r = body.translation().norm();
-            let g = G * PLANET_MASS / (r * r);
-            let acc = body.translation().normalize() * -g;
-            let impulse = acc * body.mass() * PHYSICS_TICK_LENGTH;
-            body.apply_impulse(impulse, true);
-        };
-
-        let handles = sim.ships.iter().cloned().collect::<Vec<_>>();
-        for handle in handles {
-            let mut ship = sim.ship_mut(handle);
-            if ship.data().team == 2 {
-                continue;
-            }
-            apply_gravity(ship.body());
-        }
-
-        let handles = sim.bullets.iter().cloned().collect::<Vec<_>>();
-        for handle in handles {
-            let body = sim.bodies.get_mut(handle.into()).unwrap();
-            apply_gravity(body);
-        }
+        sim.add_force_field(ForceField::ShipGravity {
+            ship: planet_handle,
+        });
     }
 
     fn status(&self, sim: &Simulation) -> Status {
@@ -1,8 +1,8 @@
 use super::prelude::*;
 use crate::ship::{ShipClass, ShipData};
 use crate::simulation::PHYSICS_TICK_LENGTH;
-use oort_api::{Class, ClassStats};
 
+#[derive(Serialize, Deserialize)]
 pub struct PlanetaryDefense {
     rng: SeededRng,
 }
@@ -70,14 +70,9 @@ impl Scenario for PlanetaryDefense {
                 vector![0.0, 0.0],
                 0.0,
                 ShipData {
-                    class: ShipClass::Planet,
-                    team: 2,
                     health: Self::PLANET_HEALTH,
                     radar_cross_section: 50.0,
-                    ..ShipData::from(ClassStats {
-                        mass: 20e6,
-                        ..Class::Unknown.default_stats()
-                    })
+                    ..planet(20e6)
                 },
             );
         }
@@ -175,4 +170,13 @@ impl Scenario for PlanetaryDefense {
             1e6
         }
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
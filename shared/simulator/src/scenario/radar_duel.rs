@@ -1,5 +1,6 @@
 use super::prelude::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct RadarDuel {
     ship0: Option<ShipHandle>,
     ship1: Option<ShipHandle>,
@@ -71,4 +72,13 @@ impl Scenario for RadarDuel {
         sim.write_target(self.ship0.unwrap(), ship1_position, ship1_velocity);
         sim.write_target(self.ship1.unwrap(), ship0_position, ship0_velocity);
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
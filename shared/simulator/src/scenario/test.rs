@@ -44,6 +44,7 @@ impl Scenario for BasicScenario {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct MissileTest {
     target: Option<ShipHandle>,
     rng: SeededRng,
@@ -143,6 +144,15 @@ impl Scenario for MissileTest {
     fn solution(&self) -> Code {
         builtin("missile")
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
 
 pub struct FrigateVsCruiser {}
@@ -1,5 +1,6 @@
 use super::prelude::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct TutorialAcceleration {
     hit_target: bool,
 }
@@ -87,4 +88,13 @@ impl Scenario for TutorialAcceleration {
     fn previous_names(&self) -> Vec<String> {
         vec!["tutorial02".into()]
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use super::prelude::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct TutorialAcceleration2 {
     hit_target: bool,
     target: Option<Point2<f64>>,
@@ -95,4 +96,13 @@ impl Scenario for TutorialAcceleration2 {
     fn previous_names(&self) -> Vec<String> {
         vec!["tutorial03".into()]
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
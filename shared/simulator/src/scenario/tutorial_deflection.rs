@@ -1,5 +1,6 @@
 use super::prelude::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct TutorialDeflection {
     ship_handle: Option<ShipHandle>,
     target_handle: Option<ShipHandle>,
@@ -86,4 +87,13 @@ impl Scenario for TutorialDeflection {
     fn previous_names(&self) -> Vec<String> {
         vec!["tutorial05".into()]
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use super::prelude::*;
 
+#[derive(Serialize, Deserialize)]
 pub struct TutorialLead {
     ship_handle: Option<ShipHandle>,
     target_handle: Option<ShipHandle>,
@@ -83,4 +84,13 @@ impl Scenario for TutorialLead {
     fn next_scenario(&self) -> Option<String> {
         Some("tutorial_deflection".to_string())
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
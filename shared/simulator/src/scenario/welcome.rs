@@ -3,6 +3,7 @@ use crate::ship::ShipClass;
 use super::prelude::*;
 use rand::seq::SliceRandom;
 
+#[derive(Serialize, Deserialize)]
 pub struct Welcome {
     rng: Option<SeededRng>,
 }
@@ -94,4 +95,13 @@ impl Scenario for Welcome {
             color: vector![0.0, 0.0, 0.0, 0.0],
         }]
     }
+
+    fn save_state(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(self)?)
+    }
+
+    fn load_state(&mut self, state: &[u8]) -> anyhow::Result<()> {
+        *self = bincode::deserialize(state)?;
+        Ok(())
+    }
 }
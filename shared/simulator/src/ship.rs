@@ -1,8 +1,9 @@
 use super::index_set::{HasIndex, Index};
 use super::rng::new_rng;
 use crate::color;
+use crate::event::{Attacker, GameEventKind};
 use crate::model;
-use crate::radar::Radar;
+use crate::radar::{Esm, Radar};
 use crate::radio::Radio;
 use crate::rng;
 use crate::simulation::{self, PHYSICS_TICK_LENGTH};
@@ -10,13 +11,17 @@ use crate::simulation::{Particle, Simulation};
 use crate::{bullet, collision};
 use bullet::BulletData;
 use nalgebra::{vector, Rotation2, UnitComplex, Vector2};
-use oort_api::{Ability, Class, ClassStats};
+use oort_api::{Ability, Class, ClassStats, EcmMode};
 use rand::Rng;
 use rapier2d_f64::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::f64::consts::TAU;
 
-#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug, Ord, PartialOrd)]
+// Team for asteroids, planets and other ships not controlled by a player.
+pub const NEUTRAL_TEAM: i32 = 9;
+pub const MAX_PLAYER_TEAMS: usize = NEUTRAL_TEAM as usize;
+
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct ShipHandle(pub Index);
 
 impl HasIndex for ShipHandle {
@@ -32,7 +37,7 @@ impl From<ShipHandle> for u64 {
     }
 }
 
-#[derive(Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, Debug)]
+#[derive(Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, Debug)]
 pub enum ShipClass {
     Fighter,
     Frigate,
@@ -59,7 +64,7 @@ impl ShipClass {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Gun {
     pub magazine_size: i32,
     pub magazine_remaining: i32,
@@ -78,7 +83,7 @@ pub struct Gun {
     pub bullet_mass: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MissileLauncher {
     pub class: ShipClass,
     pub reload_ticks: u32,
@@ -88,7 +93,7 @@ pub struct MissileLauncher {
     pub angle: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShipAbility {
     pub ability: Ability,
     pub active_time: f64,
@@ -97,7 +102,7 @@ pub struct ShipAbility {
     pub reload_time_remaining: f64,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Warhead {
     pub count: i32,
     pub mass: f32,
@@ -106,7 +111,11 @@ pub struct Warhead {
     pub ttl: f32,
 }
 
-#[derive(Debug, Clone)]
+// Applied to the warhead when the ShapedCharge ability is active.
+const SHAPED_CHARGE_WIDTH_FACTOR: f64 = 0.1;
+const SHAPED_CHARGE_MASS_FACTOR: f32 = 4.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShipData {
     pub class: ShipClass,
     pub team: i32,
@@ -127,11 +136,13 @@ pub struct ShipData {
     pub guns: Vec<Gun>,
     pub missile_launchers: Vec<MissileLauncher>,
     pub radar: Option<Radar>,
+    pub esm: Option<Esm>,
     pub radar_cross_section: f64,
     pub radios: Vec<Radio>,
     pub abilities: Vec<ShipAbility>,
     pub target: Option<Box<Target>>,
     pub warhead: Warhead,
+    pub last_attacker: Option<Attacker>,
 }
 
 impl From<ClassStats> for ShipData {
@@ -148,7 +159,7 @@ impl From<ClassStats> for ShipData {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Target {
     pub position: Vector2<f64>,
     pub velocity: Vector2<f64>,
@@ -176,11 +187,13 @@ impl Default for ShipData {
             guns: vec![],
             missile_launchers: vec![],
             radar: None,
+            esm: None,
             radar_cross_section: 10.0,
             radios: vec![],
             abilities: vec![],
             target: None,
             warhead: Default::default(),
+            last_attacker: None,
         }
     }
 }
@@ -237,9 +250,21 @@ fn radio() -> Radio {
         power: 20e3,
         rx_cross_section: 5.0,
         min_rssi: 1e-5,
+        reliable_rssi: 2e-5,
         channel: 0,
+        ecm_mode: EcmMode::None,
         sent: None,
         received: None,
+        in_flight: vec![],
+    }
+}
+
+fn shaped_charge() -> ShipAbility {
+    ShipAbility {
+        ability: Ability::ShapedCharge,
+        active_time: 1.0,
+        reload_time: 10.0,
+        ..Default::default()
     }
 }
 
@@ -278,6 +303,7 @@ pub fn fighter(team: i32) -> ShipData {
             min_width: TAU / 720.0,
             ..Default::default()
         }),
+        esm: Some(Esm::default()),
         radar_cross_section: 10.0,
         radios: vec![radio(), radio()],
         abilities: vec![ShipAbility {
@@ -328,8 +354,10 @@ pub fn frigate(team: i32) -> ShipData {
         radar: Some(Radar {
             power: 100e3,
             rx_cross_section: 10.0,
+            max_contacts: 4,
             ..Default::default()
         }),
+        esm: Some(Esm::default()),
         radar_cross_section: 30.0,
         radios: vec![radio(), radio(), radio(), radio()],
         ..ShipData::from(Class::Frigate.default_stats())
@@ -388,8 +416,10 @@ pub fn cruiser(team: i32) -> ShipData {
         radar: Some(Radar {
             power: 200e3,
             rx_cross_section: 20.0,
+            max_contacts: oort_api::MAX_RADAR_CONTACTS,
             ..Default::default()
         }),
+        esm: Some(Esm::default()),
         radar_cross_section: CRUISER_RADAR_CROSS_SECTION,
         radios: vec![
             radio(),
@@ -414,13 +444,26 @@ pub fn cruiser(team: i32) -> ShipData {
 pub fn asteroid(variant: i32) -> ShipData {
     ShipData {
         class: ShipClass::Asteroid { variant },
-        team: 9,
+        team: NEUTRAL_TEAM,
         health: 200.0,
         radar_cross_section: 50.0,
         ..ShipData::from(Class::Asteroid.default_stats())
     }
 }
 
+pub fn planet(mass: f64) -> ShipData {
+    ShipData {
+        class: ShipClass::Planet,
+        team: NEUTRAL_TEAM,
+        health: 1e9,
+        radar_cross_section: 1e6,
+        ..ShipData::from(ClassStats {
+            mass,
+            ..Class::Unknown.default_stats()
+        })
+    }
+}
+
 pub fn target(team: i32) -> ShipData {
     ShipData {
         class: ShipClass::Target,
@@ -445,12 +488,15 @@ pub fn missile(team: i32) -> ShipData {
         radios: vec![radio()],
         ttl: Some(60 * 60),
         fuel: Some(2000.0),
-        abilities: vec![ShipAbility {
-            ability: Ability::Boost,
-            active_time: 2.0,
-            reload_time: 10.0,
-            ..Default::default()
-        }],
+        abilities: vec![
+            ShipAbility {
+                ability: Ability::Boost,
+                active_time: 2.0,
+                reload_time: 10.0,
+                ..Default::default()
+            },
+            shaped_charge(),
+        ],
         warhead: Warhead {
             count: 20,
             mass: 0.05,
@@ -477,12 +523,15 @@ pub fn torpedo(team: i32) -> ShipData {
         radios: vec![radio()],
         ttl: Some(60 * 60),
         fuel: Some(3000.0),
-        abilities: vec![ShipAbility {
-            ability: Ability::Decoy,
-            active_time: 0.5,
-            reload_time: 10.0,
-            ..Default::default()
-        }],
+        abilities: vec![
+            ShipAbility {
+                ability: Ability::Decoy,
+                active_time: 0.5,
+                reload_time: 10.0,
+                ..Default::default()
+            },
+            shaped_charge(),
+        ],
         warhead: Warhead {
             count: 50,
             mass: 0.25,
@@ -543,6 +592,7 @@ pub fn create(
 
     sim.ships.insert(handle);
     sim.new_ships.push((data.team, handle));
+    sim.stats.add_ship(handle, data.team, data.class);
     sim.ship_data.insert(handle.index(), data);
 
     handle
@@ -752,10 +802,18 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
                     team,
                     color,
                     ttl: gun.ttl + t as f32,
+                    owner: Some(self.handle),
                 },
             );
             t += dt;
         }
+
+        self.simulation.record_event(GameEventKind::GunFired {
+            ship: self.handle.into(),
+            team,
+            gun: index as usize,
+            bullets: gun.burst_size as u32,
+        });
     }
 
     pub fn launch_missile(&mut self, index: i64) {
@@ -782,7 +840,7 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
         let rot2 = rot * UnitComplex::new(missile_launcher.angle);
         let v = body.linvel() + rot2.transform_vector(&vector![speed, 0.0]);
         let team = self.data().team;
-        create(
+        let missile_handle = create(
             self.simulation,
             p,
             v,
@@ -793,6 +851,13 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
                 _ => unimplemented!(),
             },
         );
+        self.simulation
+            .record_event(GameEventKind::MissileLaunched {
+                ship: self.handle.into(),
+                team,
+                missile: missile_handle.into(),
+                class: missile_launcher.class,
+            });
     }
 
     pub fn aim(&mut self, index: i64, heading: f64) {
@@ -810,14 +875,31 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
         }
         self.data_mut().destroyed = true;
 
-        let warhead = self.data().warhead.clone();
+        let mut warhead = self.data().warhead.clone();
+        let mut direction = *self.body().rotation();
+        if self.readonly().is_ability_active(Ability::ShapedCharge) {
+            warhead.width *= SHAPED_CHARGE_WIDTH_FACTOR;
+            warhead.mass *= SHAPED_CHARGE_MASS_FACTOR;
+            // Aim along the closing velocity so fragments hit where the
+            // target will be rather than where we're pointing.
+            if let Some(contact) = self.readonly().radar().and_then(|radar| radar.scan()) {
+                let v = self.body().linvel() - contact.velocity;
+                if v.norm() > 1e-3 {
+                    direction = UnitComplex::new(v.y.atan2(v.x));
+                }
+            }
+        }
         let team = self.data().team;
+        self.simulation.record_event(GameEventKind::Exploded {
+            ship: self.handle.into(),
+            team,
+        });
         let p =
             self.body().position().translation.vector - self.body().linvel() * PHYSICS_TICK_LENGTH;
         let mut rng = new_rng(0);
         for _ in 0..warhead.count {
             let color = vector![rng.gen_range(0.7..1.0), 0.5, 0.5, rng.gen_range(0.5..1.0)];
-            let rot = self.body().rotation()
+            let rot = direction
                 * Rotation2::new(rng.gen_range((-warhead.width / 2.0)..(warhead.width / 2.0)));
             let speed = warhead.speed * 2.0 * rng.gen_range(0.0..1.0);
             let v = self.body().linvel() + rot.transform_vector(&vector![speed, 0.0]);
@@ -831,6 +913,7 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
                     team,
                     color: color::to_u32(color),
                     ttl: warhead.ttl,
+                    owner: Some(self.handle),
                 },
             );
             self.simulation.events.particles.push(Particle {
@@ -854,7 +937,16 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
             }
             ship_ability.active_time_remaining = ship_ability.active_time - PHYSICS_TICK_LENGTH;
             ship_ability.reload_time_remaining = ship_ability.reload_time;
+        } else {
+            return;
         }
+        let team = self.data().team;
+        self.simulation
+            .record_event(GameEventKind::AbilityActivated {
+                ship: self.handle.into(),
+                team,
+                ability,
+            });
     }
 
     pub fn deactivate_ability(&mut self, ability: oort_api::Ability) {
@@ -949,6 +1041,14 @@ impl<'a: 'b, 'b> ShipAccessorMut<'a> {
 
         // Destruction.
         if self.data().destroyed {
+            let data = self.data();
+            let event = GameEventKind::Destroyed {
+                ship: self.handle.into(),
+                team: data.team,
+                class: data.class,
+                attacker: data.last_attacker,
+            };
+            self.simulation.record_event(event);
             if let Some(team_ctrl) = self.simulation.get_team_controller(self.data().team) {
                 team_ctrl.borrow_mut().remove_ship(self.handle);
             }
@@ -2,15 +2,18 @@ use crate::bullet::{self, BulletData, BulletHandle};
 use crate::collision;
 use crate::debug;
 pub use crate::debug::Line;
+use crate::event::{GameEvent, GameEventKind};
+use crate::force_field::{self, ForceField};
 use crate::index_set::{HasIndex, IndexSet};
 use crate::radar;
-use crate::radio;
+use crate::radio::{self, RadioModel};
 use crate::scenario;
 use crate::scenario::Scenario;
 use crate::ship::{ShipAccessor, ShipAccessorMut, ShipData, ShipHandle, Target};
 use crate::snapshot::*;
+use crate::stats::{self, MatchStats, StatsRecorder};
 use crate::vm;
-use crate::vm::TeamController;
+use crate::vm::{TeamController, TeamControllerCheckpoint, VmLimits};
 use crossbeam::channel::Sender;
 use instant::Instant;
 use nalgebra::{Vector2, Vector4};
@@ -20,7 +23,7 @@ use rapier2d_f64::data::Coarena;
 use rapier2d_f64::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::rc::Rc;
 
 pub const MAX_WORLD_SIZE: f64 = 200000.0;
@@ -34,6 +37,14 @@ pub enum Code {
     Builtin(String),
     #[cfg(feature = "precompile")]
     Precompiled(bytes::Bytes),
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    Native(vm::native::NativeCode),
+    // Command line of an external process controller. Never deserialized, so
+    // loading a scenario or replay file can't run a command.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    Process(Vec<String>),
 }
 
 pub struct Simulation {
@@ -63,12 +74,20 @@ pub struct Simulation {
     timing: Timing,
     pub(crate) rng: ChaCha8Rng,
     world_size: f64,
+    pub(crate) force_fields: Vec<ForceField>,
+    pub(crate) stats: StatsRecorder,
 }
 
 impl Simulation {
     pub fn new(scenario_name: &str, seed: u32, codes: &[Code]) -> Box<Simulation> {
-        let mut scenario = scenario::load(scenario_name);
+        Self::new_with_scenario(scenario::load(scenario_name), seed, codes)
+    }
 
+    pub fn new_with_scenario(
+        mut scenario: Box<dyn Scenario>,
+        seed: u32,
+        codes: &[Code],
+    ) -> Box<Simulation> {
         log::debug!("seed {seed}");
         let (contact_send, contact_recv) = crossbeam::channel::unbounded();
         let mut sim = Box::new(Simulation {
@@ -102,11 +121,14 @@ impl Simulation {
             timing: Default::default(),
             rng: crate::rng::new_rng(seed),
             world_size: scenario.world_size(),
+            force_fields: Vec::new(),
+            stats: Default::default(),
         });
 
         for (team, code) in codes.iter().enumerate() {
             if !matches!(code, Code::None) {
-                sim.upload_code(team as i32, code);
+                let team = team as i32;
+                sim.create_team_controller(team, code, &scenario.vm_limits(team));
             }
         }
 
@@ -122,6 +144,122 @@ impl Simulation {
         sim
     }
 
+    pub fn checkpoint(&self) -> anyhow::Result<Vec<u8>> {
+        let scenario = self.scenario.as_ref().unwrap();
+        let mut team_controllers = BTreeMap::new();
+        for (team, team_ctrl) in self.team_controllers.iter() {
+            let team_checkpoint = team_ctrl
+                .borrow()
+                .checkpoint()
+                .map_err(|e| anyhow::anyhow!("Failed to checkpoint team {team}: {}", e.msg))?;
+            team_controllers.insert(*team, team_checkpoint);
+        }
+        let checkpoint = Checkpoint {
+            scenario_name: scenario.name(),
+            scenario_state: scenario.save_state()?,
+            tick: self.tick,
+            seed: self.seed,
+            cheats: self.cheats,
+            world_size: self.world_size,
+            force_fields: self.force_fields.clone(),
+            stats: self.stats.clone(),
+            rng: self.rng.clone(),
+            ships: self.ships.iter().cloned().collect(),
+            ship_data: self.ship_data.clone(),
+            new_ships: self.new_ships.clone(),
+            bullets: self.bullets.iter().cloned().collect(),
+            bullet_data: self.bullet_data.clone(),
+            bodies: self.bodies.clone(),
+            impulse_joints: self.impulse_joints.clone(),
+            multibody_joints: self.multibody_joints.clone(),
+            colliders: self.colliders.clone(),
+            integration_parameters: self.integration_parameters,
+            island_manager: self.island_manager.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            team_controllers,
+        };
+        Ok(bincode::serialize(&checkpoint)?)
+    }
+
+    pub fn restore(data: &[u8]) -> anyhow::Result<Box<Simulation>> {
+        let checkpoint: Checkpoint = bincode::deserialize(data)?;
+        let scenario = scenario::load_safe(&checkpoint.scenario_name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown scenario {:?}", checkpoint.scenario_name))?;
+        Self::restore_checkpoint(checkpoint, scenario)
+    }
+
+    // For scenarios that aren't built in, e.g. a FileScenario.
+    pub fn restore_with_scenario(
+        data: &[u8],
+        scenario: Box<dyn Scenario>,
+    ) -> anyhow::Result<Box<Simulation>> {
+        let checkpoint: Checkpoint = bincode::deserialize(data)?;
+        if checkpoint.scenario_name != scenario.name() {
+            anyhow::bail!(
+                "Checkpoint is for scenario {:?}, not {:?}",
+                checkpoint.scenario_name,
+                scenario.name()
+            );
+        }
+        Self::restore_checkpoint(checkpoint, scenario)
+    }
+
+    fn restore_checkpoint(
+        checkpoint: Checkpoint,
+        mut scenario: Box<dyn Scenario>,
+    ) -> anyhow::Result<Box<Simulation>> {
+        scenario.load_state(&checkpoint.scenario_state)?;
+
+        let mut team_controllers = HashMap::new();
+        for (team, team_checkpoint) in checkpoint.team_controllers {
+            let team_ctrl = TeamController::restore(team_checkpoint)
+                .map_err(|e| anyhow::anyhow!("Failed to restore team {team}: {}", e.msg))?;
+            team_controllers.insert(team, Rc::new(RefCell::new(team_ctrl)));
+        }
+
+        let mut ships = IndexSet::new();
+        for handle in checkpoint.ships {
+            ships.insert(handle);
+        }
+        let mut bullets = IndexSet::new();
+        for handle in checkpoint.bullets {
+            bullets.insert(handle);
+        }
+
+        let (contact_send, contact_recv) = crossbeam::channel::unbounded();
+        Ok(Box::new(Simulation {
+            scenario: Some(scenario),
+            ships,
+            ship_data: checkpoint.ship_data,
+            team_controllers,
+            new_ships: checkpoint.new_ships,
+            bullets,
+            bullet_data: checkpoint.bullet_data,
+            bodies: checkpoint.bodies,
+            impulse_joints: checkpoint.impulse_joints,
+            multibody_joints: checkpoint.multibody_joints,
+            colliders: checkpoint.colliders,
+            integration_parameters: checkpoint.integration_parameters,
+            physics_pipeline: PhysicsPipeline::new(),
+            island_manager: checkpoint.island_manager,
+            broad_phase: checkpoint.broad_phase,
+            narrow_phase: checkpoint.narrow_phase,
+            ccd_solver: CCDSolver::new(),
+            event_collector: CollisionEventHandler::new(contact_send),
+            contact_recv,
+            events: SimEvents::new(),
+            tick: checkpoint.tick,
+            cheats: checkpoint.cheats,
+            seed: checkpoint.seed,
+            timing: Default::default(),
+            rng: checkpoint.rng,
+            world_size: checkpoint.world_size,
+            force_fields: checkpoint.force_fields,
+            stats: checkpoint.stats,
+        }))
+    }
+
     pub fn tick(&self) -> u32 {
         self.tick
     }
@@ -142,10 +280,26 @@ impl Simulation {
         self.world_size
     }
 
+    pub fn add_force_field(&mut self, field: ForceField) {
+        self.force_fields.push(field);
+    }
+
+    pub fn force_fields(&self) -> &[ForceField] {
+        &self.force_fields
+    }
+
+    pub fn radio_model(&self) -> RadioModel {
+        self.scenario.as_ref().unwrap().radio_model()
+    }
+
     pub fn status(&self) -> scenario::Status {
         self.scenario.as_ref().unwrap().status(self)
     }
 
+    pub fn stats(&self) -> MatchStats {
+        self.stats.report()
+    }
+
     pub fn ship(self: &Simulation, handle: ShipHandle) -> ShipAccessor {
         ShipAccessor {
             simulation: self,
@@ -233,17 +387,26 @@ impl Simulation {
         bullet::tick(self);
         self.timing.bullet += bullet_timer.elapsed();
 
+        force_field::tick(self);
+
         let scenario_timer = Timer::new();
         let mut scenario = std::mem::take(&mut self.scenario);
         scenario.as_mut().unwrap().tick(self);
         self.scenario = scenario;
         self.timing.scenario += scenario_timer.elapsed();
 
+        stats::tick(self);
+
         self.tick += 1;
     }
 
     pub fn upload_code(&mut self, team: i32, code: &Code) {
-        match vm::new_team_controller(code) {
+        let limits = self.scenario.as_ref().unwrap().vm_limits(team);
+        self.create_team_controller(team, code, &limits);
+    }
+
+    fn create_team_controller(&mut self, team: i32, code: &Code, limits: &VmLimits) {
+        match vm::new_team_controller(code, limits) {
             Ok(team_ctrl) => {
                 self.team_controllers
                     .insert(team, Rc::new(RefCell::new(team_ctrl)));
@@ -255,6 +418,14 @@ impl Simulation {
         }
     }
 
+    pub(crate) fn record_event(&mut self, kind: GameEventKind) {
+        self.stats.record(&kind);
+        self.events.game_events.push(GameEvent {
+            tick: self.tick,
+            kind,
+        });
+    }
+
     pub fn events(&self) -> &SimEvents {
         &self.events
     }
@@ -327,17 +498,25 @@ impl Simulation {
             status: self.status(),
             ships: vec![],
             bullets: vec![],
+            contacts: vec![],
             scenario_lines: self.scenario.as_ref().unwrap().lines(),
             debug_lines: self.events.debug_lines.clone(),
             debug_text: self.events.debug_text.clone(),
             drawn_text: self.events.drawn_text.clone(),
             particles: self.events.particles.clone(),
             errors: self.events.errors.clone(),
+            events: self.events.game_events.clone(),
+            gas_used: self.events.gas_used.clone(),
             cheats: self.cheats,
             timing: self.timing.clone(),
             world_size: self.world_size,
+            stats: None,
         };
 
+        if snapshot.status != scenario::Status::Running {
+            snapshot.stats = Some(self.stats());
+        }
+
         for &handle in self.ships.iter() {
             let ship = self.ship(handle);
             let id = handle.into();
@@ -376,6 +555,59 @@ impl Simulation {
         snapshot
     }
 
+    // A snapshot containing only what the given team knows about: its own
+    // ships and bullets plus the noisy radar contacts from its ships.
+    pub fn snapshot_for_team(&self, nonce: u32, team: i32) -> Snapshot {
+        let mut snapshot = self.snapshot(nonce);
+        snapshot.ships.retain(|ship| ship.team == team);
+        let own_ships: HashSet<u64> = snapshot.ships.iter().map(|ship| ship.id).collect();
+
+        snapshot.bullets = self
+            .bullets
+            .iter()
+            .zip(snapshot.bullets)
+            .filter(|(handle, _)| self.bullet_data.get(handle.index()).unwrap().team == team)
+            .map(|(_, bullet)| bullet)
+            .collect();
+
+        for &handle in self.ships.iter() {
+            let ship = self.ship(handle);
+            if ship.data().team != team {
+                continue;
+            }
+            if let Some(radar) = ship.radar() {
+                if radar.scan_all().is_empty() {
+                    snapshot.contacts.extend(radar.scan());
+                } else {
+                    snapshot.contacts.extend_from_slice(radar.scan_all());
+                }
+            }
+        }
+
+        snapshot.events.retain(|event| match event.kind {
+            GameEventKind::Hit { attacker, .. } if attacker.team == team => true,
+            ref kind => kind.team() == team,
+        });
+        snapshot
+            .debug_lines
+            .retain(|(id, _)| own_ships.contains(id));
+        snapshot.debug_text.retain(|id, _| own_ships.contains(id));
+        snapshot.gas_used.retain(|id, _| own_ships.contains(id));
+        snapshot
+            .drawn_text
+            .retain(|id, _| id.map(|id| own_ships.contains(&id)).unwrap_or(true));
+        // Particles come from every team's guns and explosions.
+        snapshot.particles.clear();
+        // Errors and timing aren't attributed to a team.
+        snapshot.errors.clear();
+        snapshot.timing = Default::default();
+        if let Some(stats) = snapshot.stats.as_mut() {
+            stats.ships.retain(|_, ship| ship.team == team);
+            stats.teams.retain(|&t, _| t == team);
+        }
+        snapshot
+    }
+
     pub fn get_team_controller(&mut self, team: i32) -> Option<Rc<RefCell<Box<TeamController>>>> {
         self.team_controllers.get_mut(&team).map(|x| x.clone())
     }
@@ -395,6 +627,33 @@ impl Simulation {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    scenario_name: String,
+    scenario_state: Vec<u8>,
+    tick: u32,
+    seed: u32,
+    cheats: bool,
+    world_size: f64,
+    force_fields: Vec<ForceField>,
+    stats: StatsRecorder,
+    rng: ChaCha8Rng,
+    ships: Vec<ShipHandle>,
+    ship_data: Coarena<ShipData>,
+    new_ships: Vec<(i32, ShipHandle)>,
+    bullets: Vec<BulletHandle>,
+    bullet_data: Coarena<BulletData>,
+    bodies: RigidBodySet,
+    impulse_joints: ImpulseJointSet,
+    multibody_joints: MultibodyJointSet,
+    colliders: ColliderSet,
+    integration_parameters: IntegrationParameters,
+    island_manager: IslandManager,
+    broad_phase: BroadPhase,
+    narrow_phase: NarrowPhase,
+    team_controllers: BTreeMap<i32, TeamControllerCheckpoint>,
+}
+
 pub struct CollisionEventHandler {
     collision_event_sender: Sender<CollisionEvent>,
 }
@@ -444,6 +703,9 @@ pub struct SimEvents {
     pub debug_lines: Vec<(u64, Vec<Line>)>,
     pub debug_text: BTreeMap<u64, String>,
     pub drawn_text: BTreeMap<Option<u64>, Vec<Text>>,
+    pub game_events: Vec<GameEvent>,
+    // Instructions executed by each ship's controller this tick.
+    pub gas_used: BTreeMap<u64, u32>,
 }
 
 impl SimEvents {
@@ -454,6 +716,8 @@ impl SimEvents {
             debug_lines: Vec::new(),
             debug_text: BTreeMap::new(),
             drawn_text: BTreeMap::new(),
+            game_events: Vec::new(),
+            gas_used: BTreeMap::new(),
         }
     }
 
@@ -463,6 +727,8 @@ impl SimEvents {
         self.debug_lines.clear();
         self.debug_text.clear();
         self.drawn_text.clear();
+        self.game_events.clear();
+        self.gas_used.clear();
     }
 }
 
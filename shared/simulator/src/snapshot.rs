@@ -1,6 +1,9 @@
+use crate::event::GameEvent;
+use crate::radar::ScanResult;
 use crate::scenario::Status;
 use crate::ship::ShipClass;
 use crate::simulation::{Line, Particle};
+use crate::stats::MatchStats;
 use crate::vm;
 use nalgebra::{Point2, Vector2};
 use oort_api::{Ability, Text};
@@ -15,15 +18,26 @@ pub struct Snapshot {
     pub status: Status,
     pub ships: Vec<ShipSnapshot>,
     pub bullets: Vec<BulletSnapshot>,
+    // Radar contacts, only set in snapshots from Simulation::snapshot_for_team.
+    #[serde(default)]
+    pub contacts: Vec<ScanResult>,
     pub scenario_lines: Vec<Line>,
     pub particles: Vec<Particle>,
     pub errors: Vec<vm::Error>,
+    #[serde(default)]
+    pub events: Vec<GameEvent>,
+    // Instructions executed by each ship this tick, keyed by ShipSnapshot::id.
+    #[serde(default)]
+    pub gas_used: BTreeMap<u64, u32>,
     pub cheats: bool,
     pub debug_lines: Vec<(u64, Vec<Line>)>,
     pub debug_text: BTreeMap<u64, String>,
     pub drawn_text: BTreeMap<Option<u64>, Vec<Text>>,
     pub timing: Timing,
     pub world_size: f64,
+    // Only set once the match is over.
+    #[serde(default)]
+    pub stats: Option<MatchStats>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -0,0 +1,435 @@
+use crate::ship::ShipClass;
+use crate::simulation::Particle;
+use crate::snapshot::{BulletSnapshot, ShipSnapshot, Snapshot};
+use bincode::Options;
+use nalgebra::{vector, Point2, Vector2};
+use oort_api::Ability;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Compact encoding of a stream of snapshots, used between the simulation
+// worker and the UI. Positions and velocities are quantized to fixed point and
+// varint encoded. Ships are sent as deltas against the previous frame, keyed by
+// ship ID, with a full keyframe every keyframe_interval frames.
+
+const POSITION_SCALE: f64 = 64.0;
+const VELOCITY_SCALE: f64 = 64.0;
+const ANGLE_SCALE: f64 = 1e4;
+const HEALTH_SCALE: f64 = 16.0;
+const BULLET_VELOCITY_SCALE: f64 = 16.0;
+const TIME_SCALE: f32 = 1e3;
+
+pub const DEFAULT_KEYFRAME_INTERVAL: u32 = 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct QuantizedShip {
+    id: u64,
+    team: i32,
+    class: ShipClass,
+    position: [i64; 2],
+    velocity: [i64; 2],
+    acceleration: [i64; 2],
+    heading: i64,
+    angular_velocity: i64,
+    health: i64,
+    fuel: Option<i64>,
+    active_abilities: Vec<Ability>,
+}
+
+// Differences from the ship at the same index in the previous frame.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ShipDelta {
+    index: u32,
+    position: [i64; 2],
+    velocity: [i64; 2],
+    acceleration: [i64; 2],
+    heading: i64,
+    angular_velocity: i64,
+    health: i64,
+    fuel: Option<i64>,
+    active_abilities: Vec<Ability>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum ShipUpdate {
+    Changed(ShipDelta),
+    New(QuantizedShip),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum ShipFrame {
+    Keyframe(Vec<QuantizedShip>),
+    Delta(Vec<ShipUpdate>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct QuantizedBullet {
+    position: [i64; 2],
+    velocity: [i64; 2],
+    color: u32,
+    ttl: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct QuantizedParticle {
+    position: [i64; 2],
+    velocity: [i64; 2],
+    color: [u8; 4],
+    lifetime: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Frame {
+    sequence: u32,
+    ships: ShipFrame,
+    bullets: Vec<QuantizedBullet>,
+    particles: Vec<QuantizedParticle>,
+    // Everything else, with ships, bullets and particles removed.
+    rest: Snapshot,
+}
+
+fn quantize(x: f64, scale: f64) -> i64 {
+    (x * scale).round() as i64
+}
+
+fn dequantize(x: i64, scale: f64) -> f64 {
+    x as f64 / scale
+}
+
+fn quantize_vector(v: Vector2<f64>, scale: f64) -> [i64; 2] {
+    [quantize(v.x, scale), quantize(v.y, scale)]
+}
+
+fn dequantize_vector(v: [i64; 2], scale: f64) -> Vector2<f64> {
+    vector![dequantize(v[0], scale), dequantize(v[1], scale)]
+}
+
+fn sub(a: [i64; 2], b: [i64; 2]) -> [i64; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn add(a: [i64; 2], b: [i64; 2]) -> [i64; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+impl QuantizedShip {
+    fn new(ship: &ShipSnapshot) -> Self {
+        Self {
+            id: ship.id,
+            team: ship.team,
+            class: ship.class,
+            position: quantize_vector(ship.position.coords, POSITION_SCALE),
+            velocity: quantize_vector(ship.velocity, VELOCITY_SCALE),
+            acceleration: quantize_vector(ship.acceleration, VELOCITY_SCALE),
+            heading: quantize(ship.heading, ANGLE_SCALE),
+            angular_velocity: quantize(ship.angular_velocity, ANGLE_SCALE),
+            health: quantize(ship.health, HEALTH_SCALE),
+            fuel: ship.fuel.map(|x| quantize(x, HEALTH_SCALE)),
+            active_abilities: ship.active_abilities.clone(),
+        }
+    }
+
+    fn delta(&self, index: u32, prev: &QuantizedShip) -> ShipDelta {
+        ShipDelta {
+            index,
+            position: sub(self.position, prev.position),
+            velocity: sub(self.velocity, prev.velocity),
+            acceleration: sub(self.acceleration, prev.acceleration),
+            heading: self.heading - prev.heading,
+            angular_velocity: self.angular_velocity - prev.angular_velocity,
+            health: self.health - prev.health,
+            fuel: self.fuel.map(|x| x - prev.fuel.unwrap_or(0)),
+            active_abilities: self.active_abilities.clone(),
+        }
+    }
+
+    fn apply(&self, delta: &ShipDelta) -> QuantizedShip {
+        QuantizedShip {
+            id: self.id,
+            team: self.team,
+            class: self.class,
+            position: add(self.position, delta.position),
+            velocity: add(self.velocity, delta.velocity),
+            acceleration: add(self.acceleration, delta.acceleration),
+            heading: self.heading + delta.heading,
+            angular_velocity: self.angular_velocity + delta.angular_velocity,
+            health: self.health + delta.health,
+            fuel: delta.fuel.map(|x| x + self.fuel.unwrap_or(0)),
+            active_abilities: delta.active_abilities.clone(),
+        }
+    }
+
+    fn snapshot(&self) -> ShipSnapshot {
+        ShipSnapshot {
+            id: self.id,
+            position: Point2::from(dequantize_vector(self.position, POSITION_SCALE)),
+            velocity: dequantize_vector(self.velocity, VELOCITY_SCALE),
+            acceleration: dequantize_vector(self.acceleration, VELOCITY_SCALE),
+            heading: dequantize(self.heading, ANGLE_SCALE),
+            angular_velocity: dequantize(self.angular_velocity, ANGLE_SCALE),
+            team: self.team,
+            class: self.class,
+            health: dequantize(self.health, HEALTH_SCALE),
+            fuel: self.fuel.map(|x| dequantize(x, HEALTH_SCALE)),
+            active_abilities: self.active_abilities.clone(),
+        }
+    }
+}
+
+impl QuantizedBullet {
+    fn new(bullet: &BulletSnapshot) -> Self {
+        Self {
+            position: quantize_vector(bullet.position.coords, POSITION_SCALE),
+            velocity: quantize_vector(bullet.velocity, BULLET_VELOCITY_SCALE),
+            color: bullet.color,
+            ttl: (bullet.ttl.max(0.0) * TIME_SCALE) as u32,
+        }
+    }
+
+    fn snapshot(&self) -> BulletSnapshot {
+        BulletSnapshot {
+            position: Point2::from(dequantize_vector(self.position, POSITION_SCALE)),
+            velocity: dequantize_vector(self.velocity, BULLET_VELOCITY_SCALE),
+            color: self.color,
+            ttl: self.ttl as f32 / TIME_SCALE,
+        }
+    }
+}
+
+impl QuantizedParticle {
+    fn new(particle: &Particle) -> Self {
+        Self {
+            position: quantize_vector(particle.position, POSITION_SCALE),
+            velocity: quantize_vector(particle.velocity, VELOCITY_SCALE),
+            color: particle
+                .color
+                .map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .into(),
+            lifetime: (particle.lifetime.max(0.0) * TIME_SCALE) as u32,
+        }
+    }
+
+    fn particle(&self) -> Particle {
+        Particle {
+            position: dequantize_vector(self.position, POSITION_SCALE),
+            velocity: dequantize_vector(self.velocity, VELOCITY_SCALE),
+            color: self.color.map(|x| x as f32 / 255.0).into(),
+            lifetime: self.lifetime as f32 / TIME_SCALE,
+        }
+    }
+}
+
+pub struct SnapshotEncoder {
+    keyframe_interval: u32,
+    sequence: u32,
+    frames_since_keyframe: Option<u32>,
+    ships: Vec<QuantizedShip>,
+}
+
+impl SnapshotEncoder {
+    pub fn new() -> Self {
+        Self::with_keyframe_interval(DEFAULT_KEYFRAME_INTERVAL)
+    }
+
+    pub fn with_keyframe_interval(keyframe_interval: u32) -> Self {
+        Self {
+            keyframe_interval,
+            sequence: 0,
+            frames_since_keyframe: None,
+            ships: Vec::new(),
+        }
+    }
+
+    // Makes the next frame a keyframe, e.g. for a new decoder.
+    pub fn force_keyframe(&mut self) {
+        self.frames_since_keyframe = None;
+    }
+
+    pub fn encode(&mut self, mut snapshot: Snapshot) -> Vec<u8> {
+        let ships: Vec<QuantizedShip> = snapshot.ships.iter().map(QuantizedShip::new).collect();
+        let bullets = snapshot.bullets.iter().map(QuantizedBullet::new).collect();
+        let particles = snapshot
+            .particles
+            .iter()
+            .map(QuantizedParticle::new)
+            .collect();
+
+        let keyframe = match self.frames_since_keyframe {
+            Some(n) => n + 1 >= self.keyframe_interval,
+            None => true,
+        };
+        let ship_frame = if keyframe {
+            self.frames_since_keyframe = Some(0);
+            ShipFrame::Keyframe(ships.clone())
+        } else {
+            *self.frames_since_keyframe.as_mut().unwrap() += 1;
+            let prev_indices: HashMap<u64, usize> = self
+                .ships
+                .iter()
+                .enumerate()
+                .map(|(i, ship)| (ship.id, i))
+                .collect();
+            ShipFrame::Delta(
+                ships
+                    .iter()
+                    .map(|ship| match prev_indices.get(&ship.id) {
+                        Some(&i) => ShipUpdate::Changed(ship.delta(i as u32, &self.ships[i])),
+                        None => ShipUpdate::New(ship.clone()),
+                    })
+                    .collect(),
+            )
+        };
+        self.ships = ships;
+        self.sequence = self.sequence.wrapping_add(1);
+
+        let frame = Frame {
+            sequence: self.sequence,
+            ships: ship_frame,
+            bullets,
+            particles,
+            rest: Snapshot {
+                ships: vec![],
+                bullets: vec![],
+                particles: vec![],
+                ..snapshot
+            },
+        };
+        options().serialize(&frame).unwrap()
+    }
+}
+
+impl Default for SnapshotEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Default)]
+pub struct SnapshotDecoder {
+    sequence: Option<u32>,
+    ships: Vec<QuantizedShip>,
+}
+
+impl SnapshotDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn decode(&mut self, data: &[u8]) -> anyhow::Result<Snapshot> {
+        let frame: Frame = options().deserialize(data)?;
+        let ships = match frame.ships {
+            ShipFrame::Keyframe(ships) => ships,
+            ShipFrame::Delta(updates) => {
+                if self.sequence.map(|x| x.wrapping_add(1)) != Some(frame.sequence) {
+                    anyhow::bail!(
+                        "Delta frame {} does not follow frame {:?}",
+                        frame.sequence,
+                        self.sequence
+                    );
+                }
+                let mut ships = Vec::with_capacity(updates.len());
+                for update in updates {
+                    ships.push(match update {
+                        ShipUpdate::Changed(delta) => self
+                            .ships
+                            .get(delta.index as usize)
+                            .ok_or_else(|| anyhow::anyhow!("Invalid ship index {}", delta.index))?
+                            .apply(&delta),
+                        ShipUpdate::New(ship) => ship,
+                    });
+                }
+                ships
+            }
+        };
+
+        let snapshot = Snapshot {
+            ships: ships.iter().map(QuantizedShip::snapshot).collect(),
+            bullets: frame
+                .bullets
+                .iter()
+                .map(QuantizedBullet::snapshot)
+                .collect(),
+            particles: frame
+                .particles
+                .iter()
+                .map(QuantizedParticle::particle)
+                .collect(),
+            ..frame.rest
+        };
+        self.sequence = Some(frame.sequence);
+        self.ships = ships;
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scenario;
+    use crate::simulation::{Code, Simulation};
+    use test_log::test;
+
+    fn check_close(a: &Snapshot, b: &Snapshot) {
+        assert_eq!(a.nonce, b.nonce);
+        assert_eq!(a.time, b.time);
+        assert_eq!(a.ships.len(), b.ships.len());
+        for (x, y) in a.ships.iter().zip(b.ships.iter()) {
+            assert_eq!(x.id, y.id);
+            assert_eq!(x.team, y.team);
+            assert_eq!(x.class, y.class);
+            assert!((x.position - y.position).norm() < 0.1);
+            assert!((x.velocity - y.velocity).norm() < 0.1);
+            assert!((x.heading - y.heading).abs() < 1e-3);
+            assert!((x.health - y.health).abs() < 0.1);
+        }
+        assert_eq!(a.bullets.len(), b.bullets.len());
+        for (x, y) in a.bullets.iter().zip(b.bullets.iter()) {
+            assert!((x.position - y.position).norm() < 0.1);
+            assert!((x.velocity - y.velocity).norm() < 0.1);
+            assert_eq!(x.color, y.color);
+        }
+        assert_eq!(a.particles.len(), b.particles.len());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let scenario_name = "gunnery";
+        let codes = scenario::load(scenario_name).solution_codes();
+        let mut sim = Simulation::new(scenario_name, 0, &codes);
+        let mut encoder = SnapshotEncoder::with_keyframe_interval(10);
+        let mut decoder = SnapshotDecoder::new();
+        let mut full_size = 0;
+        let mut encoded_size = 0;
+        for i in 0..100 {
+            sim.step();
+            let snapshot = sim.snapshot(i);
+            let data = encoder.encode(snapshot.clone());
+            full_size += bincode::serialize(&snapshot).unwrap().len();
+            encoded_size += data.len();
+            check_close(&snapshot, &decoder.decode(&data).unwrap());
+        }
+        assert!(encoded_size < full_size);
+    }
+
+    #[test]
+    fn test_missing_keyframe() {
+        let mut sim = Simulation::new("test", 0, &[Code::None]);
+        let mut encoder = SnapshotEncoder::new();
+        let first = encoder.encode(sim.snapshot(0));
+        sim.step();
+        let second = encoder.encode(sim.snapshot(0));
+
+        let mut decoder = SnapshotDecoder::new();
+        assert!(decoder.decode(&second).is_err());
+        decoder.decode(&first).unwrap();
+        decoder.decode(&second).unwrap();
+
+        encoder.force_keyframe();
+        let mut decoder = SnapshotDecoder::new();
+        decoder.decode(&encoder.encode(sim.snapshot(0))).unwrap();
+    }
+}
@@ -0,0 +1,326 @@
+use crate::event::GameEventKind;
+use crate::ship::{ShipClass, ShipHandle};
+use crate::simulation::{Simulation, PHYSICS_TICK_LENGTH};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Per-ship statistics accumulated over the whole match. Damage dealt by a
+// missile's warhead is credited to the missile, and its launcher gets a
+// missile hit.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ShipStats {
+    pub team: i32,
+    pub class: ShipClass,
+    pub shots_fired: u32,
+    pub hits: u32,
+    pub damage_dealt: f64,
+    pub damage_received: f64,
+    pub missiles_launched: u32,
+    pub missile_hits: u32,
+    pub fuel_used: f64,
+    pub time_alive: f64,
+    pub radar_uptime: f64,
+    pub destroyed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TeamStats {
+    // Missiles and torpedoes aren't counted in ships and ships_lost.
+    pub ships: u32,
+    pub ships_lost: u32,
+    pub shots_fired: u32,
+    pub hits: u32,
+    pub damage_dealt: f64,
+    pub damage_received: f64,
+    pub missiles_launched: u32,
+    pub missile_hits: u32,
+    pub fuel_used: f64,
+    pub time_alive: f64,
+    pub radar_uptime: f64,
+}
+
+// Returned by Simulation::stats. Ships are keyed by the same ID as
+// ShipSnapshot::id.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MatchStats {
+    pub ships: BTreeMap<u64, ShipStats>,
+    pub teams: BTreeMap<i32, TeamStats>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct StatsRecorder {
+    ships: BTreeMap<u64, ShipStats>,
+    // Missiles in flight that haven't hit anything yet, mapped to the ship
+    // that launched them.
+    missile_launchers: BTreeMap<u64, u64>,
+}
+
+impl ShipStats {
+    fn new(team: i32, class: ShipClass) -> Self {
+        Self {
+            team,
+            class,
+            shots_fired: 0,
+            hits: 0,
+            damage_dealt: 0.0,
+            damage_received: 0.0,
+            missiles_launched: 0,
+            missile_hits: 0,
+            fuel_used: 0.0,
+            time_alive: 0.0,
+            radar_uptime: 0.0,
+            destroyed: false,
+        }
+    }
+}
+
+impl TeamStats {
+    // Fraction of gun bullets that hit an enemy.
+    pub fn hit_rate(&self) -> f64 {
+        ratio(self.hits as f64, self.shots_fired as f64)
+    }
+
+    pub fn missile_hit_rate(&self) -> f64 {
+        ratio(self.missile_hits as f64, self.missiles_launched as f64)
+    }
+
+    // Damage dealt divided by damage received. Infinite if the team dealt
+    // damage without taking any.
+    pub fn damage_ratio(&self) -> f64 {
+        if self.damage_received == 0.0 {
+            if self.damage_dealt == 0.0 {
+                0.0
+            } else {
+                f64::INFINITY
+            }
+        } else {
+            self.damage_dealt / self.damage_received
+        }
+    }
+}
+
+fn ratio(a: f64, b: f64) -> f64 {
+    if b == 0.0 {
+        0.0
+    } else {
+        a / b
+    }
+}
+
+impl MatchStats {
+    pub fn team(&self, team: i32) -> TeamStats {
+        self.teams.get(&team).cloned().unwrap_or_default()
+    }
+}
+
+impl StatsRecorder {
+    pub(crate) fn add_ship(&mut self, handle: ShipHandle, team: i32, class: ShipClass) {
+        self.ships
+            .insert(handle.into(), ShipStats::new(team, class));
+    }
+
+    fn ship(&mut self, id: u64) -> Option<&mut ShipStats> {
+        self.ships.get_mut(&id)
+    }
+
+    pub(crate) fn report(&self) -> MatchStats {
+        let mut teams: BTreeMap<i32, TeamStats> = BTreeMap::new();
+        for ship in self.ships.values() {
+            let team = teams.entry(ship.team).or_default();
+            if !matches!(ship.class, ShipClass::Missile | ShipClass::Torpedo) {
+                team.ships += 1;
+                team.ships_lost += ship.destroyed as u32;
+            }
+            team.shots_fired += ship.shots_fired;
+            team.hits += ship.hits;
+            team.damage_dealt += ship.damage_dealt;
+            team.damage_received += ship.damage_received;
+            team.missiles_launched += ship.missiles_launched;
+            team.missile_hits += ship.missile_hits;
+            team.fuel_used += ship.fuel_used;
+            team.time_alive += ship.time_alive;
+            team.radar_uptime += ship.radar_uptime;
+        }
+        MatchStats {
+            ships: self.ships.clone(),
+            teams,
+        }
+    }
+
+    pub(crate) fn record(&mut self, event: &GameEventKind) {
+        match *event {
+            GameEventKind::GunFired { ship, bullets, .. } => {
+                if let Some(stats) = self.ship(ship) {
+                    stats.shots_fired += bullets;
+                }
+            }
+            GameEventKind::MissileLaunched { ship, missile, .. } => {
+                if let Some(stats) = self.ship(ship) {
+                    stats.missiles_launched += 1;
+                }
+                self.missile_launchers.insert(missile, ship);
+            }
+            GameEventKind::Hit {
+                ship,
+                team,
+                attacker,
+                damage,
+            } => {
+                if let Some(stats) = self.ship(ship) {
+                    stats.damage_received += damage;
+                }
+                if team == attacker.team {
+                    return;
+                }
+                let Some(attacker) = attacker.ship else {
+                    return;
+                };
+                if let Some(stats) = self.ship(attacker) {
+                    stats.damage_dealt += damage;
+                    if !matches!(stats.class, ShipClass::Missile | ShipClass::Torpedo) {
+                        stats.hits += 1;
+                    }
+                }
+                if let Some(launcher) = self.missile_launchers.remove(&attacker) {
+                    if let Some(stats) = self.ship(launcher) {
+                        stats.missile_hits += 1;
+                    }
+                }
+            }
+            GameEventKind::Destroyed { ship, .. } => {
+                if let Some(stats) = self.ship(ship) {
+                    stats.destroyed = true;
+                }
+                // A missile destroyed without a hit can't score one later.
+                self.missile_launchers.remove(&ship);
+            }
+            GameEventKind::Exploded { .. } | GameEventKind::AbilityActivated { .. } => {}
+        }
+    }
+}
+
+#[inline(never)]
+pub fn tick(sim: &mut Simulation) {
+    let handles: Vec<ShipHandle> = sim.ships.iter().cloned().collect();
+    for handle in handles {
+        let ship = sim.ship(handle);
+        let fuel_used = ship.data().last_acceleration.norm() * PHYSICS_TICK_LENGTH;
+        let radar_enabled = ship.radar().map(|radar| radar.enabled).unwrap_or(false);
+        if let Some(stats) = sim.stats.ship(handle.into()) {
+            stats.time_alive += PHYSICS_TICK_LENGTH;
+            stats.fuel_used += fuel_used;
+            if radar_enabled {
+                stats.radar_uptime += PHYSICS_TICK_LENGTH;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ship;
+    use crate::simulation::Code;
+    use nalgebra::vector;
+    use test_log::test;
+
+    #[test]
+    fn test_gun_stats() {
+        let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+        let mut ship_data = ship::fighter(0);
+        ship_data.guns[0].inaccuracy = 0.0;
+        let shooter = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship_data,
+        );
+        let target = ship::create(
+            &mut sim,
+            vector![100.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::target(1),
+        );
+
+        for _ in 0..60 {
+            sim.step();
+            if sim.ships.contains(shooter) {
+                sim.ship_mut(shooter).fire_gun(0);
+                sim.ship_mut(shooter).accelerate(vector![10.0, 0.0]);
+            }
+        }
+
+        let stats = sim.stats();
+        let shooter_stats = &stats.ships[&shooter.into()];
+        assert!(shooter_stats.shots_fired > 1);
+        assert_eq!(shooter_stats.hits, 1);
+        assert!(shooter_stats.damage_dealt > 0.0);
+        assert!((shooter_stats.time_alive - 1.0).abs() < 1e-9);
+        assert!(shooter_stats.fuel_used > 0.0);
+        assert!(shooter_stats.radar_uptime > 0.0);
+
+        let target_stats = &stats.ships[&target.into()];
+        assert!(target_stats.destroyed);
+        assert_eq!(target_stats.damage_received, shooter_stats.damage_dealt);
+
+        let team0 = stats.team(0);
+        assert_eq!(team0.ships_lost, 0);
+        assert_eq!(team0.damage_ratio(), f64::INFINITY);
+        assert!(team0.hit_rate() > 0.0 && team0.hit_rate() < 1.0);
+        assert_eq!(stats.team(1).ships_lost, 1);
+    }
+
+    #[test]
+    fn test_missile_stats() {
+        let mut sim = Simulation::new("test", 0, &[Code::None, Code::None]);
+        let launcher = ship::create(
+            &mut sim,
+            vector![0.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::fighter(0),
+        );
+
+        let launch = |sim: &mut Simulation| {
+            sim.ship_mut(launcher).launch_missile(0);
+            *sim.ships
+                .iter()
+                .find(|&&h| sim.ship(h).data().class == ShipClass::Missile)
+                .unwrap()
+        };
+
+        // Explodes next to a target.
+        let missile = launch(&mut sim);
+        let p = sim.ship(missile).position().vector;
+        ship::create(
+            &mut sim,
+            p + vector![50.0, 0.0],
+            vector![0.0, 0.0],
+            0.0,
+            ship::target(1),
+        );
+        sim.ship_mut(missile).explode();
+        for _ in 0..10 {
+            sim.step();
+        }
+        assert!(sim.stats().ships[&missile.into()].damage_dealt > 0.0);
+
+        // Explodes in empty space.
+        sim.ship_mut(launcher).data_mut().missile_launchers[0].reload_ticks_remaining = 0;
+        let missile = launch(&mut sim);
+        sim.ship_mut(missile).explode();
+        for _ in 0..10 {
+            sim.step();
+        }
+
+        let stats = sim.stats();
+        let launcher_stats = &stats.ships[&launcher.into()];
+        assert_eq!(launcher_stats.missiles_launched, 2);
+        assert_eq!(launcher_stats.missile_hits, 1);
+        assert_eq!(launcher_stats.hits, 0);
+        assert_eq!(stats.team(0).missile_hit_rate(), 0.5);
+        assert!(sim.stats.missile_launchers.is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+use wasmparser::{Name, NameSectionReader, Parser, Payload};
+
+// Maps function names to the source line where each function starts.
+//
+// Instrumentation rewrites the code section and drops the DWARF sections, so
+// instruction offsets in a trap can't be mapped back to a line. Function
+// names survive, so we look them up in the original module instead.
+pub struct DebugInfo {
+    locations: HashMap<String, String>,
+}
+
+impl DebugInfo {
+    // Returns None if the module has no line tables.
+    pub fn parse(wasm: &[u8]) -> Option<DebugInfo> {
+        let mut num_imported_functions = 0;
+        let mut code_section_start = 0;
+        let mut function_ranges = vec![];
+        let mut function_names = HashMap::new();
+        let mut dwarf_sections = HashMap::new();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.ok()? {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let wasmparser::TypeRef::Func(_) = import.ok()?.ty {
+                            num_imported_functions += 1;
+                        }
+                    }
+                }
+                Payload::CodeSectionStart { range, .. } => code_section_start = range.start,
+                Payload::CodeSectionEntry(body) => function_ranges.push(body.range()),
+                Payload::CustomSection(reader) if reader.name() == "name" => {
+                    let names = NameSectionReader::new(reader.data(), reader.data_offset()).ok()?;
+                    for name in names {
+                        if let Ok(Name::Function(map)) = name {
+                            for naming in map.into_iter().flatten() {
+                                function_names.insert(naming.index, naming.name);
+                            }
+                        }
+                    }
+                }
+                Payload::CustomSection(reader) if reader.name().starts_with(".debug_") => {
+                    dwarf_sections.insert(reader.name(), reader.data());
+                }
+                _ => {}
+            }
+        }
+        if !dwarf_sections.contains_key(".debug_line") {
+            return None;
+        }
+
+        let dwarf = gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+            let data = dwarf_sections.get(id.name()).copied().unwrap_or(&[]);
+            Ok(gimli::EndianSlice::new(data, gimli::LittleEndian))
+        })
+        .ok()?;
+        let context = addr2line::Context::from_dwarf(dwarf).ok()?;
+
+        // DWARF addresses are relative to the start of the code section.
+        let mut locations = HashMap::new();
+        for (i, range) in function_ranges.iter().enumerate() {
+            let Some(name) = function_names.get(&(num_imported_functions + i as u32)) else {
+                continue;
+            };
+            let start = (range.start - code_section_start) as u64;
+            let end = (range.end - code_section_start) as u64;
+            let Some((_, _, location)) = context
+                .find_location_range(start, end)
+                .ok()
+                .and_then(|mut iter| iter.next())
+            else {
+                continue;
+            };
+            if let (Some(file), Some(line)) = (location.file, location.line) {
+                let file = file.rsplit('/').next().unwrap_or(file);
+                locations.insert(name.to_string(), format!("{file}:{line}"));
+            }
+        }
+        Some(DebugInfo { locations })
+    }
+
+    // Takes the function name as it appears in the name section.
+    pub fn location(&self, function_name: &str) -> Option<&str> {
+        self.locations.get(function_name).map(|s| s.as_str())
+    }
+}
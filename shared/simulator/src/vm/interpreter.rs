@@ -0,0 +1,182 @@
+use super::{instrument, Error, Offsets, Vm, VmLimits, GAS_PER_TICK};
+use crate::simulation::Code;
+use wasmi::core::Pages;
+use wasmi::{Engine, Linker, Memory, Module, Store, TypedFunc};
+
+// Runs the module in the wasmi interpreter. Slower than a compiled engine
+// but there's no startup compile cost and execution doesn't depend on the
+// host.
+pub struct InterpreterVm {
+    store: Store<()>,
+    memory: Memory,
+    offsets: Offsets,
+    tick_ship: TypedFunc<(), ()>,
+    reset_gas: TypedFunc<i32, ()>,
+    get_gas: TypedFunc<(), i32>,
+    add_submemory: TypedFunc<(), (i32, i32)>,
+    select_submemory: TypedFunc<i32, ()>,
+    reset_submemory: TypedFunc<i32, ()>,
+}
+
+impl InterpreterVm {
+    pub fn create(code: &Code, limits: &VmLimits) -> Result<InterpreterVm, Error> {
+        let wasm = match code {
+            Code::Wasm(wasm) => instrument(wasm, limits)?,
+            _ => {
+                return Err(Error {
+                    msg: "The interpreter can only run wasm code".to_string(),
+                })
+            }
+        };
+        let engine = Engine::default();
+        let module = Module::new(&engine, &wasm[..]).map_err(translate_error)?;
+        let mut store = Store::new(&engine, ());
+        let linker = Linker::<()>::new(&engine);
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(translate_error)?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| missing_export("memory"))?;
+        let global = |name: &str| -> Option<u32> {
+            instance
+                .get_global(&store, name)
+                .and_then(|global| global.get(&store).i32())
+                .map(|offset| offset as u32)
+        };
+        let offsets = Offsets {
+            system_state: global("SYSTEM_STATE").ok_or_else(|| missing_export("SYSTEM_STATE"))?,
+            environment: global("ENVIRONMENT").ok_or_else(|| missing_export("ENVIRONMENT"))?,
+            panic_buffer: global("PANIC_BUFFER").ok_or_else(|| missing_export("PANIC_BUFFER"))?,
+            esm_contacts: global("ESM_CONTACTS"),
+            radar_contacts: global("RADAR_CONTACTS"),
+        };
+
+        let tick_ship = instance
+            .get_typed_func(&store, "tick")
+            .map_err(translate_error)?;
+        let reset_gas = instance
+            .get_typed_func(&store, "reset_gas")
+            .map_err(translate_error)?;
+        let get_gas = instance
+            .get_typed_func(&store, "get_gas")
+            .map_err(translate_error)?;
+        let add_submemory = instance
+            .get_typed_func(&store, "add_submemory")
+            .map_err(translate_error)?;
+        let select_submemory = instance
+            .get_typed_func(&store, "select_submemory")
+            .map_err(translate_error)?;
+        let reset_submemory = instance
+            .get_typed_func(&store, "reset_submemory")
+            .map_err(translate_error)?;
+
+        let mut vm = InterpreterVm {
+            store,
+            memory,
+            offsets,
+            tick_ship,
+            reset_gas,
+            get_gas,
+            add_submemory,
+            select_submemory,
+            reset_submemory,
+        };
+        vm.reset_gas(GAS_PER_TICK as i32)?;
+        Ok(vm)
+    }
+}
+
+impl Vm for InterpreterVm {
+    fn offsets(&self) -> &Offsets {
+        &self.offsets
+    }
+
+    fn tick_ship(&mut self) -> Result<(), Error> {
+        self.tick_ship
+            .call(&mut self.store, ())
+            .map_err(translate_runtime_error)
+    }
+
+    fn reset_gas(&mut self, gas: i32) -> Result<(), Error> {
+        self.reset_gas
+            .call(&mut self.store, gas)
+            .map_err(translate_runtime_error)
+    }
+
+    fn remaining_gas(&mut self) -> Result<i32, Error> {
+        self.get_gas
+            .call(&mut self.store, ())
+            .map_err(translate_runtime_error)
+    }
+
+    fn add_submemory(&mut self) -> Result<(u32, u32), Error> {
+        let (index, base_address) = self
+            .add_submemory
+            .call(&mut self.store, ())
+            .map_err(translate_runtime_error)?;
+        Ok((index as u32, base_address as u32))
+    }
+
+    fn select_submemory(&mut self, index: u32) -> Result<(), Error> {
+        self.select_submemory
+            .call(&mut self.store, index as i32)
+            .map_err(translate_runtime_error)
+    }
+
+    fn reset_submemory(&mut self, index: u32) -> Result<(), Error> {
+        self.reset_submemory
+            .call(&mut self.store, index as i32)
+            .map_err(translate_runtime_error)
+    }
+
+    fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+        self.memory
+            .read(&self.store, offset as usize, buf)
+            .map_err(translate_error)
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        self.memory
+            .write(&mut self.store, offset as usize, data)
+            .map_err(translate_error)
+    }
+
+    fn read_memory(&self) -> Result<Vec<u8>, Error> {
+        Ok(self.memory.data(&self.store).to_vec())
+    }
+
+    fn write_memory(&mut self, data: &[u8]) -> Result<(), Error> {
+        let current_size = self.memory.data(&self.store).len();
+        if data.len() > current_size {
+            let delta = (data.len() - current_size) / wasmer::WASM_PAGE_SIZE;
+            let delta = Pages::new(delta as u32).ok_or_else(|| Error {
+                msg: "Checkpoint memory too large".to_string(),
+            })?;
+            self.memory
+                .grow(&mut self.store, delta)
+                .map_err(translate_error)?;
+        }
+        self.write(0, data)
+    }
+}
+
+fn missing_export(name: &str) -> Error {
+    Error {
+        msg: format!("Missing export {name:?}"),
+    }
+}
+
+fn translate_error(err: impl std::fmt::Display) -> Error {
+    Error {
+        msg: format!("Interpreter error: {err}"),
+    }
+}
+
+fn translate_runtime_error(err: wasmi::Error) -> Error {
+    Error {
+        msg: format!("Ship runtime error: {err}"),
+    }
+}
@@ -1,5 +1,7 @@
 // Based on https://github.com/scrtlabs/SecretNetwork/blob/621d3899babc4741ef1ba596152c097677d246db/cosmwasm/enclaves/shared/contract-engine/src/wasm3/gas.rs
-use walrus::{ir::*, FunctionBuilder, GlobalId, InitExpr, LocalFunction, ValType};
+use walrus::{
+    ir::*, FunctionBuilder, FunctionId, GlobalId, ImportKind, InitExpr, LocalFunction, ValType,
+};
 
 pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
     let mut module = match walrus::Module::from_buffer(wasm) {
@@ -15,6 +17,23 @@ pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
         .globals
         .add_local(ValType::I32, true, InitExpr::Value(Value::I32(0)));
 
+    // Replace calls to the imported oort.remaining_gas() with a read of the gas global.
+    let remaining_gas_import = module
+        .imports
+        .iter()
+        .find(|import| import.module == "oort" && import.name == "remaining_gas")
+        .and_then(|import| match import.kind {
+            ImportKind::Function(func) => Some((import.id(), func)),
+            _ => None,
+        });
+    if let Some((import_id, import_func)) = remaining_gas_import {
+        for (_, func) in module.funcs.iter_local_mut() {
+            replace_calls(func, import_func, gas_global);
+        }
+        module.imports.delete(import_id);
+        module.funcs.delete(import_func);
+    }
+
     // Rewrite each block to check and decrement gas.
     for (_, func) in module.funcs.iter_local_mut() {
         rewrite_function(func, gas_global);
@@ -47,6 +66,17 @@ fn rewrite_function(func: &mut LocalFunction, gas_global: GlobalId) {
     }
 }
 
+fn replace_calls(func: &mut LocalFunction, target: FunctionId, gas_global: GlobalId) {
+    let block_ids: Vec<_> = func.blocks().map(|(block_id, _block)| block_id).collect();
+    for block_id in block_ids {
+        for (instr, _) in func.block_mut(block_id).instrs.iter_mut() {
+            if matches!(instr, Instr::Call(Call { func }) if *func == target) {
+                *instr = Instr::GlobalGet(GlobalGet { global: gas_global });
+            }
+        }
+    }
+}
+
 /// Number of injected metering instructions (needed to calculate final instruction size).
 const METERING_INSTRUCTION_COUNT: usize = 8;
 
@@ -59,14 +89,15 @@ fn rewrite_block(func: &mut LocalFunction, block_id: InstrSeqId, gas_global: Glo
     let builder = func.builder_mut();
     let mut builder = builder.dangling_instr_seq(None);
     let seq = builder
-        // if unsigned(globals[gas]) < unsigned(block_cost) { throw(); }
+        // if unsigned(globals[gas]) < unsigned(block_cost) { globals[gas] = 0; throw(); }
         .global_get(gas_global)
         .i32_const(block_cost)
         .binop(BinaryOp::I32LtU)
         .if_else(
             None,
             |then| {
-                then.unreachable();
+                // Zeroing the counter distinguishes running out of gas from other traps.
+                then.i32_const(0).global_set(gas_global).unreachable();
             },
             |_else| {},
         )
@@ -144,6 +175,8 @@ mod tests {
     i32.const 2
     i32.lt_u
     if  ;; label = @1
+      i32.const 0
+      global.set 0
       unreachable
     end
     global.get 0
@@ -155,6 +188,8 @@ mod tests {
       i32.const 1
       i32.lt_u
       if  ;; label = @2
+        i32.const 0
+        global.set 0
         unreachable
       end
       global.get 0
@@ -175,4 +210,22 @@ mod tests {
 ",
         );
     }
+
+    #[test]
+    fn test_remaining_gas() {
+        let wasm = wat2wasm(
+            "
+(module
+    (import \"oort\" \"remaining_gas\" (func $remaining_gas (result i32)))
+    (func $f (export \"f\") (result i32)
+        call $remaining_gas
+    )
+)
+",
+        );
+        let new_wat = wasm2wat(&rewrite(&wasm).unwrap());
+        assert!(!new_wat.contains("import"), "{new_wat}");
+        assert!(!new_wat.contains("call"), "{new_wat}");
+        assert!(new_wat.contains("(export \"f\" (func 0))"), "{new_wat}");
+    }
 }
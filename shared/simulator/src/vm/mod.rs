@@ -1,8 +1,17 @@
 // TODO clean up error translation
-// TODO add methods to WasmVm for each exported function
 // TODO shift pointers according to headroom + base
 pub mod builtin;
+#[cfg_attr(feature = "interpreter", allow(dead_code))]
+mod debuginfo;
+#[cfg(feature = "interpreter")]
+mod interpreter;
 mod limiter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod native;
+#[cfg(not(target_arch = "wasm32"))]
+mod process;
+#[cfg_attr(feature = "interpreter", allow(dead_code))]
+mod wasmer_vm;
 
 use crate::color;
 use crate::debug;
@@ -10,25 +19,83 @@ use crate::rng::new_rng;
 use crate::ship::{ShipClass, ShipHandle};
 use crate::simulation::{Code, Particle, Simulation, PHYSICS_TICK_LENGTH};
 use nalgebra::{point, vector, Rotation2};
-use oort_api::{ActiveAbilities, Class, EcmMode, Line, SystemState, Text};
+use oort_api::{ActiveAbilities, Class, EcmMode, Line, RadarMode, SystemState, Text};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::cell::{Ref, RefCell, RefMut};
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::f64::consts::TAU;
-use std::ops::{Deref, DerefMut};
-use std::rc::Rc;
-use wasmer::{imports, Instance, MemoryView, Module, Store, WasmPtr};
+
+#[cfg(feature = "precompile")]
+pub use wasmer_vm::{engine_version, precompile};
 
 pub type Vec2 = nalgebra::Vector2<f64>;
 pub type Environment = BTreeMap<String, String>;
 
 const SUBMEMORY_SIZE: u32 = 1 << 20;
-const GAS_PER_TICK: i32 = 1_000_000;
+const GAS_PER_TICK: u32 = 1_000_000;
 const MAX_DEBUG_LINES: u32 = 1024;
 const MAX_DRAWN_TEXT: u32 = 128;
 
+// Resource limits for a team's ship controllers. Every ship on a team shares
+// the same compiled module, so the memory limit can't vary by class.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VmLimits {
+    // Instructions each ship may execute per tick.
+    #[serde(default = "default_gas_per_tick")]
+    pub gas_per_tick: u32,
+    // Overrides gas_per_tick for specific ship classes.
+    #[serde(default)]
+    pub class_gas_per_tick: BTreeMap<ShipClass, u32>,
+    // Bytes of memory available to each ship. Must be a multiple of the wasm
+    // page size.
+    #[serde(default = "default_memory_size")]
+    pub memory_size: u32,
+}
+
+impl VmLimits {
+    pub fn gas_per_tick(&self, class: ShipClass) -> u32 {
+        self.class_gas_per_tick
+            .get(&class)
+            .copied()
+            .unwrap_or(self.gas_per_tick)
+    }
+
+    fn gas(&self, class: ShipClass) -> i32 {
+        self.gas_per_tick(class).min(i32::MAX as u32) as i32
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.memory_size == 0 || self.memory_size % wasmer::WASM_PAGE_SIZE as u32 != 0 {
+            return Err(Error {
+                msg: format!(
+                    "Memory limit {} is not a multiple of the page size",
+                    self.memory_size
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for VmLimits {
+    fn default() -> Self {
+        Self {
+            gas_per_tick: GAS_PER_TICK,
+            class_gas_per_tick: BTreeMap::new(),
+            memory_size: SUBMEMORY_SIZE,
+        }
+    }
+}
+
+fn default_gas_per_tick() -> u32 {
+    GAS_PER_TICK
+}
+
+fn default_memory_size() -> u32 {
+    SUBMEMORY_SIZE
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Error {
     pub msg: String,
@@ -43,14 +110,6 @@ impl From<wasm_bindgen::JsValue> for Error {
     }
 }
 
-impl From<wasmer::InstantiationError> for Error {
-    fn from(err: wasmer::InstantiationError) -> Self {
-        Self {
-            msg: format!("Wasmer instantiation error: {err:?}"),
-        }
-    }
-}
-
 impl From<anyhow::Error> for Error {
     fn from(err: anyhow::Error) -> Self {
         Self {
@@ -59,50 +118,273 @@ impl From<anyhow::Error> for Error {
     }
 }
 
-pub fn new_team_controller(code: &Code) -> Result<Box<TeamController>, Error> {
+// An engine running a team's compiled code. The module is rewritten by
+// wasm_submemory and the limiter before it's loaded, so memory isolation and
+// gas metering behave the same in every engine.
+pub trait Vm {
+    fn offsets(&self) -> &Offsets;
+    fn tick_ship(&mut self) -> Result<(), Error>;
+    fn reset_gas(&mut self, gas: i32) -> Result<(), Error>;
+    fn remaining_gas(&mut self) -> Result<i32, Error>;
+    fn add_submemory(&mut self) -> Result<(u32, u32), Error>;
+    fn select_submemory(&mut self, index: u32) -> Result<(), Error>;
+    fn reset_submemory(&mut self, index: u32) -> Result<(), Error>;
+    fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error>;
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Error>;
+    fn read_memory(&self) -> Result<Vec<u8>, Error>;
+    fn write_memory(&mut self, data: &[u8]) -> Result<(), Error>;
+}
+
+// Addresses of the API's statics relative to the start of a submemory.
+pub struct Offsets {
+    pub system_state: u32,
+    pub environment: u32,
+    pub panic_buffer: u32,
+    // None for code built against an API without ESM support.
+    pub esm_contacts: Option<u32>,
+    // None for code built against an API without track-while-scan support.
+    pub radar_contacts: Option<u32>,
+}
+
+fn create_vm(code: &Code, limits: &VmLimits) -> Result<Box<dyn Vm>, Error> {
+    limits.validate()?;
+    #[cfg(feature = "interpreter")]
+    let vm = interpreter::InterpreterVm::create(code, limits)?;
+    #[cfg(not(feature = "interpreter"))]
+    let vm = wasmer_vm::WasmVm::create(code, limits)?;
+    Ok(Box::new(vm))
+}
+
+fn instrument(wasm: &[u8], limits: &VmLimits) -> Result<Vec<u8>, Error> {
+    let wasm = wasm_submemory::rewrite(wasm, limits.memory_size)?;
+    limiter::rewrite(&wasm)
+}
+
+pub fn new_team_controller(code: &Code, limits: &VmLimits) -> Result<Box<TeamController>, Error> {
     match code {
-        Code::Wasm(_) => TeamController::create(code),
+        Code::Wasm(_) => TeamController::create(code, limits),
         #[cfg(feature = "precompile")]
-        Code::Precompiled(_) => TeamController::create(code),
+        Code::Precompiled(_) => TeamController::create(code, limits),
+        #[cfg(not(target_arch = "wasm32"))]
+        Code::Native(native_code) => Ok(Box::new(TeamController::Native(
+            native::NativeTeamController::new(native_code),
+        ))),
+        #[cfg(not(target_arch = "wasm32"))]
+        Code::Process(command) => Ok(Box::new(TeamController::Process(
+            process::ProcessTeamController::create(command)?,
+        ))),
         Code::Builtin(name) => match builtin::load_compiled(name) {
-            Ok(code) => new_team_controller(&code),
+            Ok(code) => new_team_controller(&code, limits),
             Err(e) => Err(Error { msg: e }),
         },
         _ => unreachable!(),
     }
 }
 
+// Runs a team's ships in a VM, as native Rust code or in an external process.
+pub enum TeamController {
+    Vm(VmTeamController),
+    #[cfg(not(target_arch = "wasm32"))]
+    Native(native::NativeTeamController),
+    #[cfg(not(target_arch = "wasm32"))]
+    Process(process::ProcessTeamController),
+}
+
+impl TeamController {
+    pub fn create(code: &Code, limits: &VmLimits) -> Result<Box<TeamController>, Error> {
+        Ok(Box::new(TeamController::Vm(VmTeamController::create(
+            code, limits,
+        )?)))
+    }
+
+    pub fn checkpoint(&self) -> Result<TeamControllerCheckpoint, Error> {
+        match self {
+            TeamController::Vm(ctrl) => ctrl.checkpoint(),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Native(_) => Err(Error {
+                msg: "Native team controllers can't be checkpointed".to_string(),
+            }),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Process(_) => Err(Error {
+                msg: "Process team controllers can't be checkpointed".to_string(),
+            }),
+        }
+    }
+
+    pub fn restore(checkpoint: TeamControllerCheckpoint) -> Result<Box<TeamController>, Error> {
+        Ok(Box::new(TeamController::Vm(VmTeamController::restore(
+            checkpoint,
+        )?)))
+    }
+
+    pub fn add_ship(&mut self, handle: ShipHandle, sim: &Simulation) -> Result<(), Error> {
+        match self {
+            TeamController::Vm(ctrl) => ctrl.add_ship(handle, sim),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Native(ctrl) => ctrl.add_ship(handle, sim),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Process(ctrl) => ctrl.add_ship(handle, sim),
+        }
+    }
+
+    pub fn remove_ship(&mut self, handle: ShipHandle) {
+        match self {
+            TeamController::Vm(ctrl) => ctrl.remove_ship(handle),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Native(ctrl) => ctrl.remove_ship(handle),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Process(ctrl) => ctrl.remove_ship(handle),
+        }
+    }
+
+    pub fn tick(&mut self, sim: &mut Simulation) {
+        let mut handles: Vec<ShipHandle> = match self {
+            TeamController::Vm(ctrl) => ctrl.ship_controllers.keys().cloned().collect(),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Native(ctrl) => ctrl.handles(),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Process(ctrl) => ctrl.handles(),
+        };
+        handles.sort_by_key(|x| x.0);
+
+        for handle in handles {
+            if let Some(msg) = sim.ship(handle).data().crash_message.as_ref() {
+                sim.emit_debug_text(handle, format!("Crashed: {}", msg.clone()));
+                emit_crash_particles(sim, handle);
+                continue;
+            }
+
+            let result = match self {
+                TeamController::Vm(ctrl) => ctrl.tick_ship(sim, handle),
+                #[cfg(not(target_arch = "wasm32"))]
+                TeamController::Native(ctrl) => ctrl.tick_ship(sim, handle),
+                #[cfg(not(target_arch = "wasm32"))]
+                TeamController::Process(ctrl) => ctrl.tick_ship(sim, handle),
+            };
+            if let Err(e) = result {
+                log::warn!("{}", e.msg);
+                sim.emit_debug_text(handle, format!("Crashed: {}", e.msg.clone()));
+                sim.ship_mut(handle).data_mut().crash_message = Some(e.msg);
+            }
+        }
+    }
+
+    pub fn update_environment(&mut self, environment: &Environment) -> Result<(), Error> {
+        match self {
+            TeamController::Vm(ctrl) => ctrl.update_environment(environment),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Native(ctrl) => ctrl.update_environment(environment),
+            #[cfg(not(target_arch = "wasm32"))]
+            TeamController::Process(ctrl) => ctrl.update_environment(environment),
+        }
+    }
+}
+
 pub struct ShipController {
     index: u32,
     state: LocalSystemState,
     base_address: u32,
-    system_state_ptr: WasmPtr<u64>,
-    environment_ptr: WasmPtr<u8>,
-    panic_buffer_ptr: WasmPtr<u8>,
+    system_state_address: u32,
+    environment_address: u32,
+    panic_buffer_address: u32,
 }
 
-pub struct TeamController {
-    vm: WasmVm,
+pub struct VmTeamController {
+    code: Code,
+    limits: VmLimits,
+    vm: Box<dyn Vm>,
     ship_controllers: HashMap<ShipHandle, ShipController>,
     next_id: u32,
     free_submemories: Vec<(u32, u32)>, // (index, base_address)
     environment: Environment,
 }
 
-impl TeamController {
-    pub fn create(code: &Code) -> Result<Box<TeamController>, Error> {
-        Ok(Box::new(TeamController {
-            vm: WasmVm::create(code)?,
+impl VmTeamController {
+    pub fn create(code: &Code, limits: &VmLimits) -> Result<VmTeamController, Error> {
+        Ok(VmTeamController {
+            code: code.clone(),
+            limits: limits.clone(),
+            vm: create_vm(code, limits)?,
             ship_controllers: HashMap::new(),
             next_id: 1,
             free_submemories: Vec::new(),
             environment: Environment::new(),
-        }))
+        })
     }
 
-    pub fn add_ship(&mut self, handle: ShipHandle, sim: &Simulation) -> Result<(), Error> {
-        let mut state = LocalSystemState::new();
+    pub fn checkpoint(&self) -> Result<TeamControllerCheckpoint, Error> {
+        let mut ship_controllers: Vec<_> = self
+            .ship_controllers
+            .iter()
+            .map(|(handle, ship_controller)| {
+                (
+                    *handle,
+                    ShipControllerCheckpoint {
+                        index: ship_controller.index,
+                        base_address: ship_controller.base_address,
+                        state: ship_controller.state.state.to_vec(),
+                    },
+                )
+            })
+            .collect();
+        ship_controllers.sort_by_key(|(handle, _)| *handle);
+        Ok(TeamControllerCheckpoint {
+            code: self.code.clone(),
+            limits: self.limits.clone(),
+            memory: self.vm.read_memory()?,
+            ship_controllers,
+            next_id: self.next_id,
+            free_submemories: self.free_submemories.clone(),
+            environment: self.environment.clone(),
+        })
+    }
+
+    pub fn restore(checkpoint: TeamControllerCheckpoint) -> Result<VmTeamController, Error> {
+        let mut vm = create_vm(&checkpoint.code, &checkpoint.limits)?;
+
+        // Recreate the same submemories so that the VM's bookkeeping matches
+        // the saved memory contents.
+        let num_submemories = checkpoint.ship_controllers.len() + checkpoint.free_submemories.len();
+        for _ in 0..num_submemories {
+            vm.add_submemory()?;
+        }
+        vm.write_memory(&checkpoint.memory)?;
+
+        let mut ship_controllers = HashMap::new();
+        for (handle, ship_checkpoint) in checkpoint.ship_controllers {
+            let mut state = LocalSystemState::new();
+            if ship_checkpoint.state.len() != state.state.len() {
+                return Err(Error {
+                    msg: "checkpoint system state has wrong size".to_string(),
+                });
+            }
+            state.state.copy_from_slice(&ship_checkpoint.state);
+            let base_address = ship_checkpoint.base_address;
+            ship_controllers.insert(
+                handle,
+                ShipController {
+                    index: ship_checkpoint.index,
+                    state,
+                    base_address,
+                    system_state_address: base_address + vm.offsets().system_state,
+                    environment_address: base_address + vm.offsets().environment,
+                    panic_buffer_address: base_address + vm.offsets().panic_buffer,
+                },
+            );
+        }
+
+        Ok(VmTeamController {
+            code: checkpoint.code,
+            limits: checkpoint.limits,
+            vm,
+            ship_controllers,
+            next_id: checkpoint.next_id,
+            free_submemories: checkpoint.free_submemories,
+            environment: checkpoint.environment,
+        })
+    }
 
+    pub fn add_ship(&mut self, handle: ShipHandle, sim: &Simulation) -> Result<(), Error> {
         let (index, base_address) = {
             if let Some((index, base_address)) = self.free_submemories.pop() {
                 (index, base_address)
@@ -111,29 +393,17 @@ impl TeamController {
             }
         };
 
-        state.set(
-            SystemState::Seed,
-            (make_seed(sim.seed(), handle) & 0xffffff) as f64,
-        );
-        state.set(SystemState::Id, self.next_id as f64);
+        let state = initial_system_state(sim, handle, self.next_id);
         self.next_id += 1;
-        if let Some(radar) = sim.ship(handle).data().radar.as_ref() {
-            state.set(SystemState::RadarHeading, radar.heading);
-            state.set(SystemState::RadarWidth, radar.width);
-            state.set(SystemState::RadarMinDistance, radar.min_distance);
-            state.set(SystemState::RadarMaxDistance, radar.max_distance);
-        }
 
         self.vm.select_submemory(index)?;
 
-        let system_state_ptr: WasmPtr<u64> =
-            WasmPtr::new(base_address + self.vm.system_state_offset);
-        let environment_ptr: WasmPtr<u8> = WasmPtr::new(base_address + self.vm.environment_offset);
-        let panic_buffer_ptr: WasmPtr<u8> =
-            WasmPtr::new(base_address + self.vm.panic_buffer_offset);
+        let offsets = self.vm.offsets();
+        let system_state_address = base_address + offsets.system_state;
+        let environment_address = base_address + offsets.environment;
+        let panic_buffer_address = base_address + offsets.panic_buffer;
 
-        self.vm
-            .update_environment(environment_ptr, &self.environment)?;
+        write_environment(self.vm.as_mut(), environment_address, &self.environment)?;
 
         self.ship_controllers.insert(
             handle,
@@ -141,9 +411,9 @@ impl TeamController {
                 index,
                 state,
                 base_address,
-                system_state_ptr,
-                environment_ptr,
-                panic_buffer_ptr,
+                system_state_address,
+                environment_address,
+                panic_buffer_address,
             },
         );
 
@@ -152,126 +422,90 @@ impl TeamController {
 
     pub fn remove_ship(&mut self, handle: ShipHandle) {
         let ship_controller = self.ship_controllers.remove(&handle).unwrap();
-        self.vm
-            .reset_gas
-            .call(&mut self.vm.store_mut(), &[GAS_PER_TICK.into()])
-            .unwrap();
+        self.vm.reset_gas(GAS_PER_TICK as i32).unwrap();
         self.vm.reset_submemory(ship_controller.index).unwrap();
         self.free_submemories
             .push((ship_controller.index, ship_controller.base_address));
     }
 
-    pub fn tick(&mut self, sim: &mut Simulation) {
-        let mut handles: Vec<_> = self.ship_controllers.keys().cloned().collect();
-        handles.sort_by_key(|x| x.0);
-
-        for handle in handles {
-            if let Err(e) = self.tick_ship(sim, handle) {
-                log::warn!("{}", e.msg);
-                sim.emit_debug_text(handle, format!("Crashed: {}", e.msg.clone()));
-                sim.ship_mut(handle).data_mut().crash_message = Some(e.msg);
-            }
-        }
-    }
-
     fn tick_ship(&mut self, sim: &mut Simulation, handle: ShipHandle) -> Result<(), Error> {
-        if let Some(msg) = sim.ship(handle).data().crash_message.as_ref() {
-            sim.emit_debug_text(handle, format!("Crashed: {}", msg.clone()));
-            let mut rng = new_rng(sim.tick());
-            if rng.gen_range(0.0..1.0) < 0.2 {
-                let color = vector![0.5, 0.5, 0.9, rng.gen_range(0.5..1.0)];
-                let rot = Rotation2::new(rng.gen_range(0.0..TAU));
-                let speed = 300.0 * rng.gen_range(0.0..1.0);
-                let p = sim.ship(handle).position().vector;
-                let v =
-                    sim.ship(handle).body().linvel() + rot.transform_vector(&vector![speed, 0.0]);
-                let offset = v * rng.gen_range(0.0..PHYSICS_TICK_LENGTH);
-                sim.events.particles.push(Particle {
-                    position: p + offset,
-                    velocity: v,
-                    color,
-                    lifetime: 1.0,
-                });
-            }
-            return Ok(());
-        }
-
         let vm = &mut self.vm;
         let ship_controller = &mut self.ship_controllers.get_mut(&handle).unwrap();
         let state = &mut ship_controller.state;
+        let gas_per_tick = self.limits.gas(sim.ship(handle).data().class);
 
         {
-            translate_runtime_error(
-                vm.reset_gas
-                    .call(vm.store_mut().deref_mut(), &[GAS_PER_TICK.into()]),
-            )?;
+            vm.reset_gas(gas_per_tick)?;
 
             vm.select_submemory(ship_controller.index)?;
 
             generate_system_state(sim, handle, state);
+            write_esm_contacts(
+                sim,
+                handle,
+                state,
+                ship_controller.base_address,
+                self.limits.memory_size,
+                vm.as_mut(),
+            )?;
+            write_radar_contacts(
+                sim,
+                handle,
+                state,
+                ship_controller.base_address,
+                self.limits.memory_size,
+                vm.as_mut(),
+            )?;
 
-            let store = vm.store();
-            let memory_view = vm.memory.view(store.deref());
-            let ptr = ship_controller.system_state_ptr;
-            let slice = ptr
-                .slice(&memory_view, SystemState::Size as u32)
-                .expect("system state write");
-            slice.write_slice(&state.state).expect("system state write");
+            write_u64s(
+                vm.as_mut(),
+                ship_controller.system_state_address,
+                &state.state,
+            )
+            .expect("system state write");
         }
 
-        let result = vm.tick_ship.call(vm.store_mut().deref_mut(), &[]);
+        let result = vm.tick_ship();
+        let gas = vm.remaining_gas()?;
+        sim.events
+            .gas_used
+            .insert(handle.into(), (gas_per_tick - gas.max(0)) as u32);
         if let Err(e) = result {
-            if let Ok(ret) = vm.get_gas.call(vm.store_mut().deref_mut(), &[]) {
-                if !ret.is_empty() {
-                    let gas: i32 = ret[0].i32().unwrap();
-                    if gas <= 0 {
-                        return Err(Error {
-                            msg: "Ship exceeded maximum number of instructions".to_string(),
-                        });
-                    }
-                }
+            if gas <= 0 {
+                return Err(Error {
+                    msg: "Ship exceeded maximum number of instructions".to_string(),
+                });
             }
 
-            {
-                let store = vm.store();
-                let memory_view = vm.memory.view(store.deref());
-                if let Some(vec) = WasmVm::read_vec(
-                    &memory_view,
-                    ship_controller.panic_buffer_ptr.offset(),
-                    oort_api::panic::PANIC_BUFFER_SIZE as u32,
-                ) {
-                    let null_pos = vec.iter().position(|&x| x == 0).unwrap_or(vec.len());
-                    let msg = String::from_utf8_lossy(&vec[0..null_pos]).to_string();
-                    if msg.is_empty() {
-                        return Err(Error {
-                            msg: "Ship exceeded maximum number of instructions".to_string(),
-                        });
-                    } else {
-                        return Err(Error { msg });
-                    }
+            if let Some(vec) = read_vec::<u8>(
+                vm.as_ref(),
+                ship_controller.panic_buffer_address,
+                oort_api::panic::PANIC_BUFFER_SIZE as u32,
+            ) {
+                let null_pos = vec.iter().position(|&x| x == 0).unwrap_or(vec.len());
+                let msg = String::from_utf8_lossy(&vec[0..null_pos]).to_string();
+                if !msg.is_empty() {
+                    return Err(Error { msg });
                 }
             }
 
-            return translate_runtime_error(Err(e));
+            return Err(e);
         }
 
         {
-            let store = vm.store();
-            let memory_view = vm.memory.view(store.deref());
-            let ptr = ship_controller.system_state_ptr;
-            let slice = ptr
-                .slice(&memory_view, SystemState::Size as u32)
-                .expect("system state read");
-            slice
-                .read_slice(&mut state.state)
-                .expect("system state read");
+            read_u64s(
+                vm.as_ref(),
+                ship_controller.system_state_address,
+                &mut state.state,
+            )
+            .expect("system state read");
             apply_system_state(sim, handle, state);
 
             if state.get(SystemState::DebugTextLength) > 0.0 {
                 let offset =
                     state.get(SystemState::DebugTextPointer) as u32 + ship_controller.base_address;
                 let length = state.get(SystemState::DebugTextLength) as u32;
-                if let Some(s) = WasmVm::read_string(&memory_view, offset, length) {
+                if let Some(s) = read_string(vm.as_ref(), offset, length) {
                     sim.emit_debug_text(handle, s);
                 }
             }
@@ -281,20 +515,8 @@ impl TeamController {
                     state.get(SystemState::DebugLinesPointer) as u32 + ship_controller.base_address;
                 let length = state.get(SystemState::DebugLinesLength) as u32;
                 if length <= MAX_DEBUG_LINES {
-                    if let Some(lines) = WasmVm::read_vec::<Line>(&memory_view, offset, length) {
-                        if validate_lines(&lines) {
-                            sim.emit_debug_lines(
-                                handle,
-                                lines
-                                    .iter()
-                                    .map(|v| crate::debug::Line {
-                                        a: point![v.x0, v.y0],
-                                        b: point![v.x1, v.y1],
-                                        color: color::from_u24(v.color),
-                                    })
-                                    .collect::<Vec<debug::Line>>(),
-                            );
-                        }
+                    if let Some(lines) = read_vec::<Line>(vm.as_ref(), offset, length) {
+                        emit_debug_lines(sim, handle, &lines);
                     }
                 }
             }
@@ -304,10 +526,8 @@ impl TeamController {
                     state.get(SystemState::DrawnTextPointer) as u32 + ship_controller.base_address;
                 let length = state.get(SystemState::DrawnTextLength) as u32;
                 if length <= MAX_DRAWN_TEXT {
-                    if let Some(texts) = WasmVm::read_vec::<Text>(&memory_view, offset, length) {
-                        if validate_texts(&texts) {
-                            sim.emit_drawn_text(Some(handle), &texts);
-                        }
+                    if let Some(texts) = read_vec::<Text>(vm.as_ref(), offset, length) {
+                        emit_drawn_text(sim, handle, &texts);
                     }
                 }
             }
@@ -319,181 +539,166 @@ impl TeamController {
     pub fn update_environment(&mut self, environment: &Environment) -> Result<(), Error> {
         self.environment = environment.clone();
         for (_, ship_controller) in self.ship_controllers.iter_mut() {
-            self.vm
-                .update_environment(ship_controller.environment_ptr, environment)?;
+            write_environment(
+                self.vm.as_mut(),
+                ship_controller.environment_address,
+                environment,
+            )?;
         }
         Ok(())
     }
 }
 
-#[derive(Clone)]
-pub struct WasmVm {
-    store: Rc<RefCell<wasmer::Store>>,
-    memory: wasmer::Memory,
-    system_state_offset: u32,
-    environment_offset: u32,
-    panic_buffer_offset: u32,
-    tick_ship: wasmer::Function,
-    reset_gas: wasmer::Function,
-    get_gas: wasmer::Function,
-    add_submemory: wasmer::Function,
-    select_submemory: wasmer::Function,
-    reset_submemory: wasmer::Function,
-}
-
-impl WasmVm {
-    pub fn create(code: &Code) -> Result<WasmVm, Error> {
-        #[cfg(feature = "js")]
-        let mut store = Store::default();
-        #[cfg(feature = "sys")]
-        let mut store = Store::new(wasmer_compiler_cranelift::Cranelift::new());
-        let module = match code {
-            Code::Wasm(wasm) => {
-                let wasm = wasm_submemory::rewrite(wasm, SUBMEMORY_SIZE)?;
-                let wasm = limiter::rewrite(&wasm)?;
-                translate_error(Module::new(&store, wasm))?
-            }
-            #[cfg(feature = "precompile")]
-            Code::Precompiled(bytes) => {
-                translate_error(unsafe { Module::deserialize(&store, bytes.clone()) })?
-            }
-            _ => unreachable!(),
-        };
-        let import_object = imports! {};
-        let instance = Instance::new(&mut store, &module, &import_object)?;
-
-        let memory = translate_error(instance.exports.get_memory("memory"))?.clone();
-        let system_state_offset: u32 = translate_error(instance.exports.get_global("SYSTEM_STATE"))?
-            .get(&mut store)
-            .i32()
-            .unwrap() as u32;
-        let environment_offset: u32 = translate_error(instance.exports.get_global("ENVIRONMENT"))?
-            .get(&mut store)
-            .i32()
-            .unwrap() as u32;
-        let panic_buffer_offset: u32 = translate_error(instance.exports.get_global("PANIC_BUFFER"))?
-            .get(&mut store)
-            .i32()
-            .unwrap() as u32;
-
-        let tick_ship = translate_error(instance.exports.get_function("tick"))?.clone();
-        let reset_gas = translate_error(instance.exports.get_function("reset_gas"))?.clone();
-        let get_gas = translate_error(instance.exports.get_function("get_gas"))?.clone();
-        let add_submemory =
-            translate_error(instance.exports.get_function("add_submemory"))?.clone();
-        let select_submemory =
-            translate_error(instance.exports.get_function("select_submemory"))?.clone();
-        let reset_submemory =
-            translate_error(instance.exports.get_function("reset_submemory"))?.clone();
-
-        translate_runtime_error(reset_gas.call(&mut store, &[GAS_PER_TICK.into()]))?;
-
-        Ok(WasmVm {
-            store: Rc::new(RefCell::new(store)),
-            memory,
-            system_state_offset,
-            environment_offset,
-            panic_buffer_offset,
-            tick_ship,
-            reset_gas,
-            get_gas,
-            add_submemory,
-            select_submemory,
-            reset_submemory,
-        })
-    }
+#[derive(Serialize, Deserialize)]
+pub struct ShipControllerCheckpoint {
+    index: u32,
+    base_address: u32,
+    state: Vec<u64>,
+}
 
-    fn store(&self) -> Ref<'_, Store> {
-        self.store.borrow()
-    }
+#[derive(Serialize, Deserialize)]
+pub struct TeamControllerCheckpoint {
+    code: Code,
+    limits: VmLimits,
+    memory: Vec<u8>,
+    ship_controllers: Vec<(ShipHandle, ShipControllerCheckpoint)>,
+    next_id: u32,
+    free_submemories: Vec<(u32, u32)>,
+    environment: Environment,
+}
+
+fn read_string(vm: &dyn Vm, offset: u32, length: u32) -> Option<String> {
+    let mut bytes = vec![0; length as usize];
+    vm.read(offset, &mut bytes).ok()?;
+    String::from_utf8(bytes).ok()
+}
 
-    fn store_mut(&self) -> RefMut<'_, Store> {
-        self.store.borrow_mut()
+fn read_vec<T: Default + Clone>(vm: &dyn Vm, offset: u32, length: u32) -> Option<Vec<T>> {
+    let byte_length = length.checked_mul(std::mem::size_of::<T>() as u32)?;
+    let mut bytes = vec![0u8; byte_length as usize];
+    vm.read(offset, &mut bytes).ok()?;
+    let src_ptr = bytes.as_ptr() as *const T;
+    Some(
+        (0..length as usize)
+            .map(|i| unsafe { std::ptr::read_unaligned(src_ptr.add(i)) })
+            .collect(),
+    )
+}
+
+fn read_u64s(vm: &dyn Vm, offset: u32, values: &mut [u64]) -> Result<(), Error> {
+    let mut bytes = vec![0u8; values.len() * 8];
+    vm.read(offset, &mut bytes)?;
+    for (value, chunk) in values.iter_mut().zip(bytes.chunks_exact(8)) {
+        *value = u64::from_le_bytes(chunk.try_into().unwrap());
     }
+    Ok(())
+}
 
-    fn read_string(memory_view: &MemoryView, offset: u32, length: u32) -> Option<String> {
-        let ptr: WasmPtr<u8> = WasmPtr::new(offset);
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.resize(length as usize, 0);
-        let slice = ptr.slice(memory_view, length).ok()?;
-        slice.read_slice(&mut bytes).ok()?;
-        String::from_utf8(bytes).ok()
+fn write_u64s(vm: &mut dyn Vm, offset: u32, values: &[u64]) -> Result<(), Error> {
+    let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    vm.write(offset, &bytes)
+}
+
+// Writes to an offset within a ship's submemory. The offset comes from the
+// guest, so writes that would land in another ship's memory are rejected.
+fn write_submemory(
+    vm: &mut dyn Vm,
+    base_address: u32,
+    memory_size: u32,
+    offset: u32,
+    values: &[u64],
+) -> Result<(), Error> {
+    let len = (values.len() * 8) as u32;
+    match offset.checked_add(len) {
+        Some(end) if end <= memory_size => write_u64s(vm, base_address + offset, values),
+        _ => Err(Error {
+            msg: "Write outside of submemory".to_string(),
+        }),
     }
+}
+
+fn write_environment(vm: &mut dyn Vm, offset: u32, environment: &Environment) -> Result<(), Error> {
+    vm.write(offset, format_environment(environment)?.as_bytes())
+}
 
-    fn read_vec<T: Default + Clone>(
-        memory_view: &MemoryView,
-        offset: u32,
-        length: u32,
-    ) -> Option<Vec<T>> {
-        let ptr: WasmPtr<u8> = WasmPtr::new(offset);
-        let byte_length = length.saturating_mul(std::mem::size_of::<T>() as u32);
-        let slice = ptr.slice(memory_view, byte_length).ok()?;
-        let byte_vec = slice.read_to_vec().ok()?;
-        let src_ptr = unsafe { std::mem::transmute::<*const u8, *const T>(byte_vec.as_ptr()) };
-        let src_slice = unsafe { std::slice::from_raw_parts(src_ptr, length as usize) };
-        Some(src_slice.to_vec())
+fn format_environment(environment: &Environment) -> Result<String, Error> {
+    let environment_string = environment
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<String>>()
+        .join("\n");
+    if environment_string.len() > oort_api::MAX_ENVIRONMENT_SIZE {
+        return Err(Error {
+            msg: "environment too large".to_string(),
+        });
     }
+    Ok(environment_string)
+}
 
-    fn update_environment(&self, ptr: WasmPtr<u8>, environment: &Environment) -> Result<(), Error> {
-        let environment_string = environment
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect::<Vec<String>>()
-            .join("\n");
-        if environment_string.len() > oort_api::MAX_ENVIRONMENT_SIZE {
-            return Err(Error {
-                msg: "environment too large".to_string(),
-            });
-        }
-        let store = self.store_mut();
-        let view = self.memory.view(&store);
-        let slice = ptr
-            .slice(&view, environment_string.bytes().len() as u32)
-            .ok()
-            .unwrap();
-        slice
-            .write_slice(environment_string.as_bytes())
-            .ok()
-            .unwrap();
-        Ok(())
+fn initial_system_state(sim: &Simulation, handle: ShipHandle, id: u32) -> LocalSystemState {
+    let mut state = LocalSystemState::new();
+    state.set(
+        SystemState::Seed,
+        (make_seed(sim.seed(), handle) & 0xffffff) as f64,
+    );
+    state.set(SystemState::Id, id as f64);
+    if let Some(radar) = sim.ship(handle).data().radar.as_ref() {
+        state.set(SystemState::RadarHeading, radar.heading);
+        state.set(SystemState::RadarWidth, radar.width);
+        state.set(SystemState::RadarMinDistance, radar.min_distance);
+        state.set(SystemState::RadarMaxDistance, radar.max_distance);
     }
+    state
+}
 
-    fn add_submemory(&mut self) -> Result<(u32, u32), Error> {
-        let mut store = self.store_mut();
-        let ret = self.add_submemory.call(store.deref_mut(), &[]).unwrap(); // XXX;
-        match *ret {
-            [wasmer::Value::I32(index), wasmer::Value::I32(base_address)] => {
-                Ok((index as u32, base_address as u32))
-            }
-            _ => Err(Error {
-                msg: "unexpected add_submemory return value".to_string(),
-            }),
-        }
+fn emit_crash_particles(sim: &mut Simulation, handle: ShipHandle) {
+    let mut rng = new_rng(sim.tick());
+    if rng.gen_range(0.0..1.0) < 0.2 {
+        let color = vector![0.5, 0.5, 0.9, rng.gen_range(0.5..1.0)];
+        let rot = Rotation2::new(rng.gen_range(0.0..TAU));
+        let speed = 300.0 * rng.gen_range(0.0..1.0);
+        let p = sim.ship(handle).position().vector;
+        let v = sim.ship(handle).body().linvel() + rot.transform_vector(&vector![speed, 0.0]);
+        let offset = v * rng.gen_range(0.0..PHYSICS_TICK_LENGTH);
+        sim.events.particles.push(Particle {
+            position: p + offset,
+            velocity: v,
+            color,
+            lifetime: 1.0,
+        });
     }
+}
 
-    fn select_submemory(&mut self, index: u32) -> Result<(), Error> {
-        let mut store = self.store_mut();
-        self.select_submemory
-            .call(store.deref_mut(), &[wasmer::Value::I32(index as i32)])
-            .unwrap(); // XXX;
-        Ok(())
+fn emit_debug_lines(sim: &mut Simulation, handle: ShipHandle, lines: &[Line]) {
+    if lines.len() > MAX_DEBUG_LINES as usize || !validate_lines(lines) {
+        return;
     }
+    sim.emit_debug_lines(
+        handle,
+        lines
+            .iter()
+            .map(|v| crate::debug::Line {
+                a: point![v.x0, v.y0],
+                b: point![v.x1, v.y1],
+                color: color::from_u24(v.color),
+            })
+            .collect::<Vec<debug::Line>>(),
+    );
+}
 
-    fn reset_submemory(&mut self, index: u32) -> Result<(), Error> {
-        let mut store = self.store_mut();
-        self.reset_submemory
-            .call(store.deref_mut(), &[wasmer::Value::I32(index as i32)])
-            .unwrap(); // XXX;
-        Ok(())
+fn emit_drawn_text(sim: &mut Simulation, handle: ShipHandle, texts: &[Text]) {
+    if texts.len() > MAX_DRAWN_TEXT as usize || !validate_texts(texts) {
+        return;
     }
+    sim.emit_drawn_text(Some(handle), texts);
 }
 
-struct LocalSystemState {
+pub(crate) struct LocalSystemState {
     pub state: [u64; SystemState::Size as usize],
 }
 
 impl LocalSystemState {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             state: [0; SystemState::Size as usize],
         }
@@ -507,7 +712,7 @@ impl LocalSystemState {
         self.state[index as usize] = value;
     }
 
-    fn get(&self, index: SystemState) -> f64 {
+    pub(crate) fn get(&self, index: SystemState) -> f64 {
         let v = f64::from_bits(self.get_u64(index));
         if v.is_nan() || v.is_infinite() {
             0.0
@@ -516,12 +721,16 @@ impl LocalSystemState {
         }
     }
 
-    fn set(&mut self, index: SystemState, value: f64) {
+    pub(crate) fn set(&mut self, index: SystemState, value: f64) {
         self.set_u64(index, value.to_bits());
     }
 }
 
-fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut LocalSystemState) {
+pub(crate) fn generate_system_state(
+    sim: &mut Simulation,
+    handle: ShipHandle,
+    state: &mut LocalSystemState,
+) {
     state.set(
         SystemState::Class,
         translate_class(sim.ship(handle).data().class) as u32 as f64,
@@ -562,6 +771,11 @@ fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut L
         } else {
             state.set(SystemState::RadarContactFound, 0.0);
         }
+
+        state.set(
+            SystemState::RadarContactsLength,
+            radar.scan_all().len().min(oort_api::MAX_RADAR_CONTACTS) as f64,
+        );
     } else if let Some(target) = sim.ship(handle).data().target.as_ref() {
         state.set(SystemState::RadarContactFound, 1.0);
         state.set(SystemState::RadarContactPositionX, target.position.x);
@@ -627,7 +841,11 @@ fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut L
     }
 }
 
-fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut LocalSystemState) {
+pub(crate) fn apply_system_state(
+    sim: &mut Simulation,
+    handle: ShipHandle,
+    state: &mut LocalSystemState,
+) {
     sim.ship_mut(handle).accelerate(Vec2::new(
         state.get(SystemState::AccelerateX),
         state.get(SystemState::AccelerateY),
@@ -660,6 +878,8 @@ fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut Loca
         radar.set_min_distance(state.get(SystemState::RadarMinDistance));
         radar.set_max_distance(state.get(SystemState::RadarMaxDistance));
         radar.set_ecm_mode(translate_ecm_mode(state.get(SystemState::RadarEcmMode)));
+        radar.set_mode(RadarMode::from(state.get(SystemState::RadarMode)));
+        radar.set_enabled(state.get(SystemState::RadarDisabled) == 0.0);
     }
 
     let active_abilities = ActiveAbilities(state.get_u64(SystemState::ActivateAbility));
@@ -680,6 +900,7 @@ fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut Loca
         state.set(SystemState::Explode, 0.0);
     }
 
+    let radio_ecm_modes = state.get_u64(SystemState::RadioEcmModes);
     for (i, radio) in sim
         .ship_mut(handle)
         .data_mut()
@@ -689,6 +910,11 @@ fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut Loca
     {
         let idxs = oort_api::prelude::radio_internal::radio_indices(i);
         radio.set_channel(state.get(idxs.channel) as usize);
+        radio.set_ecm_mode(if radio_ecm_modes & (1 << i) != 0 {
+            EcmMode::Noise
+        } else {
+            EcmMode::None
+        });
         if state.get(idxs.send) != 0.0 {
             let msg = [
                 state.get(idxs.data[0]),
@@ -701,6 +927,96 @@ fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut Loca
     }
 }
 
+// Copies track-while-scan contacts into the ship's exported buffer.
+fn write_radar_contacts(
+    sim: &Simulation,
+    handle: ShipHandle,
+    state: &mut LocalSystemState,
+    base_address: u32,
+    memory_size: u32,
+    vm: &mut dyn Vm,
+) -> Result<(), Error> {
+    let Some(offset) = vm.offsets().radar_contacts else {
+        state.set(SystemState::RadarContactsLength, 0.0);
+        return Ok(());
+    };
+
+    let data = radar_contacts_data(sim, handle, state);
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let data: Vec<u64> = data.iter().map(|x| x.to_bits()).collect();
+    write_submemory(vm, base_address, memory_size, offset, &data).map_err(|_| Error {
+        msg: "Failed to write radar contacts".to_string(),
+    })
+}
+
+fn radar_contacts_data(sim: &Simulation, handle: ShipHandle, state: &LocalSystemState) -> Vec<f64> {
+    let n = state.get(SystemState::RadarContactsLength) as usize;
+    let contacts = match sim.ship(handle).data().radar.as_ref() {
+        Some(radar) if n > 0 => radar.scan_all(),
+        _ => return vec![],
+    };
+
+    let mut data: Vec<f64> = Vec::with_capacity(n * oort_api::RADAR_CONTACT_SIZE);
+    for contact in contacts.iter().take(n) {
+        data.extend([
+            translate_class(contact.class) as u32 as f64,
+            contact.position.x,
+            contact.position.y,
+            contact.velocity.x,
+            contact.velocity.y,
+            contact.rssi,
+            contact.snr,
+        ]);
+    }
+    data
+}
+
+// Copies ESM contacts into the ship's exported buffer.
+fn write_esm_contacts(
+    sim: &Simulation,
+    handle: ShipHandle,
+    state: &mut LocalSystemState,
+    base_address: u32,
+    memory_size: u32,
+    vm: &mut dyn Vm,
+) -> Result<(), Error> {
+    let Some(offset) = vm.offsets().esm_contacts else {
+        state.set(SystemState::EsmContactsLength, 0.0);
+        return Ok(());
+    };
+
+    let data = esm_contacts_data(sim, handle, state);
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let data: Vec<u64> = data.iter().map(|x| x.to_bits()).collect();
+    write_submemory(vm, base_address, memory_size, offset, &data).map_err(|_| Error {
+        msg: "Failed to write ESM contacts".to_string(),
+    })
+}
+
+fn esm_contacts_data(
+    sim: &Simulation,
+    handle: ShipHandle,
+    state: &mut LocalSystemState,
+) -> Vec<f64> {
+    let Some(esm) = sim.ship(handle).data().esm.as_ref() else {
+        state.set(SystemState::EsmContactsLength, 0.0);
+        return vec![];
+    };
+
+    let contacts = &esm.contacts()[..esm.contacts().len().min(oort_api::MAX_ESM_CONTACTS)];
+    state.set(SystemState::EsmContactsLength, contacts.len() as f64);
+    contacts
+        .iter()
+        .flat_map(|contact| [contact.bearing, contact.rssi])
+        .collect()
+}
+
 fn translate_class(class: ShipClass) -> Class {
     match class {
         ShipClass::Fighter => Class::Fighter,
@@ -725,27 +1041,6 @@ fn translate_ecm_mode(v: f64) -> EcmMode {
     }
 }
 
-fn translate_error<T, U>(err: Result<T, U>) -> Result<T, Error>
-where
-    U: std::fmt::Debug,
-{
-    match err {
-        Ok(val) => Ok(val),
-        Err(err) => Err(Error {
-            msg: format!("Wasmer error: {err:?}"),
-        }),
-    }
-}
-
-fn translate_runtime_error<T>(err: Result<T, wasmer::RuntimeError>) -> Result<T, Error> {
-    match err {
-        Ok(val) => Ok(val),
-        Err(err) => Err(Error {
-            msg: format!("Ship runtime error: {err:?}"),
-        }),
-    }
-}
-
 fn validate_floats(vs: &[f64]) -> bool {
     vs.iter().all(|v| v.is_finite())
 }
@@ -762,15 +1057,6 @@ fn validate_texts(texts: &[Text]) -> bool {
         .all(|t| validate_floats(&[t.x, t.y]) && t.length as usize <= t.text.len())
 }
 
-#[cfg(feature = "precompile")]
-pub fn precompile(wasm: &[u8]) -> Result<Code, Error> {
-    let wasm = wasm_submemory::rewrite(wasm, SUBMEMORY_SIZE)?;
-    let wasm = limiter::rewrite(&wasm)?;
-    let store = Store::default();
-    let module = translate_error(Module::new(&store, wasm))?;
-    Ok(Code::Precompiled(translate_error(module.serialize())?))
-}
-
 fn make_seed(sim_seed: u32, handle: ShipHandle) -> i64 {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::Hasher;
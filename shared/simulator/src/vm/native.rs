@@ -0,0 +1,159 @@
+use super::{
+    apply_system_state, emit_debug_lines, emit_drawn_text, esm_contacts_data, format_environment,
+    generate_system_state, initial_system_state, radar_contacts_data, Environment, Error,
+    LocalSystemState,
+};
+use crate::ship::ShipHandle;
+use crate::simulation::Simulation;
+use oort_api::native::Context;
+use oort_api::SystemState;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+type ShipFactory = dyn Fn() -> Box<dyn FnMut()> + Send + Sync;
+
+// Ship code compiled into the host binary. Runs without a VM, so it can be
+// unit tested, debugged and measured for coverage like any other Rust code.
+//
+// Example:
+//
+//     let code = Code::Native(NativeCode::new(Ship::new, Ship::tick));
+#[derive(Clone)]
+pub struct NativeCode {
+    factory: Arc<ShipFactory>,
+}
+
+impl NativeCode {
+    pub fn new<S: 'static>(new: impl Fn() -> S + Send + Sync + 'static, tick: fn(&mut S)) -> Self {
+        let new = Arc::new(new);
+        Self {
+            factory: Arc::new(move || -> Box<dyn FnMut()> {
+                let new = new.clone();
+                // Like the wasm entry point, the ship is constructed during
+                // its first tick so that the API is usable in Ship::new.
+                let mut ship: Option<S> = None;
+                Box::new(move || tick(ship.get_or_insert_with(|| new())))
+            }),
+        }
+    }
+
+    fn id(&self) -> *const u8 {
+        Arc::as_ptr(&self.factory) as *const u8
+    }
+}
+
+impl std::fmt::Debug for NativeCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "NativeCode({:?})", self.id())
+    }
+}
+
+impl PartialEq for NativeCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+impl Eq for NativeCode {}
+
+impl std::hash::Hash for NativeCode {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id().hash(state);
+    }
+}
+
+struct NativeShipController {
+    state: LocalSystemState,
+    context: Context,
+    tick: Box<dyn FnMut()>,
+}
+
+pub struct NativeTeamController {
+    code: NativeCode,
+    ship_controllers: HashMap<ShipHandle, NativeShipController>,
+    next_id: u32,
+    environment: Environment,
+}
+
+impl NativeTeamController {
+    pub fn new(code: &NativeCode) -> Self {
+        Self {
+            code: code.clone(),
+            ship_controllers: HashMap::new(),
+            next_id: 1,
+            environment: Environment::new(),
+        }
+    }
+
+    pub fn handles(&self) -> Vec<ShipHandle> {
+        self.ship_controllers.keys().cloned().collect()
+    }
+
+    pub fn add_ship(&mut self, handle: ShipHandle, sim: &Simulation) -> Result<(), Error> {
+        let state = initial_system_state(sim, handle, self.next_id);
+        self.next_id += 1;
+        let mut context = Context::new();
+        write_environment(&mut context, &self.environment)?;
+        self.ship_controllers.insert(
+            handle,
+            NativeShipController {
+                state,
+                context,
+                tick: (self.code.factory)(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_ship(&mut self, handle: ShipHandle) {
+        self.ship_controllers.remove(&handle);
+    }
+
+    pub fn tick_ship(&mut self, sim: &mut Simulation, handle: ShipHandle) -> Result<(), Error> {
+        let ship_controller = self.ship_controllers.get_mut(&handle).unwrap();
+        let state = &mut ship_controller.state;
+        let context = &mut ship_controller.context;
+
+        generate_system_state(sim, handle, state);
+        let esm_contacts = esm_contacts_data(sim, handle, state);
+        context.esm_contacts[..esm_contacts.len()].copy_from_slice(&esm_contacts);
+        let radar_contacts = radar_contacts_data(sim, handle, state);
+        context.radar_contacts[..radar_contacts.len()].copy_from_slice(&radar_contacts);
+        context.system_state[..SystemState::Size as usize].copy_from_slice(&state.state);
+
+        let (result, output) = context.run(&mut ship_controller.tick);
+        result.map_err(|msg| Error { msg })?;
+
+        state
+            .state
+            .copy_from_slice(&context.system_state[..SystemState::Size as usize]);
+        apply_system_state(sim, handle, state);
+
+        if !output.text.is_empty() {
+            sim.emit_debug_text(handle, output.text);
+        }
+        if !output.lines.is_empty() {
+            emit_debug_lines(sim, handle, &output.lines);
+        }
+        if !output.drawn_text.is_empty() {
+            emit_drawn_text(sim, handle, &output.drawn_text);
+        }
+
+        Ok(())
+    }
+
+    pub fn update_environment(&mut self, environment: &Environment) -> Result<(), Error> {
+        self.environment = environment.clone();
+        for ship_controller in self.ship_controllers.values_mut() {
+            write_environment(&mut ship_controller.context, environment)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_environment(context: &mut Context, environment: &Environment) -> Result<(), Error> {
+    let environment_string = format_environment(environment)?;
+    context.environment.fill(0);
+    context.environment[..environment_string.len()].copy_from_slice(environment_string.as_bytes());
+    Ok(())
+}
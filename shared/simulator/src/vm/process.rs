@@ -0,0 +1,220 @@
+// Drives a team's ships from an external process, so strategies can be
+// written in any language.
+//
+// The process reads requests from stdin and writes responses to stdout, one
+// JSON object per line. Each tick the simulator sends one request per ship:
+//
+//     {"id": 1, "environment": {"KEY": "value"}, "state": [...],
+//      "radar_contacts": [...], "esm_contacts": [...]}
+//
+// "state" is the ship's system state, indexed the same way as SYSTEM_STATE in
+// shared/c-api/oort.h. "radar_contacts" and "esm_contacts" are the flattened
+// contents of oort_api's RADAR_CONTACTS and ESM_CONTACTS buffers, holding as
+// many contacts as the lengths in the state say. Non-finite numbers are sent
+// as null, and null in a response is read as zero. The process replies with
+// the updated state and optional debug text:
+//
+//     {"state": [...], "debug": "text"}
+//
+// Requests are sent in ship order and the simulator waits for each response,
+// so the process must answer every request before reading the next one. A
+// process that takes longer than RESPONSE_TIMEOUT to answer is killed and its
+// ships crash.
+use super::{
+    apply_system_state, esm_contacts_data, generate_system_state, initial_system_state,
+    radar_contacts_data, Environment, Error, LocalSystemState,
+};
+use crate::ship::ShipHandle;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct Request<'a> {
+    id: u32,
+    environment: &'a Environment,
+    state: Vec<f64>,
+    radar_contacts: Vec<f64>,
+    esm_contacts: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    state: Vec<Option<f64>>,
+    #[serde(default)]
+    debug: String,
+}
+
+struct ProcessShipController {
+    id: u32,
+    state: LocalSystemState,
+}
+
+pub struct ProcessTeamController {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    // Lines read from the process's stdout by a separate thread, so waiting
+    // for a response can time out.
+    responses: mpsc::Receiver<std::io::Result<String>>,
+    ship_controllers: HashMap<ShipHandle, ProcessShipController>,
+    next_id: u32,
+    environment: Environment,
+}
+
+impl ProcessTeamController {
+    pub fn create(command: &[String]) -> Result<ProcessTeamController, Error> {
+        let (program, args) = command.split_first().ok_or_else(|| Error {
+            msg: "Empty process controller command".to_string(),
+        })?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error {
+                msg: format!("Failed to start process controller {program:?}: {e}"),
+            })?;
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let (tx, responses) = mpsc::channel();
+        std::thread::spawn(move || loop {
+            let mut line = String::new();
+            match stdout.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if tx.send(Ok(line)).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    break;
+                }
+            }
+        });
+        Ok(ProcessTeamController {
+            child,
+            stdin,
+            responses,
+            ship_controllers: HashMap::new(),
+            next_id: 1,
+            environment: Environment::new(),
+        })
+    }
+
+    pub fn handles(&self) -> Vec<ShipHandle> {
+        self.ship_controllers.keys().cloned().collect()
+    }
+
+    pub fn add_ship(&mut self, handle: ShipHandle, sim: &Simulation) -> Result<(), Error> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.ship_controllers.insert(
+            handle,
+            ProcessShipController {
+                id,
+                state: initial_system_state(sim, handle, id),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn remove_ship(&mut self, handle: ShipHandle) {
+        self.ship_controllers.remove(&handle);
+    }
+
+    pub fn tick_ship(&mut self, sim: &mut Simulation, handle: ShipHandle) -> Result<(), Error> {
+        let ship_controller = self.ship_controllers.get_mut(&handle).unwrap();
+        let state = &mut ship_controller.state;
+
+        generate_system_state(sim, handle, state);
+        let esm_contacts = esm_contacts_data(sim, handle, state);
+        let radar_contacts = radar_contacts_data(sim, handle, state);
+        let request = Request {
+            id: ship_controller.id,
+            environment: &self.environment,
+            state: state.state.iter().map(|&x| f64::from_bits(x)).collect(),
+            radar_contacts,
+            esm_contacts,
+        };
+        let response = match exchange(&mut self.stdin, &self.responses, &request) {
+            Ok(response) => response,
+            Err(e) => {
+                // A late response would be mistaken for the next ship's.
+                let _ = self.child.kill();
+                let _ = self.child.wait();
+                return Err(e);
+            }
+        };
+        if response.state.len() != state.state.len() {
+            return Err(Error {
+                msg: format!(
+                    "Process controller returned {} state values, expected {}",
+                    response.state.len(),
+                    state.state.len()
+                ),
+            });
+        }
+
+        for (dst, src) in state.state.iter_mut().zip(response.state) {
+            *dst = src.unwrap_or(0.0).to_bits();
+        }
+        apply_system_state(sim, handle, state);
+
+        if !response.debug.is_empty() {
+            sim.emit_debug_text(handle, response.debug);
+        }
+
+        Ok(())
+    }
+
+    pub fn update_environment(&mut self, environment: &Environment) -> Result<(), Error> {
+        self.environment = environment.clone();
+        Ok(())
+    }
+}
+
+impl Drop for ProcessTeamController {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn exchange(
+    stdin: &mut BufWriter<ChildStdin>,
+    responses: &mpsc::Receiver<std::io::Result<String>>,
+    request: &Request,
+) -> Result<Response, Error> {
+    let translate_error = |e: std::io::Error| Error {
+        msg: format!("Process controller I/O error: {e}"),
+    };
+    serde_json::to_writer(&mut *stdin, request).map_err(|e| Error {
+        msg: format!("Failed to encode process controller request: {e}"),
+    })?;
+    stdin.write_all(b"\n").map_err(translate_error)?;
+    stdin.flush().map_err(translate_error)?;
+
+    let line = match responses.recv_timeout(RESPONSE_TIMEOUT) {
+        Ok(line) => line.map_err(translate_error)?,
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            return Err(Error {
+                msg: format!("Process controller timed out after {RESPONSE_TIMEOUT:?}"),
+            })
+        }
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            return Err(Error {
+                msg: "Process controller exited".to_string(),
+            })
+        }
+    };
+    serde_json::from_str(&line).map_err(|e| Error {
+        msg: format!("Invalid process controller response: {e}"),
+    })
+}
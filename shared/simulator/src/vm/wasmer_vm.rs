@@ -0,0 +1,330 @@
+use super::debuginfo::DebugInfo;
+use super::{instrument, Error, Offsets, Vm, VmLimits, GAS_PER_TICK};
+use crate::simulation::Code;
+use wasmer::{imports, Instance, Module, Store};
+
+const MAX_BACKTRACE_FRAMES: usize = 16;
+
+impl From<wasmer::InstantiationError> for Error {
+    fn from(err: wasmer::InstantiationError) -> Self {
+        Self {
+            msg: format!("Wasmer instantiation error: {err:?}"),
+        }
+    }
+}
+
+// Compiles the module with cranelift natively, or with the browser's engine
+// when built with the "js" feature.
+pub struct WasmVm {
+    store: Store,
+    memory: wasmer::Memory,
+    offsets: Offsets,
+    tick_ship: wasmer::Function,
+    reset_gas: wasmer::Function,
+    get_gas: wasmer::Function,
+    add_submemory: wasmer::Function,
+    select_submemory: wasmer::Function,
+    reset_submemory: wasmer::Function,
+    debug_info: Option<DebugInfo>,
+}
+
+impl WasmVm {
+    pub fn create(code: &Code, limits: &VmLimits) -> Result<WasmVm, Error> {
+        #[cfg(feature = "js")]
+        let mut store = Store::default();
+        #[cfg(feature = "sys")]
+        let mut store = Store::new(wasmer_compiler_cranelift::Cranelift::new());
+        let debug_info = match code {
+            Code::Wasm(wasm) => DebugInfo::parse(wasm),
+            _ => None,
+        };
+        let module = match code {
+            Code::Wasm(wasm) => translate_error(Module::new(&store, instrument(wasm, limits)?))?,
+            #[cfg(feature = "precompile")]
+            Code::Precompiled(bytes) => {
+                if limits.memory_size != super::SUBMEMORY_SIZE {
+                    return Err(Error {
+                        msg: "Precompiled code requires the default memory limit".to_string(),
+                    });
+                }
+                translate_error(unsafe { Module::deserialize(&store, bytes.clone()) })?
+            }
+            _ => unreachable!(),
+        };
+        let import_object = imports! {};
+        let instance = Instance::new(&mut store, &module, &import_object)?;
+
+        let memory = translate_error(instance.exports.get_memory("memory"))?.clone();
+        let system_state_offset: u32 = translate_error(instance.exports.get_global("SYSTEM_STATE"))?
+            .get(&mut store)
+            .i32()
+            .unwrap() as u32;
+        let environment_offset: u32 = translate_error(instance.exports.get_global("ENVIRONMENT"))?
+            .get(&mut store)
+            .i32()
+            .unwrap() as u32;
+        let panic_buffer_offset: u32 = translate_error(instance.exports.get_global("PANIC_BUFFER"))?
+            .get(&mut store)
+            .i32()
+            .unwrap() as u32;
+        let esm_contacts_offset: Option<u32> = instance
+            .exports
+            .get_global("ESM_CONTACTS")
+            .ok()
+            .and_then(|global| global.get(&mut store).i32())
+            .map(|offset| offset as u32);
+        let radar_contacts_offset: Option<u32> = instance
+            .exports
+            .get_global("RADAR_CONTACTS")
+            .ok()
+            .and_then(|global| global.get(&mut store).i32())
+            .map(|offset| offset as u32);
+
+        let tick_ship = translate_error(instance.exports.get_function("tick"))?.clone();
+        let reset_gas = translate_error(instance.exports.get_function("reset_gas"))?.clone();
+        let get_gas = translate_error(instance.exports.get_function("get_gas"))?.clone();
+        let add_submemory =
+            translate_error(instance.exports.get_function("add_submemory"))?.clone();
+        let select_submemory =
+            translate_error(instance.exports.get_function("select_submemory"))?.clone();
+        let reset_submemory =
+            translate_error(instance.exports.get_function("reset_submemory"))?.clone();
+
+        translate_runtime_error(
+            reset_gas.call(&mut store, &[(GAS_PER_TICK as i32).into()]),
+            debug_info.as_ref(),
+        )?;
+
+        Ok(WasmVm {
+            store,
+            memory,
+            offsets: Offsets {
+                system_state: system_state_offset,
+                environment: environment_offset,
+                panic_buffer: panic_buffer_offset,
+                esm_contacts: esm_contacts_offset,
+                radar_contacts: radar_contacts_offset,
+            },
+            tick_ship,
+            reset_gas,
+            get_gas,
+            add_submemory,
+            select_submemory,
+            reset_submemory,
+            debug_info,
+        })
+    }
+}
+
+impl Vm for WasmVm {
+    fn offsets(&self) -> &Offsets {
+        &self.offsets
+    }
+
+    fn tick_ship(&mut self) -> Result<(), Error> {
+        translate_runtime_error(
+            self.tick_ship.call(&mut self.store, &[]),
+            self.debug_info.as_ref(),
+        )?;
+        Ok(())
+    }
+
+    fn reset_gas(&mut self, gas: i32) -> Result<(), Error> {
+        translate_runtime_error(
+            self.reset_gas.call(&mut self.store, &[gas.into()]),
+            self.debug_info.as_ref(),
+        )?;
+        Ok(())
+    }
+
+    fn remaining_gas(&mut self) -> Result<i32, Error> {
+        let ret = translate_runtime_error(
+            self.get_gas.call(&mut self.store, &[]),
+            self.debug_info.as_ref(),
+        )?;
+        match *ret {
+            [wasmer::Value::I32(gas)] => Ok(gas),
+            _ => Err(Error {
+                msg: "unexpected get_gas return value".to_string(),
+            }),
+        }
+    }
+
+    fn add_submemory(&mut self) -> Result<(u32, u32), Error> {
+        let ret = self.add_submemory.call(&mut self.store, &[]).unwrap(); // XXX;
+        match *ret {
+            [wasmer::Value::I32(index), wasmer::Value::I32(base_address)] => {
+                Ok((index as u32, base_address as u32))
+            }
+            _ => Err(Error {
+                msg: "unexpected add_submemory return value".to_string(),
+            }),
+        }
+    }
+
+    fn select_submemory(&mut self, index: u32) -> Result<(), Error> {
+        self.select_submemory
+            .call(&mut self.store, &[wasmer::Value::I32(index as i32)])
+            .unwrap(); // XXX;
+        Ok(())
+    }
+
+    fn reset_submemory(&mut self, index: u32) -> Result<(), Error> {
+        self.reset_submemory
+            .call(&mut self.store, &[wasmer::Value::I32(index as i32)])
+            .unwrap(); // XXX;
+        Ok(())
+    }
+
+    fn read(&self, offset: u32, buf: &mut [u8]) -> Result<(), Error> {
+        translate_error(self.memory.view(&self.store).read(offset as u64, buf))
+    }
+
+    fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        translate_error(self.memory.view(&self.store).write(offset as u64, data))
+    }
+
+    fn read_memory(&self) -> Result<Vec<u8>, Error> {
+        translate_error(self.memory.view(&self.store).copy_to_vec())
+    }
+
+    fn write_memory(&mut self, data: &[u8]) -> Result<(), Error> {
+        let current_size = self.memory.view(&self.store).data_size();
+        if data.len() as u64 > current_size {
+            let delta = (data.len() as u64 - current_size) / wasmer::WASM_PAGE_SIZE as u64;
+            translate_error(self.memory.grow(&mut self.store, delta as u32))?;
+        }
+        translate_error(self.memory.view(&self.store).write(0, data))
+    }
+}
+
+#[cfg(feature = "precompile")]
+pub fn precompile(wasm: &[u8]) -> Result<Code, Error> {
+    let wasm = instrument(wasm, &VmLimits::default())?;
+    let store = Store::default();
+    let module = translate_error(Module::new(&store, wasm))?;
+    Ok(Code::Precompiled(translate_error(module.serialize())?))
+}
+
+// Identifies the engine and instrumentation that produced a precompiled
+// module. Serialized modules are only loadable by a matching engine, so
+// anything caching them on disk should key on this.
+#[cfg(feature = "precompile")]
+pub fn engine_version() -> String {
+    format!(
+        "oort_simulator-{}-wasmer-{}-{}",
+        env!("CARGO_PKG_VERSION"),
+        wasmer::VERSION,
+        std::env::consts::ARCH
+    )
+}
+
+fn translate_error<T, U>(err: Result<T, U>) -> Result<T, Error>
+where
+    U: std::fmt::Debug,
+{
+    match err {
+        Ok(val) => Ok(val),
+        Err(err) => Err(Error {
+            msg: format!("Wasmer error: {err:?}"),
+        }),
+    }
+}
+
+fn translate_runtime_error<T>(
+    err: Result<T, wasmer::RuntimeError>,
+    debug_info: Option<&DebugInfo>,
+) -> Result<T, Error> {
+    match err {
+        Ok(val) => Ok(val),
+        Err(err) => Err(Error {
+            msg: format!(
+                "Ship runtime error: {}{}",
+                err.message(),
+                format_backtrace(err.trace(), debug_info)
+            ),
+        }),
+    }
+}
+
+// Function names come from the module's name section. The compiler keeps
+// line tables, which locate each function in the source; offsets are only a
+// fallback since they refer to the instrumented module.
+fn format_backtrace(frames: &[wasmer::FrameInfo], debug_info: Option<&DebugInfo>) -> String {
+    let mut s = String::new();
+    for (i, frame) in frames.iter().take(MAX_BACKTRACE_FRAMES).enumerate() {
+        let location = frame
+            .function_name()
+            .and_then(|name| debug_info?.location(name));
+        let name = match frame.function_name() {
+            Some(name) => strip_symbol_hash(name).to_string(),
+            None => format!("<function {}>", frame.func_index()),
+        };
+        match location {
+            Some(location) => s.push_str(&format!("\n  {i}: {name}, defined at {location}")),
+            None => s.push_str(&format!(
+                "\n  {i}: {name} at offset {:#x}",
+                frame.module_offset()
+            )),
+        }
+    }
+    if frames.len() > MAX_BACKTRACE_FRAMES {
+        s.push_str(&format!(
+            "\n  ... {} more frames",
+            frames.len() - MAX_BACKTRACE_FRAMES
+        ));
+    }
+    s
+}
+
+// The linker demangles Rust symbols but leaves the trailing hash, as in
+// "oort_ai::Ship::tick::h0123456789abcdef".
+fn strip_symbol_hash(name: &str) -> &str {
+    match name.rsplit_once("::h") {
+        Some((prefix, hash)) if hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+            prefix
+        }
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_strip_symbol_hash() {
+        assert_eq!(
+            strip_symbol_hash("oort_ai::user::Ship::tick::h0123456789abcdef"),
+            "oort_ai::user::Ship::tick"
+        );
+        assert_eq!(strip_symbol_hash("tick"), "tick");
+        assert_eq!(strip_symbol_hash("foo::hash"), "foo::hash");
+    }
+
+    #[cfg(feature = "sys")]
+    #[test]
+    fn test_backtrace() {
+        let wasm = wabt::Wat2Wasm::new()
+            .write_debug_names(true)
+            .convert(
+                "
+(module
+    (func $inner
+        unreachable)
+    (func $outer (export \"outer\")
+        call $inner))
+",
+            )
+            .unwrap();
+        let mut store = Store::new(wasmer_compiler_cranelift::Cranelift::new());
+        let module = Module::new(&store, wasm.as_ref()).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let outer = instance.exports.get_function("outer").unwrap();
+        let msg = translate_runtime_error(outer.call(&mut store, &[]), None)
+            .unwrap_err()
+            .msg;
+        assert!(msg.contains("0: inner at offset"), "{msg}");
+        assert!(msg.contains("1: outer at offset"), "{msg}");
+    }
+}
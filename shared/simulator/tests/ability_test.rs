@@ -1,8 +1,9 @@
 use nalgebra::vector;
 use oort_api::Ability;
-use oort_simulator::ship;
-use oort_simulator::ship::{cruiser, fighter, frigate, torpedo, ShipClass};
+use oort_simulator::ship::{cruiser, fighter, frigate, missile, torpedo, ShipClass};
 use oort_simulator::simulation::{self, Code, PHYSICS_TICK_LENGTH};
+use oort_simulator::{bullet, ship};
+use std::f64::consts::PI;
 use test_log::test;
 
 #[test]
@@ -127,3 +128,107 @@ fn test_shield() {
     assert_ne!(sim.ship(ship0).data().health, frigate(0).health);
     assert_eq!(sim.ship(ship1).data().health, cruiser(1).health);
 }
+
+// Explodes a missile flying up the screen while pointed right, with a
+// stationary target above it.
+fn explode_missile(shaped_charge: bool) -> Vec<(f64, f32)> {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None, Code::None]);
+    let missile_velocity = vector![0.0, 100.0];
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        missile_velocity,
+        0.0,
+        missile(0),
+    );
+    ship::create(
+        &mut sim,
+        vector![0.0, 500.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(1),
+    );
+    sim.ship_mut(ship0)
+        .radar_mut()
+        .unwrap()
+        .set_heading(PI / 2.0);
+    sim.step();
+    assert!(sim.ship(ship0).radar().unwrap().scan().is_some());
+
+    if shaped_charge {
+        sim.ship_mut(ship0).activate_ability(Ability::ShapedCharge);
+        assert!(sim.ship(ship0).is_ability_active(Ability::ShapedCharge));
+    }
+    let missile_velocity = sim.ship(ship0).velocity();
+    sim.ship_mut(ship0).explode();
+
+    sim.bullets
+        .iter()
+        .map(|&handle| {
+            let v = bullet::body(&sim, handle).linvel() - missile_velocity;
+            (v.y.atan2(v.x), bullet::data(&sim, handle).mass)
+        })
+        .collect()
+}
+
+#[test]
+fn test_warhead_without_shaped_charge() {
+    let fragments = explode_missile(false);
+    assert_eq!(fragments.len(), missile(0).warhead.count as usize);
+    for (angle, mass) in fragments {
+        assert!(angle.abs() <= 0.2, "angle {angle}");
+        assert_eq!(mass, missile(0).warhead.mass);
+    }
+}
+
+#[test]
+fn test_shaped_charge() {
+    let fragments = explode_missile(true);
+    assert_eq!(fragments.len(), missile(0).warhead.count as usize);
+    for (angle, mass) in fragments {
+        // Aimed at the target rather than along the missile's heading.
+        assert!((angle - PI / 2.0).abs() <= 0.02, "angle {angle}");
+        assert!(mass > missile(0).warhead.mass);
+    }
+}
+
+#[test]
+fn test_shaped_charge_reload() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None, Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        torpedo(0),
+    );
+
+    sim.ship_mut(ship0).activate_ability(Ability::ShapedCharge);
+    for _ in 0..120 {
+        sim.step();
+    }
+    assert!(!sim.ship(ship0).is_ability_active(Ability::ShapedCharge));
+
+    sim.ship_mut(ship0).activate_ability(Ability::ShapedCharge);
+    assert!(!sim.ship(ship0).is_ability_active(Ability::ShapedCharge));
+
+    for _ in 0..(9 * 60) {
+        sim.step();
+    }
+    sim.ship_mut(ship0).activate_ability(Ability::ShapedCharge);
+    assert!(sim.ship(ship0).is_ability_active(Ability::ShapedCharge));
+}
+
+#[test]
+fn test_shaped_charge_unavailable() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None, Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.ship_mut(ship0).activate_ability(Ability::ShapedCharge);
+    assert!(!sim.ship(ship0).is_ability_active(Ability::ShapedCharge));
+}
@@ -99,3 +99,47 @@ fn test_id() {
     check(ship_handles[1], 2);
     check(ship_handles[2], 1);
 }
+
+#[test]
+fn test_gas_used() {
+    let mut sim = simulation::Simulation::new(
+        "test",
+        0,
+        &[
+            Code::Builtin("test".to_string()),
+            Code::Builtin("test".to_string()),
+        ],
+    );
+    let mut env = BTreeMap::new();
+    env.insert("TESTCASE".to_string(), "id".to_string());
+    sim.update_environment(0, env);
+    let mut env = BTreeMap::new();
+    env.insert("TESTCASE".to_string(), "infinite_loop".to_string());
+    sim.update_environment(1, env);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let ship1 = ship::create(
+        &mut sim,
+        vector![1000.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(1),
+    );
+    sim.step();
+
+    let gas_used = &sim.events().gas_used;
+    let gas0 = gas_used[&ship0.into()];
+    assert!(gas0 > 0 && gas0 < 1_000_000, "gas0: {gas0}");
+    // Execution traps when the next block doesn't fit in the remaining gas.
+    assert!(gas_used[&ship1.into()] > 999_000);
+    assert_eq!(sim.snapshot(0).gas_used, *gas_used);
+
+    // Crashed ships don't run.
+    sim.step();
+    assert!(!sim.events().gas_used.contains_key(&ship1.into()));
+}
@@ -0,0 +1,105 @@
+use oort_simulator::history::{self, History};
+use oort_simulator::scenario;
+use oort_simulator::simulation::Simulation;
+use test_log::test;
+
+fn check_restore(scenario_name: &str, checkpoint_tick: u32, ticks: u32) {
+    let scenario = scenario::load(scenario_name);
+    let codes = scenario.solution_codes();
+    let mut sim = Simulation::new(scenario_name, 0, &codes);
+    for _ in 0..checkpoint_tick {
+        sim.step();
+    }
+
+    let checkpoint = sim.checkpoint().unwrap();
+    let mut restored = Simulation::restore(&checkpoint).unwrap();
+    assert_eq!(restored.tick(), sim.tick());
+    assert_eq!(restored.hash(), sim.hash());
+
+    for _ in 0..ticks {
+        sim.step();
+        restored.step();
+        assert_eq!(
+            restored.hash(),
+            sim.hash(),
+            "hash mismatch at tick {}",
+            sim.tick()
+        );
+    }
+    assert_eq!(restored.status(), sim.status());
+}
+
+#[test]
+fn test_fighter_duel() {
+    check_restore("fighter_duel", 300, 300);
+}
+
+#[test]
+fn test_planetary_defense() {
+    check_restore("planetary_defense", 600, 300);
+}
+
+#[test]
+fn test_checkpoint_of_restored() {
+    let scenario = scenario::load("frigate_vs_cruiser");
+    let mut sim = Simulation::new("frigate_vs_cruiser", 0, &scenario.solution_codes());
+    for _ in 0..100 {
+        sim.step();
+    }
+    let restored = Simulation::restore(&sim.checkpoint().unwrap()).unwrap();
+    let restored_again = Simulation::restore(&restored.checkpoint().unwrap()).unwrap();
+    assert_eq!(restored_again.hash(), sim.hash());
+}
+
+#[test]
+fn test_history_seek() {
+    let scenario = scenario::load("fighter_duel");
+    let mut sim = Simulation::new("fighter_duel", 0, &scenario.solution_codes());
+    let mut history = History::new(100, history::DEFAULT_MAX_BYTES);
+    history.record(&sim);
+    let mut hashes = vec![sim.hash()];
+    for _ in 0..500 {
+        sim.step();
+        history.record(&sim);
+        hashes.push(sim.hash());
+    }
+    assert_eq!(history.earliest_tick(), Some(0));
+
+    let seeked = history.seek(450).unwrap();
+    assert_eq!(seeked.tick(), 450);
+    assert_eq!(seeked.hash(), hashes[450]);
+
+    // Resuming after a seek doesn't record duplicate checkpoints.
+    let bytes = history.bytes();
+    let mut sim = history.seek(300).unwrap();
+    for _ in 0..200 {
+        sim.step();
+        history.record(&sim);
+    }
+    assert_eq!(sim.hash(), hashes[500]);
+    assert_eq!(history.bytes(), bytes);
+}
+
+#[test]
+fn test_history_max_bytes() {
+    let scenario = scenario::load("fighter_duel");
+    let mut sim = Simulation::new("fighter_duel", 0, &scenario.solution_codes());
+
+    // Size the limit to hold a few compressed checkpoints.
+    let mut unlimited = History::new(100, usize::MAX);
+    unlimited.record(&sim);
+    let max_bytes = unlimited.bytes() * 3;
+
+    let mut history = History::new(100, max_bytes);
+    history.record(&sim);
+    for _ in 0..1000 {
+        sim.step();
+        history.record(&sim);
+        assert!(history.bytes() <= max_bytes);
+    }
+
+    let earliest_tick = history.earliest_tick().unwrap();
+    assert!(earliest_tick > 0);
+    assert!(history.seek(earliest_tick - 1).is_err());
+    assert_eq!(history.seek(1000).unwrap().hash(), sim.hash());
+}
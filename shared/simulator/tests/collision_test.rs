@@ -93,6 +93,7 @@ fn test_fighter_bullet_collision_same_team() {
             team: 0,
             color: BULLET_COLOR,
             ttl: 5.0,
+            owner: None,
         },
     );
 
@@ -124,6 +125,7 @@ fn test_fighter_bullet_collision_different_team() {
             team: 1,
             color: BULLET_COLOR,
             ttl: 5.0,
+            owner: None,
         },
     );
 
@@ -155,6 +157,7 @@ fn test_missile_bullet_collision_same_team() {
             team: 0,
             color: BULLET_COLOR,
             ttl: 5.0,
+            owner: None,
         },
     );
 
@@ -186,6 +189,7 @@ fn test_missile_bullet_collision_different_team() {
             team: 1,
             color: BULLET_COLOR,
             ttl: 5.0,
+            owner: None,
         },
     );
 
@@ -273,6 +277,7 @@ fn test_bullet_continuous_collision_detection() {
                 team: 1,
                 color: BULLET_COLOR,
                 ttl: 1.5,
+                owner: None,
             },
         );
 
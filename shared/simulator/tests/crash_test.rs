@@ -1,4 +1,5 @@
 use nalgebra::vector;
+use oort_simulator::scenario::FileScenario;
 use oort_simulator::ship::{self, fighter};
 use oort_simulator::simulation::{self, Code};
 use serial_test::serial;
@@ -71,3 +72,50 @@ fn test_infinite_loop() {
         );
     });
 }
+
+#[test]
+#[serial]
+fn test_vm_limits() {
+    let scenario = FileScenario::from_ron(
+        r#"(
+            name: "test_vm_limits",
+            teams: [(
+                code: Builtin("test"),
+                limits: (gas_per_tick: 500000, class_gas_per_tick: {Frigate: 2000000}),
+                ships: [
+                    (template: Fighter, position: [0.0, 0.0]),
+                    (template: Frigate, position: [1000.0, 0.0]),
+                ],
+            )],
+        )"#,
+    )
+    .unwrap();
+    let codes = scenario.initial_code();
+    let mut sim = simulation::Simulation::new_with_scenario(Box::new(scenario), 0, &codes);
+    let mut env = BTreeMap::new();
+    env.insert("TESTCASE".to_string(), "infinite_loop".to_string());
+    sim.update_environment(0, env);
+    sim.step();
+
+    let gas_used: Vec<u32> = sim.events().gas_used.values().copied().collect();
+    assert_eq!(gas_used.len(), 2);
+    assert!(gas_used.iter().any(|&gas| gas > 499_000 && gas <= 500_000));
+    assert!(gas_used
+        .iter()
+        .any(|&gas| gas > 1_999_000 && gas <= 2_000_000));
+}
+
+#[test]
+#[serial]
+fn test_invalid_memory_limit() {
+    let scenario = FileScenario::from_ron(
+        r#"(
+            name: "test_invalid_memory_limit",
+            teams: [(code: Builtin("test"), limits: (memory_size: 1000))],
+        )"#,
+    )
+    .unwrap();
+    let codes = scenario.initial_code();
+    let sim = simulation::Simulation::new_with_scenario(Box::new(scenario), 0, &codes);
+    assert!(sim.events().errors[0].msg.contains("Memory limit"));
+}
@@ -0,0 +1,114 @@
+use nalgebra::vector;
+use oort_simulator::event::{Attacker, GameEventKind};
+use oort_simulator::ship::{self, fighter, target, ShipClass};
+use oort_simulator::simulation::{self, Code};
+use test_log::test;
+
+#[test]
+fn test_gun_kill() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None, Code::None]);
+    let mut ship_data = fighter(0);
+    ship_data.guns[0].inaccuracy = 0.0;
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        ship_data,
+    );
+    let ship1 = ship::create(
+        &mut sim,
+        vector![100.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        target(1),
+    );
+
+    sim.ship_mut(ship0).fire_gun(0);
+    assert_eq!(
+        sim.events().game_events[0].kind,
+        GameEventKind::GunFired {
+            ship: ship0.into(),
+            team: 0,
+            gun: 0,
+            bullets: 1,
+        }
+    );
+
+    let mut events = vec![];
+    for _ in 0..100 {
+        sim.step();
+        events.extend(sim.events().game_events.iter().cloned());
+    }
+    assert!(!sim.ships.contains(ship1));
+
+    let attacker = Attacker {
+        ship: Some(ship0.into()),
+        team: 0,
+    };
+    assert!(events.iter().any(|e| matches!(
+        e.kind,
+        GameEventKind::Hit { ship, team: 1, attacker: a, damage }
+            if ship == u64::from(ship1) && a == attacker && damage > 0.0
+    )));
+    let destroyed: Vec<_> = events
+        .iter()
+        .filter(|e| matches!(e.kind, GameEventKind::Destroyed { .. }))
+        .collect();
+    assert_eq!(destroyed.len(), 1);
+    assert_eq!(
+        destroyed[0].kind,
+        GameEventKind::Destroyed {
+            ship: ship1.into(),
+            team: 1,
+            class: ShipClass::Target,
+            attacker: Some(attacker),
+        }
+    );
+    assert!(destroyed[0].tick > 0 && destroyed[0].tick <= sim.tick());
+}
+
+#[test]
+fn test_missile_launch() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    sim.ship_mut(ship0).launch_missile(0);
+    let missile = match sim.events().game_events[0].kind {
+        GameEventKind::MissileLaunched {
+            ship,
+            team: 0,
+            missile,
+            class: ShipClass::Missile,
+        } if ship == u64::from(ship0) => missile,
+        ref kind => panic!("unexpected event {kind:?}"),
+    };
+
+    let missile = *sim
+        .ships
+        .iter()
+        .find(|&&h| u64::from(h) == missile)
+        .unwrap();
+    sim.ship_mut(missile).explode();
+    assert!(sim.events().game_events.iter().any(|e| e.kind
+        == GameEventKind::Exploded {
+            ship: missile.into(),
+            team: 0
+        }));
+
+    sim.step();
+    assert!(sim.events().game_events.iter().any(|e| matches!(
+        e.kind,
+        GameEventKind::Destroyed {
+            class: ShipClass::Missile,
+            attacker: None,
+            ..
+        }
+    )));
+}
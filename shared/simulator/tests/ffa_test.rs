@@ -0,0 +1,67 @@
+use nalgebra::vector;
+use oort_simulator::scenario::{self, Status};
+use oort_simulator::ship::{self, NEUTRAL_TEAM};
+use oort_simulator::simulation::Simulation;
+use test_log::test;
+
+#[test]
+fn test_place_ffa_teams() {
+    let mut rng = oort_simulator::rng::new_rng(0);
+    let placements = scenario::place_ffa_teams(&mut rng, 20000.0, 5);
+    assert_eq!(placements.len(), 5);
+    for (i, a) in placements.iter().enumerate() {
+        assert!(a.position.norm() < 10000.0);
+        // Facing the center.
+        let heading = vector![a.heading.cos(), a.heading.sin()];
+        assert!(heading.dot(&-a.position.normalize()) > 0.99);
+        for b in placements.iter().skip(i + 1) {
+            assert!((a.position - b.position).norm() > 1000.0);
+        }
+    }
+}
+
+#[test]
+fn test_fighter_ffa() {
+    let scenario = scenario::load("fighter_ffa");
+    let codes = scenario.solution_codes();
+    assert_eq!(codes.len(), 4);
+    let mut sim = Simulation::new("fighter_ffa", 0, &codes);
+    let teams: Vec<i32> = sim
+        .ships
+        .iter()
+        .map(|&handle| sim.ship(handle).data().team)
+        .collect();
+    assert_eq!(teams, vec![0, 1, 2, 3]);
+
+    while sim.status() == Status::Running {
+        sim.step();
+    }
+    match sim.status() {
+        Status::Victory { team } => assert!((0..4).contains(&team)),
+        Status::Draw => {}
+        status => panic!("unexpected status {status:?}"),
+    }
+}
+
+#[test]
+fn test_neutral_ships_ignored_for_victory() {
+    let mut sim = Simulation::new("test", 0, &[]);
+    ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        ship::fighter(2),
+    );
+    ship::create(
+        &mut sim,
+        vector![1000.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        ship::fighter(NEUTRAL_TEAM),
+    );
+    assert_eq!(
+        scenario::check_tournament_victory(&sim),
+        Status::Victory { team: 2 }
+    );
+}
@@ -0,0 +1,75 @@
+use nalgebra::vector;
+use oort_simulator::scenario::Status;
+use oort_simulator::ship::{self, fighter, ShipClass};
+use oort_simulator::simulation::{self, Code};
+use test_log::test;
+
+#[test]
+fn test_snapshot_for_team() {
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::None, Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let ship1 = ship::create(
+        &mut sim,
+        vector![1000.0, 0.0],
+        vector![0.0, 0.0],
+        std::f64::consts::PI,
+        fighter(1),
+    );
+
+    sim.step();
+    sim.ship_mut(ship1).fire_gun(0);
+    sim.step();
+    sim.ship_mut(ship0).fire_gun(0);
+
+    let full = sim.snapshot(0);
+    assert_eq!(full.ships.len(), 2);
+    assert_eq!(full.bullets.len(), 2);
+    assert!(full.contacts.is_empty());
+
+    let snapshot = sim.snapshot_for_team(0, 0);
+    assert_eq!(snapshot.ships.len(), 1);
+    assert_eq!(snapshot.ships[0].id, u64::from(ship0));
+    assert_eq!(snapshot.bullets.len(), 1);
+    assert!(snapshot.bullets[0].position.x < 500.0);
+    assert_eq!(snapshot.contacts.len(), 1);
+    let contact = &snapshot.contacts[0];
+    assert_eq!(contact.class, ShipClass::Fighter);
+    assert!((contact.position - sim.ship(ship1).position().vector).norm() < 100.0);
+    assert!(snapshot.events.iter().all(|event| event.kind.team() == 0));
+
+    let snapshot = sim.snapshot_for_team(0, 1);
+    assert_eq!(snapshot.ships.len(), 1);
+    assert_eq!(snapshot.ships[0].id, u64::from(ship1));
+    assert_eq!(snapshot.bullets.len(), 1);
+    assert!(snapshot.bullets[0].position.x > 500.0);
+}
+
+#[test]
+fn test_snapshot_for_team_hides_errors_and_stats() {
+    let missing = Code::Process(vec!["/nonexistent/controller".to_string()]);
+    let mut sim = simulation::Simulation::new("fighter_duel", 0, &[Code::None, missing]);
+    assert!(!sim.snapshot(0).errors.is_empty());
+    assert!(sim.snapshot_for_team(0, 0).errors.is_empty());
+
+    let enemy = *sim
+        .ships
+        .iter()
+        .find(|&&handle| sim.ship(handle).data().team == 1)
+        .unwrap();
+    sim.ship_mut(enemy).explode();
+    sim.step();
+    assert_eq!(sim.status(), Status::Victory { team: 0 });
+
+    let full = sim.snapshot(0).stats.unwrap();
+    assert_eq!(full.teams.len(), 2);
+    let stats = sim.snapshot_for_team(0, 0).stats.unwrap();
+    assert!(stats.ships.values().all(|ship| ship.team == 0));
+    assert!(!stats.ships.is_empty());
+    assert_eq!(stats.teams.keys().collect::<Vec<_>>(), vec![&0]);
+}
@@ -0,0 +1,111 @@
+use nalgebra::vector;
+use oort_api::prelude::*;
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation::{self, Code};
+use oort_simulator::vm::native::NativeCode;
+use std::collections::BTreeMap;
+use test_log::test;
+
+struct Ship {
+    ticks: u32,
+}
+
+impl Ship {
+    fn new() -> Ship {
+        Ship { ticks: 0 }
+    }
+
+    fn tick(&mut self) {
+        accelerate(vec2(100.0, 0.0));
+        debug!("id={} ticks={}", id(), self.ticks);
+        if let Some(testcase) = oort_api::sys::getenv("TESTCASE") {
+            debug!("testcase={}", testcase);
+        }
+        self.ticks += 1;
+    }
+}
+
+struct PanicShip;
+
+impl PanicShip {
+    fn new() -> PanicShip {
+        PanicShip
+    }
+
+    fn tick(&mut self) {
+        panic!("Panic!");
+    }
+}
+
+fn native_code() -> Code {
+    Code::Native(NativeCode::new(Ship::new, Ship::tick))
+}
+
+#[test]
+fn test_native_ship() {
+    let mut sim = simulation::Simulation::new("test", 0, &[native_code(), Code::None]);
+    let mut env = BTreeMap::new();
+    env.insert("TESTCASE".to_string(), "native".to_string());
+    sim.update_environment(0, env);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let ship1 = ship::create(
+        &mut sim,
+        vector![0.0, 1000.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    sim.step();
+    sim.step();
+
+    assert_eq!(
+        sim.events().debug_text.get(&ship0.into()).unwrap(),
+        "id=1 ticks=1\ntestcase=native\n"
+    );
+    assert_eq!(
+        sim.events().debug_text.get(&ship1.into()).unwrap(),
+        "id=2 ticks=1\ntestcase=native\n"
+    );
+    assert!(sim.ship(ship0).velocity().x > 0.0);
+}
+
+#[test]
+fn test_native_panic() {
+    let code = Code::Native(NativeCode::new(PanicShip::new, PanicShip::tick));
+    let mut sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    sim.step();
+
+    assert_eq!(
+        sim.events().debug_text.get(&handle.into()).unwrap(),
+        "Crashed: ship panicked at 'Panic!'"
+    );
+}
+
+#[test]
+fn test_native_checkpoint() {
+    let mut sim = simulation::Simulation::new("test", 0, &[native_code(), Code::None]);
+    ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    assert!(sim.checkpoint().is_err());
+}
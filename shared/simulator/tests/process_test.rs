@@ -0,0 +1,137 @@
+#![cfg(unix)]
+use nalgebra::vector;
+use oort_api::SystemState;
+use oort_simulator::ship::{self, fighter};
+use oort_simulator::simulation::{self, Code};
+use test_log::test;
+
+fn process_code(command: &[&str]) -> Code {
+    Code::Process(command.iter().map(|x| x.to_string()).collect())
+}
+
+#[test]
+fn test_process_controller() {
+    // Echoes each request back with debug text added.
+    let code = process_code(&["sed", "-u", r#"s/^{/{"debug":"hello",/"#]);
+    let mut sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    sim.step();
+    sim.step();
+
+    assert_eq!(
+        sim.events().debug_text.get(&handle.into()).unwrap(),
+        "hello"
+    );
+    assert!(sim.ship(handle).data().crash_message.is_none());
+}
+
+#[test]
+fn test_process_controller_contacts() {
+    // Echoes each request back, adding debug text if it has contact buffers.
+    let code = process_code(&[
+        "sed",
+        "-u",
+        r#"/"radar_contacts":\[.*\],"esm_contacts":\[/ s/^{/{"debug":"contacts",/"#,
+    ]);
+    let mut sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    sim.step();
+    sim.step();
+
+    assert_eq!(
+        sim.events().debug_text.get(&handle.into()).unwrap(),
+        "contacts"
+    );
+}
+
+#[test]
+fn test_process_controller_state() {
+    // Accelerates along the x axis in reply to every request.
+    let mut state = vec![0.0; SystemState::Size as usize];
+    state[SystemState::AccelerateX as usize] = 60.0;
+    let response = serde_json::json!({ "state": state }).to_string();
+    let script = format!("while read line; do echo '{response}'; done");
+    let code = process_code(&["sh", "-c", &script]);
+    let mut sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    for _ in 0..10 {
+        sim.step();
+    }
+
+    assert!(sim.ship(handle).data().crash_message.is_none());
+    assert!(sim.ship(handle).velocity().x > 1.0);
+}
+
+#[test]
+fn test_process_controller_exited() {
+    let code = process_code(&["true"]);
+    let mut sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    sim.step();
+
+    let msg = sim.ship(handle).data().crash_message.clone().unwrap();
+    assert!(msg.starts_with("Process controller"), "msg: {msg:?}");
+}
+
+#[test]
+fn test_process_controller_timeout() {
+    let code = process_code(&["sleep", "60"]);
+    let mut sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    let handle = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+
+    let start = std::time::Instant::now();
+    sim.step();
+
+    let msg = sim.ship(handle).data().crash_message.clone().unwrap();
+    assert!(
+        msg.starts_with("Process controller timed out"),
+        "msg: {msg:?}"
+    );
+    assert!(start.elapsed() < std::time::Duration::from_secs(30));
+}
+
+#[test]
+fn test_process_controller_missing() {
+    let code = process_code(&["/nonexistent/controller"]);
+    let sim = simulation::Simulation::new("test", 0, &[code, Code::None]);
+    assert_eq!(sim.events().errors.len(), 1);
+}
+
+#[test]
+fn test_process_code_not_deserialized() {
+    assert!(serde_json::from_str::<Code>(r#"{"Process":["true"]}"#).is_err());
+}
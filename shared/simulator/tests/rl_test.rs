@@ -0,0 +1,79 @@
+use oort_simulator::rl::{self, Action, Env, VecEnv};
+use oort_simulator::scenario::Status;
+use test_log::test;
+
+fn fire(observations: &[rl::Observation]) -> Vec<Action> {
+    observations
+        .iter()
+        .map(|obs| Action {
+            ship: obs.ship,
+            fire: [true, false, false, false],
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[test]
+fn test_env() {
+    let mut env = Env::new(0, rl::DamageDealt::default());
+    let mut observations = env.reset("tutorial_guns", 0).unwrap();
+    assert_eq!(observations.len(), 1);
+    assert_eq!(observations[0].values.len(), rl::OBSERVATION_SIZE);
+
+    let mut total_reward = 0.0;
+    loop {
+        let step = env.step(&fire(&observations));
+        total_reward += step.reward;
+        observations = step.observations;
+        if step.done {
+            break;
+        }
+    }
+
+    assert_eq!(
+        env.simulation().unwrap().status(),
+        Status::Victory { team: 0 }
+    );
+    assert!(total_reward > 0.0);
+}
+
+#[test]
+fn test_vec_env() {
+    let seeds: Vec<u32> = (0..4).collect();
+    let mut vec_env = VecEnv::new(seeds.len(), || Env::new(0, rl::Outcome));
+    let mut observations = vec_env.reset("tutorial_guns", &seeds).unwrap();
+    let mut rewards = vec![0.0; seeds.len()];
+    let mut done = vec![false; seeds.len()];
+
+    while !done.iter().all(|&x| x) {
+        let actions: Vec<Vec<Action>> = observations
+            .iter()
+            .zip(done.iter())
+            .map(|(obs, &done)| if done { vec![] } else { fire(obs) })
+            .collect();
+        let steps = vec_env.step(&actions);
+        for (i, step) in steps.into_iter().enumerate() {
+            if !done[i] {
+                rewards[i] += step.reward;
+                done[i] = step.done;
+            }
+            observations[i] = step.observations;
+        }
+    }
+
+    assert_eq!(rewards, vec![1.0; seeds.len()]);
+}
+
+#[test]
+fn test_vec_env_more_envs_than_threads() {
+    let n = std::thread::available_parallelism().unwrap().get() * 2 + 1;
+    let seeds: Vec<u32> = (0..n as u32).collect();
+    let mut vec_env = VecEnv::new(n, || Env::new(0, rl::DamageDealt::default()));
+    assert_eq!(vec_env.len(), n);
+    let observations = vec_env.reset("tutorial_guns", &seeds).unwrap();
+    assert_eq!(observations.len(), n);
+    let actions: Vec<Vec<Action>> = observations.iter().map(|obs| fire(obs)).collect();
+    let steps = vec_env.step(&actions);
+    assert_eq!(steps.len(), n);
+    assert!(steps.iter().all(|step| step.observations.len() == 1));
+}
@@ -21,6 +21,9 @@ struct Arguments {
     #[clap(long, default_value = "/tmp/oort-wasm-cache")]
     wasm_cache: Option<PathBuf>,
 
+    #[clap(long)]
+    module_cache: Option<PathBuf>,
+
     #[clap(long)]
     local_compiler: bool,
 }
@@ -36,6 +39,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         panic!("Expected at least two shortcodes");
     }
 
+    let module_cache = args
+        .module_cache
+        .or_else(oort_tools::default_module_cache_dir)
+        .and_then(oort_tools::ModuleCache::new);
+
     log::info!("Compiling AIs");
     let ais = if args.local_compiler {
         let mut compiler = oort_compiler::Compiler::new();
@@ -47,7 +55,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 AI {
                     name: x.clone(),
                     source_code: src,
-                    compiled_code: Code::Wasm(wasm),
+                    compiled_code: oort_tools::precompile(&wasm, module_cache.as_ref()).unwrap(),
                 }
             })
             .collect::<Vec<_>>()
@@ -58,6 +66,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             &args.shortcodes,
             args.dev,
             args.wasm_cache.as_deref(),
+            module_cache.as_ref(),
         )
         .await?
     };
@@ -37,6 +37,7 @@ async fn main() -> anyhow::Result<()> {
         &args.shortcodes,
         args.dev,
         args.wasm_cache.as_deref(),
+        None,
     )
     .await?;
     let codes = vec![ais[0].compiled_code.clone(), ais[1].compiled_code.clone()];
@@ -0,0 +1,175 @@
+use clap::{Parser, Subcommand};
+use oort_simulator::simulation::Code;
+use oort_tools::replay::{self, Replay, Verification};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap()]
+struct Arguments {
+    #[clap(subcommand)]
+    cmd: SubCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum SubCommand {
+    /// Run a match and save it as a replay.
+    Record {
+        scenario: String,
+        shortcodes: Vec<String>,
+
+        #[clap(short, long, default_value_t = 0)]
+        seed: u32,
+
+        #[clap(short, long)]
+        output: PathBuf,
+
+        /// Ticks between recorded hashes.
+        #[clap(short, long, default_value_t = replay::DEFAULT_CHECKPOINT_INTERVAL)]
+        interval: u32,
+
+        /// Only store wasm hashes instead of the wasm itself.
+        #[clap(long)]
+        hashes_only: bool,
+
+        #[clap(short, long)]
+        dev: bool,
+
+        #[clap(long, default_value = "/tmp/oort-wasm-cache")]
+        wasm_cache: Option<PathBuf>,
+    },
+    /// Re-run a replay and check every recorded hash.
+    Verify {
+        replay: PathBuf,
+
+        /// Wasm files for replays recorded with --hashes-only.
+        #[clap(short, long)]
+        wasm: Vec<PathBuf>,
+    },
+    /// Print the contents of a replay.
+    Info { replay: PathBuf },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("replay=info"))
+        .init();
+
+    let args = Arguments::parse();
+    match args.cmd {
+        SubCommand::Record {
+            scenario,
+            shortcodes,
+            seed,
+            output,
+            interval,
+            hashes_only,
+            dev,
+            wasm_cache,
+        } => {
+            let http = reqwest::Client::new();
+            let mut teams = vec![];
+            for shortcode in shortcodes.iter() {
+                let (_, wasm) = oort_tools::fetch_and_compile_wasm(
+                    &http,
+                    shortcode,
+                    dev,
+                    wasm_cache.as_deref(),
+                )
+                .await?;
+                let name = shortcode.rsplit('/').next().unwrap().to_string();
+                teams.push((name, Code::Wasm(wasm)));
+            }
+
+            log::info!("Recording {} seed {}", scenario, seed);
+            let replay = replay::record(&scenario, seed, &teams, interval, !hashes_only)?;
+            replay.save(&output)?;
+            log::info!(
+                "Saved {} checkpoints to {:?}, final status {:?}",
+                replay.checkpoints.len(),
+                output,
+                replay.final_status
+            );
+        }
+        SubCommand::Verify { replay, wasm } => {
+            let replay = Replay::load(&replay)?;
+            let current_version = oort_version::version();
+            if replay.simulator_version != current_version {
+                log::info!(
+                    "Replay recorded with simulator {}, verifying with {}",
+                    replay.simulator_version,
+                    current_version
+                );
+            }
+
+            let available = wasm
+                .iter()
+                .map(|path| Ok(Code::Wasm(std::fs::read(path)?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let codes = replay.resolve_codes(&available)?;
+
+            match replay.verify(&codes) {
+                Verification::Match => {
+                    println!(
+                        "OK: {} checkpoints matched, final status {:?}",
+                        replay.checkpoints.len(),
+                        replay.final_status
+                    );
+                }
+                Verification::Diverged {
+                    last_good_tick,
+                    tick,
+                    expected,
+                    actual,
+                } => {
+                    match last_good_tick {
+                        Some(last_good_tick) => println!(
+                            "Diverged between tick {} and tick {}",
+                            last_good_tick + 1,
+                            tick
+                        ),
+                        None => println!("Diverged at or before tick {}", tick),
+                    }
+                    println!("  expected hash: {expected}");
+                    println!("  actual hash:   {actual}");
+                    std::process::exit(1);
+                }
+                Verification::StatusMismatch { expected, actual } => {
+                    println!("Final status differs");
+                    println!("  expected: {expected:?}");
+                    println!("  actual:   {actual:?}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        SubCommand::Info { replay } => {
+            let replay = Replay::load(&replay)?;
+            println!("Scenario: {}", replay.scenario_name);
+            println!("Seed: {}", replay.seed);
+            println!("Simulator version: {}", replay.simulator_version);
+            for (i, team) in replay.teams.iter().enumerate() {
+                println!(
+                    "Team {}: {} wasm {} ({})",
+                    i,
+                    team.name,
+                    team.wasm_hash,
+                    if team.code.is_some() {
+                        "embedded"
+                    } else {
+                        "hash only"
+                    }
+                );
+            }
+            println!(
+                "Checkpoints: {} every {} ticks",
+                replay.checkpoints.len(),
+                replay.checkpoint_interval
+            );
+            if let Some(last) = replay.checkpoints.last() {
+                println!("Final tick: {}", last.tick);
+            }
+            println!("Final status: {:?}", replay.final_status);
+        }
+    }
+
+    Ok(())
+}
@@ -5,11 +5,13 @@ use firestore::*;
 use gcloud_sdk::google::firestore::v1::Document;
 use indicatif::{MultiProgress, ProgressBar};
 use oort_proto::LeaderboardSubmission;
+use oort_simulator::simulation::Code;
 use oort_simulator::{scenario, simulation};
-use oort_tools::ParallelCompiler;
+use oort_tools::{ModuleCache, ParallelCompiler};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap()]
@@ -25,6 +27,9 @@ struct Arguments {
 
     #[clap(long, value_parser, default_value_t = 10)]
     limit: usize,
+
+    #[clap(long)]
+    module_cache: Option<PathBuf>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -47,6 +52,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let db = FirestoreDb::new(&args.project_id).await?;
     let compiler = ParallelCompiler::new(4);
+    let module_cache = args
+        .module_cache
+        .clone()
+        .or_else(oort_tools::default_module_cache_dir)
+        .and_then(ModuleCache::new);
 
     let mut scenario_names = vec![];
     if let Some(scenario) = args.scenario.as_ref() {
@@ -106,8 +116,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     docid
                 );
 
-                let wasm = match compiler.compile(&msg.code) {
-                    Ok(wasm) => wasm,
+                let code = match compiler
+                    .compile(&msg.code)
+                    .and_then(|wasm| oort_tools::precompile(&wasm, module_cache.as_ref()))
+                {
+                    Ok(code) => code,
                     Err(e) => {
                         log::warn!(
                             "{}/{}: Compilation failed for docid={}: {}",
@@ -125,7 +138,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                     msg.scenario_name,
                     msg.username
                 );
-                let status = run_simulations(&msg.scenario_name, wasm, &progress);
+                let status = run_simulations(&msg.scenario_name, &code, &progress);
                 match status {
                     Some(new_time) => {
                         if (msg.time - new_time).abs() >= 0.001 {
@@ -199,11 +212,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     Ok(())
 }
 
-fn run_simulations(scenario_name: &str, wasm: Vec<u8>, progress: &ProgressBar) -> Option<f64> {
+fn run_simulations(scenario_name: &str, code: &Code, progress: &ProgressBar) -> Option<f64> {
     let results: Vec<Option<f64>> = (0..10u32)
         .into_par_iter()
         .map(|seed| {
-            let ret = run_simulation(scenario_name, seed, wasm.clone());
+            let ret = run_simulation(scenario_name, seed, code.clone());
             progress.inc(1);
             ret
         })
@@ -215,10 +228,10 @@ fn run_simulations(scenario_name: &str, wasm: Vec<u8>, progress: &ProgressBar) -
     Some(results.iter().map(|x| x.unwrap()).sum::<f64>() / results.len() as f64)
 }
 
-fn run_simulation(scenario_name: &str, seed: u32, wasm: Vec<u8>) -> Option<f64> {
+fn run_simulation(scenario_name: &str, seed: u32, code: Code) -> Option<f64> {
     let scenario = scenario::load(scenario_name);
     let mut codes = scenario.initial_code();
-    codes[0] = simulation::Code::Wasm(wasm);
+    codes[0] = code;
     let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
     while sim.status() == scenario::Status::Running && sim.tick() < scenario::MAX_TICKS {
         sim.step();
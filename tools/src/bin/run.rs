@@ -0,0 +1,181 @@
+use clap::Parser;
+use oort_simulator::scenario::{self, FileScenario, Scenario, Status};
+use oort_simulator::simulation::{Code, Simulation};
+use oort_simulator::vm;
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+#[derive(Parser, Debug)]
+#[clap()]
+struct Arguments {
+    /// Name of a built-in scenario or path to a .ron/.toml scenario file
+    scenario: String,
+
+    /// AI for each team, in team order. Either a source file (Rust or C), a
+    /// .wasm file, or "builtin:<name>". Teams without an AI use the
+    /// scenario's initial code.
+    ais: Vec<String>,
+
+    /// Base seed
+    #[clap(short, long, default_value = "0")]
+    seed: u32,
+
+    /// Number of seeds to run, starting at the base seed
+    #[clap(short, long, default_value = "1")]
+    rounds: u32,
+
+    #[clap(long, default_value_t = scenario::MAX_TICKS)]
+    max_ticks: u32,
+
+    #[clap(long)]
+    pretty: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct TeamResult {
+    team: i32,
+    surviving_ships: usize,
+    surviving_classes: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize, Debug)]
+struct Crash {
+    tick: u32,
+    team: i32,
+    class: String,
+    msg: String,
+}
+
+#[derive(Serialize, Debug)]
+struct MatchResult {
+    scenario: String,
+    seed: u32,
+    status: Status,
+    score_time: f64,
+    ticks: u32,
+    teams: Vec<TeamResult>,
+    errors: Vec<vm::Error>,
+    crashes: Vec<Crash>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("run=warn"))
+        .target(env_logger::Target::Stderr)
+        .init();
+
+    let args = Arguments::parse();
+    let file_scenario = if args.scenario.ends_with(".ron") || args.scenario.ends_with(".toml") {
+        Some(FileScenario::load(Path::new(&args.scenario))?)
+    } else {
+        None
+    };
+    let load_scenario = || -> Box<dyn Scenario> {
+        match file_scenario.as_ref() {
+            Some(scenario) => Box::new(scenario.clone()),
+            None => scenario::load(&args.scenario),
+        }
+    };
+    if file_scenario.is_none() && scenario::load_safe(&args.scenario).is_none() {
+        anyhow::bail!("Unknown scenario {:?}", args.scenario);
+    }
+    let scenario = load_scenario();
+
+    let mut codes = scenario.initial_code();
+    let mut compiler = None;
+    for (team, ai) in args.ais.iter().enumerate() {
+        let code = load_code(ai, &mut compiler)?;
+        if team < codes.len() {
+            codes[team] = code;
+        } else {
+            codes.push(code);
+        }
+    }
+
+    let results: Vec<MatchResult> = (args.seed..(args.seed + args.rounds))
+        .into_par_iter()
+        .map(|seed| run_simulation(load_scenario(), seed, &codes, args.max_ticks))
+        .collect();
+
+    let output = if args.pretty {
+        serde_json::to_string_pretty(&results)?
+    } else {
+        serde_json::to_string(&results)?
+    };
+    println!("{output}");
+
+    Ok(())
+}
+
+fn load_code(ai: &str, compiler: &mut Option<oort_compiler::Compiler>) -> anyhow::Result<Code> {
+    if let Some(name) = ai.strip_prefix("builtin:") {
+        return Ok(Code::Builtin(name.to_string()));
+    }
+    if ai.ends_with(".wasm") {
+        return Ok(Code::Wasm(std::fs::read(ai)?));
+    }
+    let src = std::fs::read_to_string(ai)?;
+    log::info!("Compiling {:?}", ai);
+    let wasm = compiler
+        .get_or_insert_with(oort_compiler::Compiler::new)
+        .compile(&src)
+        .map_err(|e| anyhow::anyhow!("Failed to compile {:?}: {}", ai, e))?;
+    Ok(Code::Wasm(wasm))
+}
+
+fn run_simulation(
+    scenario: Box<dyn Scenario>,
+    seed: u32,
+    codes: &[Code],
+    max_ticks: u32,
+) -> MatchResult {
+    let scenario_name = scenario.name();
+    let mut sim = Simulation::new_with_scenario(scenario, seed, codes);
+    let mut errors = sim.events().errors.clone();
+    let mut crashes = vec![];
+    let mut crashed = HashSet::new();
+    while sim.status() == Status::Running && sim.tick() < max_ticks {
+        sim.step();
+        errors.extend(sim.events().errors.iter().cloned());
+        for &handle in sim.ships.iter() {
+            let ship = sim.ship(handle);
+            if let Some(msg) = ship.data().crash_message.as_ref() {
+                if crashed.insert(handle) {
+                    crashes.push(Crash {
+                        tick: sim.tick(),
+                        team: ship.data().team,
+                        class: ship.data().class.name().to_string(),
+                        msg: msg.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut teams: BTreeMap<i32, TeamResult> = BTreeMap::new();
+    for &handle in sim.ships.iter() {
+        let data = sim.ship(handle).data();
+        let team = teams.entry(data.team).or_insert_with(|| TeamResult {
+            team: data.team,
+            surviving_ships: 0,
+            surviving_classes: BTreeMap::new(),
+        });
+        team.surviving_ships += 1;
+        *team
+            .surviving_classes
+            .entry(data.class.name().to_string())
+            .or_default() += 1;
+    }
+
+    MatchResult {
+        scenario: scenario_name,
+        seed,
+        status: sim.status(),
+        score_time: sim.score_time(),
+        ticks: sim.tick(),
+        teams: teams.into_values().collect(),
+        errors,
+        crashes,
+    }
+}
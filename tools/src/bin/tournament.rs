@@ -10,7 +10,7 @@ use oort_tools::AI;
 use rand::Rng;
 use rayon::prelude::*;
 use skillratings::{
-    glicko2::{glicko2, Glicko2Config, Glicko2Rating},
+    glicko2::{glicko2_rating_period, Glicko2Config, Glicko2Rating},
     Outcomes,
 };
 use std::default::Default;
@@ -37,6 +37,9 @@ enum SubCommand {
 
         #[clap(short, long)]
         dry_run: bool,
+
+        #[clap(long)]
+        module_cache: Option<PathBuf>,
     },
     RunUnofficial {
         scenario: String,
@@ -50,6 +53,9 @@ enum SubCommand {
 
         #[clap(long, default_value = "/tmp/oort-wasm-cache")]
         wasm_cache: Option<PathBuf>,
+
+        #[clap(long)]
+        module_cache: Option<PathBuf>,
     },
     Fetch {
         scenario: String,
@@ -80,14 +86,36 @@ async fn main() -> anyhow::Result<()> {
             usernames,
             rounds,
             dry_run,
-        } => cmd_run(&args.project_id, &scenario, &usernames, rounds, dry_run).await,
+            module_cache,
+        } => {
+            cmd_run(
+                &args.project_id,
+                &scenario,
+                &usernames,
+                rounds,
+                dry_run,
+                module_cache,
+            )
+            .await
+        }
         SubCommand::RunUnofficial {
             scenario,
             shortcodes,
             rounds,
             dev,
             wasm_cache,
-        } => cmd_run_unofficial(&scenario, &shortcodes, rounds, dev, wasm_cache).await,
+            module_cache,
+        } => {
+            cmd_run_unofficial(
+                &scenario,
+                &shortcodes,
+                rounds,
+                dev,
+                wasm_cache,
+                module_cache,
+            )
+            .await
+        }
         SubCommand::Fetch { scenario, out_dir } => {
             cmd_fetch(&args.project_id, &scenario, &out_dir).await
         }
@@ -105,9 +133,13 @@ async fn cmd_run(
     usernames: &[String],
     rounds: i32,
     dry_run: bool,
+    module_cache: Option<PathBuf>,
 ) -> anyhow::Result<()> {
     let db = FirestoreDb::new(project_id).await?;
     scenario::load_safe(scenario_name).expect("Unknown scenario");
+    let module_cache = module_cache
+        .or_else(oort_tools::default_module_cache_dir)
+        .and_then(oort_tools::ModuleCache::new);
 
     let mut compiler = oort_compiler::Compiler::new();
     let entrants = get_entrants(&db, scenario_name, usernames).await?;
@@ -116,7 +148,7 @@ async fn cmd_run(
         .map(|entrant| {
             log::info!("Compiling {:?}", entrant.username);
             let compiled_code = compiler.compile(&entrant.source_code)?;
-            let compiled_code = oort_simulator::vm::precompile(&compiled_code).unwrap();
+            let compiled_code = oort_tools::precompile(&compiled_code, module_cache.as_ref())?;
             Ok(AI {
                 name: entrant.username.clone(),
                 source_code: entrant.source_code.clone(),
@@ -144,12 +176,22 @@ async fn cmd_run_unofficial(
     rounds: i32,
     dev: bool,
     wasm_cache: Option<PathBuf>,
+    module_cache: Option<PathBuf>,
 ) -> anyhow::Result<()> {
     scenario::load_safe(scenario_name).expect("Unknown scenario");
 
     let http = reqwest::Client::new();
-    let ais = oort_tools::fetch_and_compile_multiple(&http, shortcodes, dev, wasm_cache.as_deref())
-        .await?;
+    let module_cache = module_cache
+        .or_else(oort_tools::default_module_cache_dir)
+        .and_then(oort_tools::ModuleCache::new);
+    let ais = oort_tools::fetch_and_compile_multiple(
+        &http,
+        shortcodes,
+        dev,
+        wasm_cache.as_deref(),
+        module_cache.as_ref(),
+    )
+    .await?;
 
     log::info!("Running tournament");
     let results = run_tournament(scenario_name, &ais, rounds);
@@ -160,56 +202,86 @@ async fn cmd_run_unofficial(
 }
 
 fn run_tournament(scenario_name: &str, ais: &[AI], rounds: i32) -> TournamentResults {
+    let num_teams = scenario::load(scenario_name).initial_code().len();
+    assert!(
+        ais.len() >= num_teams,
+        "{scenario_name} needs at least {num_teams} entrants"
+    );
     let seeds: Vec<u32> = (0..rounds).map(|_| rand::thread_rng().gen()).collect();
-    let mut pairings: HashMap<(String, String), f64> = HashMap::new();
+    let mut wins: HashMap<(String, String), f64> = HashMap::new();
+    let mut games: HashMap<(String, String), f64> = HashMap::new();
     let config = Glicko2Config::new();
     let mut ratings: Vec<Glicko2Rating> = Vec::new();
     ratings.resize_with(ais.len(), Default::default);
-    let pairs: Vec<(i32, Vec<_>)> = (0..rounds)
-        .flat_map(|round| (0..(ais.len())).permutations(2).map(move |x| (round, x)))
+    // Duels are played from both sides. Free-for-all matches rely on the
+    // scenario shuffling team placements instead.
+    let groups: Vec<Vec<usize>> = if num_teams == 2 {
+        (0..ais.len()).permutations(2).collect()
+    } else {
+        (0..ais.len()).combinations(num_teams).collect()
+    };
+    let matches: Vec<(i32, Vec<usize>)> = (0..rounds)
+        .flat_map(|round| groups.iter().map(move |x| (round, x.clone())))
         .collect();
-    let progress = indicatif::ProgressBar::new(pairs.len() as u64);
+    let progress = indicatif::ProgressBar::new(matches.len() as u64);
     progress.set_style(
         indicatif::ProgressStyle::default_bar()
             .template("{wide_bar} {pos}/{len} Elapsed: {elapsed_precise} ETA: {eta_precise}")
             .unwrap(),
     );
-    let outcomes: Vec<(i32, Vec<_>, Outcomes)> = pairs
+    let outcomes: Vec<(i32, Vec<usize>, Option<usize>)> = matches
         .par_iter()
         .map(|(round, indices)| {
             let seed = seeds[*round as usize];
-            let ai0: &AI = &ais[indices[0]];
-            let ai1: &AI = &ais[indices[1]];
-            let r = run_simulation(scenario_name, seed, &[ai0, ai1]);
+            let match_ais: Vec<&AI> = indices.iter().map(|&i| &ais[i]).collect();
+            let winner = run_simulation(scenario_name, seed, &match_ais);
             progress.inc(1);
-            (*round, indices.clone(), r)
+            (*round, indices.clone(), winner.map(|team| indices[team]))
         })
         .collect();
     progress.finish_and_clear();
 
-    for (round, indices, outcome) in outcomes {
-        let i0 = indices[0];
-        let i1 = indices[1];
+    for (round, indices, winner) in outcomes {
         log::debug!(
-            "{} vs {} seed {}: {:?}",
-            ais[i0].name,
-            ais[i1].name,
+            "{} seed {}: winner {:?}",
+            indices.iter().map(|&i| &ais[i].name).join(" vs "),
             round,
-            outcome
+            winner.map(|i| &ais[i].name)
         );
-        let (r0, r1) = glicko2(&ratings[i0], &ratings[i1], &outcome, &config);
-        ratings[i0] = r0;
-        ratings[i1] = r1;
-
-        let increment = 1.0 / (2.0 * rounds as f64);
-        if outcome == Outcomes::WIN {
-            *pairings
-                .entry((ais[i0].name.clone(), ais[i1].name.clone()))
-                .or_default() += increment;
-        } else if outcome == Outcomes::LOSS {
-            *pairings
-                .entry((ais[i1].name.clone(), ais[i0].name.clone()))
-                .or_default() += increment;
+
+        // A multi-player match is rated as the winner beating every other
+        // player and the remaining players drawing with each other.
+        let outcome = |i: usize, j: usize| match winner {
+            Some(w) if w == i => Outcomes::WIN,
+            Some(w) if w == j => Outcomes::LOSS,
+            _ => Outcomes::DRAW,
+        };
+        let new_ratings: Vec<Glicko2Rating> = indices
+            .iter()
+            .map(|&i| {
+                let results: Vec<(Glicko2Rating, Outcomes)> = indices
+                    .iter()
+                    .filter(|&&j| j != i)
+                    .map(|&j| (ratings[j], outcome(i, j)))
+                    .collect();
+                glicko2_rating_period(&ratings[i], &results, &config)
+            })
+            .collect();
+        for (&i, rating) in indices.iter().zip(new_ratings) {
+            ratings[i] = rating;
+        }
+
+        for &i in indices.iter() {
+            for &j in indices.iter() {
+                if i == j {
+                    continue;
+                }
+                let key = (ais[i].name.clone(), ais[j].name.clone());
+                *games.entry(key.clone()).or_default() += 1.0;
+                if winner == Some(i) {
+                    *wins.entry(key).or_default() += 1.0;
+                }
+            }
         }
     }
 
@@ -227,15 +299,15 @@ fn run_tournament(scenario_name: &str, ais: &[AI], rounds: i32) -> TournamentRes
     let mut win_matrix: Vec<f64> = vec![];
     for competitor in &competitors {
         for other_competitor in &competitors {
-            win_matrix.push(
-                pairings
-                    .get(&(
-                        competitor.username.clone(),
-                        other_competitor.username.clone(),
-                    ))
-                    .copied()
-                    .unwrap_or_default(),
+            let key = (
+                competitor.username.clone(),
+                other_competitor.username.clone(),
             );
+            let frac = match games.get(&key) {
+                Some(&n) => wins.get(&key).copied().unwrap_or_default() / n,
+                None => 0.0,
+            };
+            win_matrix.push(frac);
         }
     }
 
@@ -246,16 +318,16 @@ fn run_tournament(scenario_name: &str, ais: &[AI], rounds: i32) -> TournamentRes
     }
 }
 
-fn run_simulation(scenario_name: &str, seed: u32, ais: &[&AI]) -> Outcomes {
+// Returns the index of the winning AI, or None for a draw.
+fn run_simulation(scenario_name: &str, seed: u32, ais: &[&AI]) -> Option<usize> {
     let codes: Vec<_> = ais.iter().map(|x| x.compiled_code.clone()).collect();
     let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
     while sim.status() == scenario::Status::Running && sim.tick() < scenario::MAX_TICKS {
         sim.step();
     }
     match sim.status() {
-        scenario::Status::Victory { team: 0 } => Outcomes::WIN,
-        scenario::Status::Victory { team: 1 } => Outcomes::LOSS,
-        scenario::Status::Draw => Outcomes::DRAW,
+        scenario::Status::Victory { team } => Some(team as usize),
+        scenario::Status::Draw => None,
         _ => unreachable!(),
     }
 }
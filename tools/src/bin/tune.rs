@@ -2,6 +2,7 @@ use clap::Parser as _;
 use metaheuristics_nature::utility::prelude::*;
 use metaheuristics_nature::{Bounded, ObjFunc, Solver};
 use oort_simulator::simulation::Code;
+use oort_simulator::stats::MatchStats;
 use oort_simulator::{scenario, simulation};
 use std::cell::RefCell;
 use std::default::Default;
@@ -11,6 +12,14 @@ thread_local! {
   static COMPILERS: std::cell::RefCell<oort_compiler::Compiler> = RefCell::new(oort_compiler::Compiler::new());
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Fitness {
+    /// Time to win, with a large penalty for not winning.
+    Time,
+    /// Damage received divided by damage dealt.
+    DamageRatio,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("tune=info")).init();
@@ -32,6 +41,12 @@ async fn main() -> anyhow::Result<()> {
         #[clap(long)]
         wasm_cache: Option<PathBuf>,
 
+        #[clap(long)]
+        module_cache: Option<PathBuf>,
+
+        #[clap(short, long, value_enum, default_value = "time")]
+        fitness: Fitness,
+
         scenario_name: String,
         player_code: String,
         enemy_code: String,
@@ -43,11 +58,17 @@ async fn main() -> anyhow::Result<()> {
     let (initial_values, bounds) = extract_tunables(&player_src_code);
     assert!(!initial_values.is_empty());
 
+    let module_cache = args
+        .module_cache
+        .clone()
+        .or_else(oort_tools::default_module_cache_dir)
+        .and_then(oort_tools::ModuleCache::new);
     let ais = oort_tools::fetch_and_compile_multiple(
         &reqwest::Client::new(),
         &[args.player_code.clone(), args.enemy_code.clone()],
         false,
         args.wasm_cache.as_deref(),
+        module_cache.as_ref(),
     )
     .await?;
     let codes = ais
@@ -56,7 +77,12 @@ async fn main() -> anyhow::Result<()> {
         .collect::<Vec<_>>();
 
     log::info!("Running initial simulations");
-    let initial_fitness = run_simulations(&args.scenario_name, codes.clone(), args.num_seeds);
+    let initial_fitness = run_simulations(
+        &args.scenario_name,
+        codes.clone(),
+        args.num_seeds,
+        args.fitness,
+    );
     log::info!(
         "Initial fitness: {} for {:?}",
         initial_fitness,
@@ -76,6 +102,8 @@ async fn main() -> anyhow::Result<()> {
         bounds: bounds.to_vec(),
         enemy_code: codes[1].clone(),
         num_seeds: args.num_seeds,
+        fitness: args.fitness,
+        module_cache,
     };
 
     let pool = generate_pool(&initial_values);
@@ -117,8 +145,13 @@ struct ObjectiveFunction {
     bounds: Vec<[f64; 2]>,
     enemy_code: Code,
     num_seeds: u32,
+    fitness: Fitness,
+    module_cache: Option<oort_tools::ModuleCache>,
 }
 
+// Worse than any simulated result, so the solver discards the candidate.
+const FAILED_FITNESS: f64 = f64::MAX;
+
 impl Bounded for ObjectiveFunction {
     fn bound(&self) -> &[[f64; 2]] {
         self.bounds.as_slice()
@@ -134,10 +167,15 @@ impl ObjFunc for ObjectiveFunction {
         let player_src_code = rewrite_tunables(&self.player_src_code, x);
 
         let compile_start_time = std::time::Instant::now();
-        let player_code = if let Some(wasm) = compile("player code".to_string(), player_src_code) {
-            oort_simulator::vm::precompile(&wasm).unwrap()
-        } else {
-            panic!("Failed to compile player source code");
+        let Some(wasm) = compile("player code".to_string(), player_src_code) else {
+            return FAILED_FITNESS;
+        };
+        let player_code = match oort_tools::precompile(&wasm, self.module_cache.as_ref()) {
+            Ok(code) => code,
+            Err(e) => {
+                log::warn!("Failed to precompile player code: {}", e);
+                return FAILED_FITNESS;
+            }
         };
         let compile_duration = std::time::Instant::now() - compile_start_time;
 
@@ -146,6 +184,7 @@ impl ObjFunc for ObjectiveFunction {
             &self.scenario_name,
             vec![player_code, self.enemy_code.clone()],
             self.num_seeds,
+            self.fitness,
         );
         let sim_duration = std::time::Instant::now() - sim_start_time;
 
@@ -215,7 +254,7 @@ fn compile(name: String, code: String) -> Option<Vec<u8>> {
     })
 }
 
-fn run_simulations(scenario_name: &str, codes: Vec<Code>, num_seeds: u32) -> f64 {
+fn run_simulations(scenario_name: &str, codes: Vec<Code>, num_seeds: u32, fitness: Fitness) -> f64 {
     let reverse_codes = codes.iter().rev().cloned().collect::<Vec<_>>();
     (0..num_seeds)
         .into_par_iter()
@@ -223,19 +262,29 @@ fn run_simulations(scenario_name: &str, codes: Vec<Code>, num_seeds: u32) -> f64
         .map(|(seed, reverse)| {
             let codes = if reverse { &reverse_codes } else { &codes };
             let player_team = if reverse { 1 } else { 0 };
-            let (status, time) = run_simulation(scenario_name, seed, codes.clone());
-            match status {
-                scenario::Status::Victory { team } if team == player_team => time,
-                _ => 1e6,
+            let (status, time, stats) = run_simulation(scenario_name, seed, codes.clone());
+            match fitness {
+                Fitness::Time => match status {
+                    scenario::Status::Victory { team } if team == player_team => time,
+                    _ => 1e6,
+                },
+                Fitness::DamageRatio => {
+                    let team = stats.team(player_team);
+                    team.damage_received / team.damage_dealt.max(1.0)
+                }
             }
         })
         .sum()
 }
 
-fn run_simulation(scenario_name: &str, seed: u32, codes: Vec<Code>) -> (scenario::Status, f64) {
+fn run_simulation(
+    scenario_name: &str,
+    seed: u32,
+    codes: Vec<Code>,
+) -> (scenario::Status, f64, MatchStats) {
     let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
     while sim.status() == scenario::Status::Running && sim.tick() < scenario::MAX_TICKS {
         sim.step();
     }
-    (sim.status(), sim.time())
+    (sim.status(), sim.time(), sim.stats())
 }
@@ -1,4 +1,8 @@
+pub mod replay;
+
 use oort_compiler::Compiler;
+use oort_proto::compiler::CompilerDiagnostics;
+use sha2::{Digest, Sha256};
 use std::sync::Mutex;
 use std::{fs, path::Path, path::PathBuf};
 
@@ -15,8 +19,26 @@ pub async fn fetch_and_compile(
     shortcode: &str,
     dev: bool,
     wasm_cache: Option<&Path>,
+    module_cache: Option<&ModuleCache>,
 ) -> anyhow::Result<AI> {
     let name = shortcode.rsplit('/').next().unwrap().to_string();
+    let (source_code, wasm) = fetch_and_compile_wasm(http, shortcode, dev, wasm_cache).await?;
+    let compiled_code = precompile(&wasm, module_cache)?;
+
+    Ok(AI {
+        name,
+        source_code,
+        compiled_code,
+    })
+}
+
+// Returns the source code and unprocessed wasm for a shortcode or local file.
+pub async fn fetch_and_compile_wasm(
+    http: &reqwest::Client,
+    shortcode: &str,
+    dev: bool,
+    wasm_cache: Option<&Path>,
+) -> anyhow::Result<(String, Vec<u8>)> {
     let (compiler_url, shortcode_url) = if dev {
         ("http://localhost:8081", "http://localhost:8084")
     } else {
@@ -26,11 +48,7 @@ pub async fn fetch_and_compile(
     let wasm_cache = wasm_cache.and_then(|path| WasmCache::new(path.to_owned()));
     if let Some(wasm_cache) = wasm_cache.as_ref() {
         if let Some(wasm) = wasm_cache.get(shortcode) {
-            return Ok(AI {
-                name,
-                source_code: format!("// read from cache: {:?}", wasm_cache.path),
-                compiled_code: Code::Wasm(wasm),
-            });
+            return Ok((format!("// read from cache: {:?}", wasm_cache.path), wasm));
         }
     }
 
@@ -53,26 +71,19 @@ pub async fn fetch_and_compile(
         .await?;
 
     if !response.status().is_success() {
-        anyhow::bail!(
-            "Failed to compile {:?}: {:?}",
-            shortcode,
-            response.text().await?
-        );
+        let text = response.text().await?;
+        let diagnostics = serde_json::from_str::<CompilerDiagnostics>(&text)
+            .unwrap_or_else(|_| CompilerDiagnostics::from_message(&text));
+        anyhow::bail!("Failed to compile {:?}:\n{}", shortcode, diagnostics);
     }
 
-    let compiled_code = response.bytes().await?.to_vec();
+    let wasm = response.bytes().await?.to_vec();
 
     if let Some(wasm_cache) = wasm_cache {
-        wasm_cache.put(shortcode, &compiled_code);
+        wasm_cache.put(shortcode, &wasm);
     }
 
-    let compiled_code = oort_simulator::vm::precompile(&compiled_code).unwrap();
-
-    Ok(AI {
-        name,
-        source_code,
-        compiled_code,
-    })
+    Ok((source_code, wasm))
 }
 
 pub async fn fetch_and_compile_multiple(
@@ -80,10 +91,11 @@ pub async fn fetch_and_compile_multiple(
     shortcodes: &[String],
     dev: bool,
     wasm_cache: Option<&Path>,
+    module_cache: Option<&ModuleCache>,
 ) -> anyhow::Result<Vec<AI>> {
     let futures = shortcodes
         .iter()
-        .map(|shortcode| fetch_and_compile(http, shortcode, dev, wasm_cache));
+        .map(|shortcode| fetch_and_compile(http, shortcode, dev, wasm_cache, module_cache));
     let results = futures::future::join_all(futures).await;
     results.into_iter().collect()
 }
@@ -147,6 +159,123 @@ impl WasmCache {
     }
 }
 
+// Content-addressed cache of precompiled modules, keyed by a hash of the wasm
+// and the engine that compiled it. Entries are native code loaded without
+// validation, so the directory must only be writable by the current user.
+pub struct ModuleCache {
+    path: PathBuf,
+    engine: String,
+}
+
+impl ModuleCache {
+    pub fn new(path: PathBuf) -> Option<Self> {
+        if let Err(e) = create_private_dir(&path) {
+            log::warn!("Not using module cache {:?}: {}", path, e);
+            return None;
+        }
+        let mut engine = format!(
+            "{}-{}",
+            oort_simulator::vm::engine_version(),
+            oort_version::version()
+        );
+        // Local edits don't change the git version, so fall back to the
+        // binary timestamp to avoid loading modules from an older build.
+        if oort_version::built_info::GIT_DIRTY != Some(false) {
+            let binary_ts = match std::env::current_exe()
+                .and_then(fs::metadata)
+                .and_then(|x| x.modified())
+            {
+                Ok(ts) => ts,
+                Err(e) => {
+                    log::warn!("Not using module cache {:?}: {}", path, e);
+                    return None;
+                }
+            };
+            engine.push_str(&format!("-{:?}", binary_ts));
+        }
+        Some(Self { path, engine })
+    }
+
+    fn key(&self, wasm: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.engine.as_bytes());
+        hasher.update([0]);
+        hasher.update(wasm);
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&self, wasm: &[u8]) -> Option<Code> {
+        let path = self.path.join(format!("{}.bin", self.key(wasm)));
+        let bytes = fs::read(&path).ok()?;
+        log::debug!("Module cache hit for {:?}", path);
+        Some(Code::Precompiled(bytes.into()))
+    }
+
+    pub fn put(&self, wasm: &[u8], code: &Code) {
+        let Code::Precompiled(bytes) = code else {
+            return;
+        };
+        let key = self.key(wasm);
+        // Write to a temporary file first so concurrent readers never see a
+        // partially written module.
+        let tmp_path = self
+            .path
+            .join(format!("{}.{}.tmp", key, std::process::id()));
+        let path = self.path.join(format!("{key}.bin"));
+        if let Err(e) = fs::write(&tmp_path, bytes).and_then(|_| fs::rename(&tmp_path, path)) {
+            log::warn!("Failed to write to module cache: {:?}", e);
+            let _ = fs::remove_file(&tmp_path);
+        }
+    }
+
+    pub fn precompile(&self, wasm: &[u8]) -> anyhow::Result<Code> {
+        if let Some(code) = self.get(wasm) {
+            return Ok(code);
+        }
+        let code = oort_simulator::vm::precompile(wasm).map_err(|e| anyhow::anyhow!(e.msg))?;
+        self.put(wasm, &code);
+        Ok(code)
+    }
+}
+
+// The user's cache directory, e.g. ~/.cache/oort/modules.
+pub fn default_module_cache_dir() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))?;
+    Some(cache_home.join("oort").join("modules"))
+}
+
+fn create_private_dir(path: &Path) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(path)?;
+        // The directory may have existed already with looser permissions.
+        let metadata = fs::metadata(path)?;
+        if metadata.mode() & 0o022 != 0 {
+            anyhow::bail!("directory is writable by other users");
+        }
+        if metadata.uid() != unsafe { libc::geteuid() } {
+            anyhow::bail!("directory is owned by another user");
+        }
+    }
+    #[cfg(not(unix))]
+    fs::create_dir_all(path)?;
+    Ok(())
+}
+
+// Precompiles wasm, using the module cache if one is given.
+pub fn precompile(wasm: &[u8], module_cache: Option<&ModuleCache>) -> anyhow::Result<Code> {
+    match module_cache {
+        Some(module_cache) => module_cache.precompile(wasm),
+        None => oort_simulator::vm::precompile(wasm).map_err(|e| anyhow::anyhow!(e.msg)),
+    }
+}
+
 pub struct ParallelCompiler {
     sender: Mutex<std::sync::mpsc::Sender<Compiler>>,
     receiver: Mutex<std::sync::mpsc::Receiver<Compiler>>,
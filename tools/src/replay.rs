@@ -0,0 +1,225 @@
+use oort_simulator::scenario::{self, Status};
+use oort_simulator::simulation::{Code, Simulation};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+pub const FORMAT_VERSION: u32 = 1;
+pub const DEFAULT_CHECKPOINT_INTERVAL: u32 = 60;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReplayTeam {
+    pub name: String,
+    pub wasm_hash: String,
+    // Omitted for wasm when the replay only records hashes; the wasm must
+    // then be supplied separately when verifying.
+    pub code: Option<Code>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HashCheckpoint {
+    pub tick: u32,
+    pub hash: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Replay {
+    pub format_version: u32,
+    pub simulator_version: String,
+    pub scenario_name: String,
+    pub seed: u32,
+    pub teams: Vec<ReplayTeam>,
+    pub checkpoint_interval: u32,
+    pub checkpoints: Vec<HashCheckpoint>,
+    pub final_status: Status,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    Match,
+    Diverged {
+        // Last checkpoint that matched, if any.
+        last_good_tick: Option<u32>,
+        tick: u32,
+        expected: u64,
+        actual: u64,
+    },
+    StatusMismatch {
+        expected: Status,
+        actual: Status,
+    },
+}
+
+pub fn wasm_hash(code: &Code) -> anyhow::Result<String> {
+    let wasm = match code {
+        Code::Wasm(wasm) => wasm.clone(),
+        Code::Builtin(name) => match oort_simulator::vm::builtin::load_compiled(name) {
+            Ok(Code::Wasm(wasm)) => wasm,
+            Ok(_) => unreachable!(),
+            Err(e) => anyhow::bail!(e),
+        },
+        Code::None => return Ok(String::new()),
+        _ => anyhow::bail!("Replays require wasm or builtin code"),
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(&wasm);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn run(
+    scenario_name: &str,
+    seed: u32,
+    codes: &[Code],
+    checkpoint_interval: u32,
+    mut f: impl FnMut(HashCheckpoint) -> bool,
+) -> Status {
+    let mut sim = Simulation::new(scenario_name, seed, codes);
+    let interval = checkpoint_interval.max(1);
+    loop {
+        let running = sim.status() == Status::Running && sim.tick() < scenario::MAX_TICKS;
+        if sim.tick() % interval == 0 || !running {
+            let checkpoint = HashCheckpoint {
+                tick: sim.tick(),
+                hash: sim.hash(),
+            };
+            if !f(checkpoint) {
+                break;
+            }
+        }
+        if !running {
+            break;
+        }
+        sim.step();
+    }
+    sim.status()
+}
+
+pub fn record(
+    scenario_name: &str,
+    seed: u32,
+    teams: &[(String, Code)],
+    checkpoint_interval: u32,
+    embed_code: bool,
+) -> anyhow::Result<Replay> {
+    if scenario::load_safe(scenario_name).is_none() {
+        anyhow::bail!("Unknown scenario {:?}", scenario_name);
+    }
+
+    let replay_teams = teams
+        .iter()
+        .map(|(name, code)| {
+            Ok(ReplayTeam {
+                name: name.clone(),
+                wasm_hash: wasm_hash(code)?,
+                code: (embed_code || !matches!(code, Code::Wasm(_))).then(|| code.clone()),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let codes: Vec<Code> = teams.iter().map(|(_, code)| code.clone()).collect();
+    let mut checkpoints = vec![];
+    let final_status = run(scenario_name, seed, &codes, checkpoint_interval, |c| {
+        checkpoints.push(c);
+        true
+    });
+
+    Ok(Replay {
+        format_version: FORMAT_VERSION,
+        simulator_version: oort_version::version(),
+        scenario_name: scenario_name.to_string(),
+        seed,
+        teams: replay_teams,
+        checkpoint_interval,
+        checkpoints,
+        final_status,
+    })
+}
+
+impl Replay {
+    pub fn load(path: &Path) -> anyhow::Result<Replay> {
+        let replay: Replay = bincode::deserialize(&std::fs::read(path)?)?;
+        if replay.format_version != FORMAT_VERSION {
+            anyhow::bail!(
+                "Unsupported replay format version {} (expected {})",
+                replay.format_version,
+                FORMAT_VERSION
+            );
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, bincode::serialize(self)?)?;
+        Ok(())
+    }
+
+    // Finds the code for each team, using embedded code when present and
+    // otherwise matching the supplied wasm by hash.
+    pub fn resolve_codes(&self, available: &[Code]) -> anyhow::Result<Vec<Code>> {
+        let mut available_by_hash = std::collections::HashMap::new();
+        for code in available {
+            available_by_hash.insert(wasm_hash(code)?, code.clone());
+        }
+
+        self.teams
+            .iter()
+            .map(|team| {
+                if let Some(code) = team.code.as_ref() {
+                    return Ok(code.clone());
+                }
+                available_by_hash
+                    .get(&team.wasm_hash)
+                    .cloned()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "Missing wasm for team {:?} with hash {}",
+                            team.name,
+                            team.wasm_hash
+                        )
+                    })
+            })
+            .collect()
+    }
+
+    pub fn verify(&self, codes: &[Code]) -> Verification {
+        let mut expected = self.checkpoints.iter().peekable();
+        let mut last_good_tick = None;
+        let mut divergence = None;
+        let actual_status = run(
+            &self.scenario_name,
+            self.seed,
+            codes,
+            self.checkpoint_interval,
+            |actual| {
+                while expected.next_if(|c| c.tick < actual.tick).is_some() {}
+                match expected.peek() {
+                    Some(c) if c.tick == actual.tick => {
+                        if c.hash != actual.hash {
+                            divergence = Some(Verification::Diverged {
+                                last_good_tick,
+                                tick: actual.tick,
+                                expected: c.hash,
+                                actual: actual.hash,
+                            });
+                            return false;
+                        }
+                        last_good_tick = Some(actual.tick);
+                        true
+                    }
+                    _ => true,
+                }
+            },
+        );
+
+        if let Some(divergence) = divergence {
+            divergence
+        } else if actual_status != self.final_status {
+            Verification::StatusMismatch {
+                expected: self.final_status,
+                actual: actual_status,
+            }
+        } else {
+            Verification::Match
+        }
+    }
+}